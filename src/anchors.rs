@@ -0,0 +1,595 @@
+//! Anchors of links to headings, written the way the renderer writes heading IDs.
+
+use crate::book;
+use crate::config::{AnchorCase, AnchorConfig, AnchorOutput, AnchorValidation, LinkConfig, NonAsciiMode, Slugger, Transliteration};
+use crate::frontmatter;
+use crate::markdown::{self, FenceTracker};
+use crate::paths;
+use crate::warn;
+use anyhow::Result;
+use regex::Regex;
+use serde_json::Value;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::sync::LazyLock;
+
+
+/// Latin letters with accents and the ASCII letters they are written as.
+const TRANSLITERATIONS: [(&str, &str); 48] = [
+    ("àáâãäåāăą", "a"), ("ÀÁÂÃÄÅĀĂĄ", "A"), ("çćĉċč", "c"), ("ÇĆĈĊČ", "C"), ("ďđð", "d"), ("ĎĐÐ", "D"),
+    ("èéêëēĕėęě", "e"), ("ÈÉÊËĒĔĖĘĚ", "E"), ("ĝğġģ", "g"), ("ĜĞĠĢ", "G"), ("ĥħ", "h"), ("ĤĦ", "H"),
+    ("ìíîïĩīĭįı", "i"), ("ÌÍÎÏĨĪĬĮİ", "I"), ("ĵ", "j"), ("Ĵ", "J"), ("ķ", "k"), ("Ķ", "K"),
+    ("ĺļľŀł", "l"), ("ĹĻĽĿŁ", "L"), ("ñńņňŉ", "n"), ("ÑŃŅŇ", "N"), ("òóôõöøōŏő", "o"), ("ÒÓÔÕÖØŌŎŐ", "O"),
+    ("ŕŗř", "r"), ("ŔŖŘ", "R"), ("śŝşšș", "s"), ("ŚŜŞŠȘ", "S"), ("ţťŧț", "t"), ("ŢŤŦȚ", "T"),
+    ("ùúûüũūŭůűų", "u"), ("ÙÚÛÜŨŪŬŮŰŲ", "U"), ("ŵ", "w"), ("Ŵ", "W"), ("ýÿŷ", "y"), ("ÝŸŶ", "Y"),
+    ("źżž", "z"), ("ŹŻŽ", "Z"), ("ß", "ss"), ("ẞ", "SS"), ("æ", "ae"), ("Æ", "AE"),
+    ("œ", "oe"), ("Œ", "OE"), ("þ", "th"), ("Þ", "TH"), ("ĳ", "ij"), ("Ĳ", "IJ"),
+];
+
+
+/// Write the letters of a heading in ASCII.
+///
+/// Letters without an ASCII spelling, such as those of other scripts, are kept.
+///
+/// # Arguments
+/// - `heading` - The heading text, e.g. `Einführung`.
+/// - `scheme` - How letters are written.
+///
+/// # Returns
+/// The heading in ASCII letters, e.g. `Einfuhrung` or `Einfuehrung`.
+fn transliterate(heading: &str, scheme: Transliteration) -> String {
+    let mut ascii = String::with_capacity(heading.len());
+    for c in heading.chars() {
+        let german = match c {
+            'ä' => Some("ae"),
+            'ö' => Some("oe"),
+            'ü' => Some("ue"),
+            'Ä' => Some("Ae"),
+            'Ö' => Some("Oe"),
+            'Ü' => Some("Ue"),
+            _ => None,
+        };
+        match german.filter(|_| scheme == Transliteration::German) {
+            Some(letters) => ascii.push_str(letters),
+            None => match TRANSLITERATIONS.iter().find(|(letters, _)| letters.contains(c)) {
+                Some((_, letters)) => ascii.push_str(letters),
+                None => ascii.push(c),
+            },
+        }
+    }
+    ascii
+}
+
+
+/// Percent-encode the UTF-8 bytes of the characters `keep` returns false for.
+fn encode_bytes(anchor: &str, keep: impl Fn(char) -> bool) -> String {
+    let mut encoded = String::with_capacity(anchor.len());
+    for c in anchor.chars() {
+        if keep(c) {
+            encoded.push(c);
+        } else {
+            let mut bytes = [0; 4];
+            for byte in c.encode_utf8(&mut bytes).bytes() {
+                encoded.push_str(&format!("%{:02X}", byte));
+            }
+        }
+    }
+    encoded
+}
+
+
+/// Links (`[text](url)`) and wikilinks, with their display text as groups.
+static LINKS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!?\[\[([^\]|#]*)(?:#[^\]|]*)?(?:\|([^\]]*))?\]\]|!?\[([^\]]*)\]\([^)]*\)").unwrap());
+
+/// HTML tags.
+static TAGS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"</?[A-Za-z][^>]*>").unwrap());
+
+/// Emphasis with `*`, strikethrough and highlights.
+static STRONG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\*{1,3}([^*\s](?:[^*]*[^*\s])?)\*{1,3}|~~(.+?)~~|==(.+?)==").unwrap());
+
+/// Emphasis with `_`, which doesn't start or end inside words.
+static UNDERSCORES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(^|[^\p{L}\p{N}_])_{1,3}([^_\s](?:[^_]*[^_\s])?)_{1,3}([^\p{L}\p{N}_]|$)").unwrap());
+
+/// Backslash escapes.
+static ESCAPES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\([[:punct:]])").unwrap());
+
+/// The `id` attributes of HTML elements, like block anchors.
+static ELEMENT_IDS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"\bid="([^"]+)""#).unwrap());
+
+/// Links with an anchor, as Markdown links (target and anchor in groups 1 and
+/// 2) and as HTML `href`s (groups 3 and 4).
+static LINK_ANCHORS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r##"\]\(<?([^()\s#<>]*)#([^()\s<>]+)>?\)|href="([^"#]*)#([^"]+)""##).unwrap());
+
+/// Attributes at the end of a heading (`{#id .class}`).
+static ATTRIBUTES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\s*\{([^{}]*)\}$").unwrap());
+
+/// The characters GitHub and GitLab leave out of heading IDs.
+static NON_WORD: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[^\p{L}\p{M}\p{N}\p{Pc} -]").unwrap());
+
+
+/// A heading of a note and the ID the renderer gives it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Heading {
+    /// The heading level, 1 for `#`.
+    pub level: usize,
+    /// The slug of the heading text.
+    pub slug: String,
+    /// The slug with the suffix of repeated headings, e.g. `examples-1`.
+    pub id: String,
+}
+
+
+/// Get the text of a heading as the renderer shows it, without Markdown.
+///
+/// Code spans keep their content, links and wikilinks their display text,
+/// emphasis, strikethrough and highlights their text; HTML tags are removed.
+///
+/// # Examples
+/// ``The `run` command`` becomes `The run command`, `__Why__ [this](x.md)`
+/// becomes `Why this`
+///
+/// # Arguments
+/// - `heading` - The heading as written.
+///
+/// # Returns
+/// The plain text of the heading.
+fn heading_text(heading: &str) -> String {
+    if !heading.contains(['`', '*', '_', '~', '=', '[', '<', '\\']) {
+        return heading.to_string();
+    }
+    let mut text = String::new();
+    for (code, part) in markdown::code_spans(heading) {
+        if code {
+            // One space on both sides of the code is padding
+            let code = part.trim_matches('`');
+            let code = match code.strip_prefix(' ').and_then(|c| c.strip_suffix(' ')) {
+                Some(inner) if !inner.trim().is_empty() => inner,
+                _ => code,
+            };
+            text.push_str(code);
+            continue;
+        }
+        let part = LINKS.replace_all(part, |caps: &regex::Captures| {
+            caps.get(2).or(caps.get(1)).or(caps.get(3)).map_or("", |m| m.as_str()).to_string()
+        });
+        let part = TAGS.replace_all(&part, "");
+        let part = STRONG.replace_all(&part, "$1$2$3");
+        // Neighbouring emphasis shares the space between, which one match takes
+        let mut part = part.into_owned();
+        while let Cow::Owned(stripped) = UNDERSCORES.replace_all(&part, "${1}${2}${3}") {
+            part = stripped;
+        }
+        text.push_str(&ESCAPES.replace_all(&part, "$1"));
+    }
+    text
+}
+
+
+/// Remove the characters the renderer leaves out of heading IDs.
+///
+/// mdBook keeps letters and digits of all scripts, whitespace, `-` and `_`.
+/// GitHub and GitLab keep letters with their combining marks (so keycaps
+/// like `1️⃣` stay whole), digits, spaces, `-` and connector punctuation such
+/// as `_`. Punctuation, symbols and emoji are removed by all of them, while
+/// Obsidian Publish keeps everything, percent-encoded.
+///
+/// # Arguments
+/// - `heading` - The heading text.
+/// - `slugger` - The renderer.
+///
+/// # Returns
+/// The heading without the removed characters.
+fn strip_characters(heading: &str, slugger: Slugger) -> String {
+    match slugger {
+        Slugger::Mdbook => heading.chars().filter(|c| c.is_alphanumeric() || c.is_whitespace() || matches!(c, '-' | '_')).collect(),
+        Slugger::Github | Slugger::Gitlab => NON_WORD.replace_all(heading, "").into_owned(),
+        Slugger::ObsidianPublish => heading.to_string(),
+    }
+}
+
+
+/// Turn the text of a heading into the ID the renderer gives the heading.
+///
+/// # Examples
+/// `Getting Started` becomes `getting-started` with the `mdbook` slugger
+/// and `Getting+Started` with `obsidian-publish`
+///
+/// # Arguments
+/// - `heading` - The heading text, e.g. the section of `[[Note#Heading]]`.
+/// - `config` - The anchor settings.
+///
+/// # Returns
+/// The anchor without the `#`.
+pub fn slug(heading: &str, config: &AnchorConfig) -> String {
+    let mut heading = heading_text(heading).trim().to_string();
+    for rule in &config.rules {
+        heading = rule.find.replace_all(&heading, rule.replace.as_str()).into_owned();
+    }
+
+    // Encoded anchors keep all characters, slugs only those the renderer keeps
+    let encoded = config.output == AnchorOutput::Percent;
+    heading = match &config.strip {
+        _ if encoded => heading,
+        Some(re) => re.replace_all(&heading, "").into_owned(),
+        None => strip_characters(&heading, config.slugger),
+    };
+    if config.non_ascii == NonAsciiMode::Transliterate && !encoded {
+        heading = transliterate(&heading, config.transliteration);
+    }
+    let heading = match (config.case, config.slugger) {
+        (Some(AnchorCase::Lower), _) => heading.to_lowercase(),
+        (Some(AnchorCase::Upper), _) => heading.to_uppercase(),
+        (Some(AnchorCase::Keep), _) | (None, Slugger::ObsidianPublish) => heading,
+        (None, Slugger::Mdbook) => heading.to_ascii_lowercase(),
+        (None, Slugger::Github | Slugger::Gitlab) => heading.to_lowercase(),
+    };
+
+    let separator = config.separator.as_deref().unwrap_or(match config.slugger {
+        Slugger::ObsidianPublish => "+",
+        _ => "-",
+    });
+    let words: Vec<&str> = match config.slugger {
+        Slugger::Mdbook => heading.split(char::is_whitespace).collect(),
+        Slugger::Github | Slugger::Gitlab => heading.split(' ').collect(),
+        Slugger::ObsidianPublish => heading.split_whitespace().collect(),
+    };
+    let words: Vec<String> = match (encoded, config.slugger) {
+        (true, _) => words.iter().map(|word| encode_bytes(word, |c| c.is_ascii_alphanumeric() || "-._~".contains(c))).collect(),
+        (false, Slugger::ObsidianPublish) => words.iter().map(|word| paths::percent_encode(word)).collect(),
+        (false, _) => words.iter().map(|word| word.to_string()).collect(),
+    };
+    let slug = words.join(separator);
+    if encoded {
+        return slug;
+    }
+
+    // `Foo - Bar` gives `foo---bar`, unless runs of hyphens are collapsed
+    let slug = match config.collapse_hyphens.unwrap_or(config.slugger == Slugger::Gitlab) {
+        true => {
+            let mut collapsed = String::with_capacity(slug.len());
+            for c in slug.chars() {
+                if !(c == '-' && collapsed.ends_with('-')) {
+                    collapsed.push(c);
+                }
+            }
+            collapsed
+        }
+        false => slug,
+    };
+    match config.non_ascii {
+        NonAsciiMode::Encode => encode_bytes(&slug, |c| c.is_ascii()),
+        _ => slug,
+    }
+}
+
+
+/// Find the anchor of a heading in a note.
+///
+/// Repeated headings get the suffixes the renderer gives them, so the second
+/// `Examples` is `examples-1`. Obsidian links to a later one through the
+/// headings above it, as in `[[Note#Part 2#Examples]]`.
+///
+/// # Arguments
+/// - `section` - The heading or path of headings after the first `#`.
+/// - `headings` - The headings of the note, if known.
+/// - `config` - The anchor settings.
+///
+/// # Returns
+/// The anchor without the `#`; the slug of the last heading if the note or
+/// the heading isn't found.
+pub fn heading_anchor(section: &str, headings: Option<&[Heading]>, config: &AnchorConfig) -> String {
+    let mut parts: Vec<String> = section.split('#').filter(|part| !part.trim().is_empty()).map(|part| slug(part, config)).collect();
+    let Some(target) = parts.pop() else {
+        return slug(section, config);
+    };
+    let Some(headings) = headings else {
+        return target;
+    };
+
+    let mut parents: Vec<&Heading> = Vec::new();
+    for heading in headings {
+        // The parents named in the link have to be above the heading, in order
+        parents.retain(|parent| parent.level < heading.level);
+        let mut above = parents.iter().map(|parent| &parent.slug);
+        if heading.slug == target && parts.iter().all(|part| above.any(|parent| parent == part)) {
+            return heading.id.clone();
+        }
+        parents.push(heading);
+    }
+    target
+}
+
+
+/// Get the IDs the renderer gives the headings of a note.
+///
+/// Headings with an ID in their attributes (`## Setup {#install}`) get it.
+///
+/// # Arguments
+/// - `content` - The content of the note.
+/// - `config` - The anchor settings.
+///
+/// # Returns
+/// The headings outside code, in order.
+pub fn heading_ids(content: &str, config: &AnchorConfig) -> Vec<Heading> {
+    let mut fences = FenceTracker::default();
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    let mut ids = Vec::new();
+    for line in frontmatter::split(content).1.lines() {
+        if fences.is_code(line) {
+            continue;
+        }
+        let Some((level, text)) = markdown::heading(line) else { continue };
+
+        // mdBook takes the ID from the heading's attributes (`{#id .class}`)
+        let (text, custom) = match ATTRIBUTES.captures(text) {
+            Some(caps) => {
+                let custom = caps[1].split_whitespace().find_map(|attribute| attribute.strip_prefix('#')).map(str::to_string);
+                (&text[..caps.get(0).unwrap().start()], custom)
+            }
+            None => (text, None),
+        };
+        let slug = slug(text, config);
+        if let Some(id) = custom {
+            ids.push(Heading { level, slug, id });
+            continue;
+        }
+        let count = counts.entry(slug.clone()).or_insert(0);
+        let id = match *count {
+            0 => slug.clone(),
+            _ if config.slugger == Slugger::ObsidianPublish => slug.clone(),
+            n => format!("{}-{}", slug, n),
+        };
+        *count += 1;
+        ids.push(Heading { level, slug, id });
+    }
+    ids
+}
+
+
+/// Check that the anchors of links between chapters match a heading.
+///
+/// Links to other chapters and within a chapter are checked against the IDs
+/// of the target chapter's headings and of its elements with an `id`, like
+/// block anchors. Links to URLs and files outside the book are skipped.
+///
+/// # Arguments
+/// - `book` - The processed book.
+/// - `config` - The link settings.
+///
+/// # Returns
+/// An error naming the links if the validation mode is `error`.
+pub fn check_anchors(book: &Value, config: &LinkConfig) -> Result<()> {
+    if config.anchors.validate == AnchorValidation::Off {
+        return Ok(());
+    }
+
+    // The IDs of a chapter are only collected once a link points to it
+    let chapters: Vec<(&str, &str)> = book::chapters(book)
+        .into_iter()
+        .filter_map(|chapter| Some((chapter.get("path")?.as_str()?, chapter.get("content")?.as_str()?)))
+        .collect();
+    let mut ids: HashMap<&str, (&str, Option<HashSet<String>>)> = chapters
+        .iter()
+        .map(|(path, content)| (paths::strip_note_extension(path), (*content, None)))
+        .collect();
+
+    let extension = config.note_extension();
+    let mut broken = Vec::new();
+    for (path, content) in &chapters {
+        for (_, text) in markdown::split_protected(content).into_iter().filter(|(protected, _)| !protected) {
+            for caps in LINK_ANCHORS.captures_iter(text) {
+                let (target, anchor) = match caps.get(1) {
+                    Some(target) => (target.as_str(), &caps[2]),
+                    None => (&caps[3], &caps[4]),
+                };
+                if paths::is_url(target) {
+                    continue;
+                }
+                let chapter = match paths::percent_decode(target) {
+                    target if target.is_empty() => paths::strip_note_extension(path).to_string(),
+                    target => match target.strip_suffix(&extension).or_else(|| paths::is_note_file(&target).then(|| paths::strip_note_extension(&target))) {
+                        Some(stem) => paths::join(path, stem),
+                        None => continue,
+                    },
+                };
+                let Some((content, ids)) = ids.get_mut(chapter.as_str()) else { continue };
+                let ids = ids.get_or_insert_with(|| {
+                    let mut ids: HashSet<String> = heading_ids(content, &config.anchors).into_iter().map(|heading| heading.id).collect();
+                    ids.extend(ELEMENT_IDS.captures_iter(content).map(|caps| caps[1].to_string()));
+                    ids
+                });
+                if !ids.contains(anchor) && !ids.contains(&paths::percent_decode(anchor)) {
+                    broken.push(format!("'{}#{}' in '{}'", target, anchor, path));
+                }
+            }
+        }
+    }
+
+    if config.anchors.validate == AnchorValidation::Error && !broken.is_empty() {
+        anyhow::bail!("Links to missing headings: {}", broken.join(", "));
+    }
+    for link in &broken {
+        warn(&format!("Link {} doesn't match any heading", link));
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AnchorRule;
+
+    fn slug_with(heading: &str, slugger: Slugger) -> String {
+        slug(heading, &AnchorConfig { slugger, ..Default::default() })
+    }
+
+    #[test]
+    fn test_default_slugger() {
+        let config = AnchorConfig::default();
+        assert_eq!(slug("Test test", &config), "test-test");
+        assert_eq!(slug("Hello World Example", &config), "hello-world-example");
+        assert_eq!(slug("UPPERCASE", &config), "uppercase");
+    }
+
+    #[test]
+    fn test_punctuation() {
+        assert_eq!(slug_with("What's New?", Slugger::Mdbook), "whats-new");
+        assert_eq!(slug_with("Setup: Linux & macOS", Slugger::Mdbook), "setup-linux--macos");
+        assert_eq!(slug_with("„Zitat“ – mit ‹Zeichen›…", Slugger::Mdbook), "zitat--mit-zeichen");
+        assert_eq!(slug_with("C++ vs. C#", Slugger::Github), "c-vs-c");
+        assert_eq!(slug_with("Setup: Linux & macOS", Slugger::Gitlab), "setup-linux-macos");
+        assert_eq!(slug_with("a‿b (c)", Slugger::Github), "a‿b-c");
+        assert_eq!(slug_with("a‿b (c)", Slugger::Mdbook), "ab-c");
+        assert_eq!(slug_with("What's New?", Slugger::ObsidianPublish), "What's+New%3F");
+    }
+
+    #[test]
+    fn test_collapse_hyphens() {
+        let collapse = |slugger, collapse_hyphens| AnchorConfig { slugger, collapse_hyphens: Some(collapse_hyphens), ..Default::default() };
+        assert_eq!(slug("Foo - Bar", &collapse(Slugger::Mdbook, true)), "foo-bar");
+        assert_eq!(slug("Setup: Linux & macOS", &collapse(Slugger::Github, true)), "setup-linux-macos");
+        assert_eq!(slug("Foo - Bar", &collapse(Slugger::Gitlab, false)), "foo---bar");
+        assert_eq!(slug("Foo - Bar", &AnchorConfig::default()), "foo---bar");
+    }
+
+    #[test]
+    fn test_non_ascii() {
+        let config = |non_ascii, transliteration| AnchorConfig { non_ascii, transliteration, ..Default::default() };
+        assert_eq!(slug("Einführung", &config(NonAsciiMode::Keep, Transliteration::Ascii)), "einführung");
+        assert_eq!(slug("Einführung", &config(NonAsciiMode::Encode, Transliteration::Ascii)), "einf%C3%BChrung");
+        assert_eq!(slug("Einführung", &config(NonAsciiMode::Transliterate, Transliteration::Ascii)), "einfuhrung");
+        assert_eq!(slug("Über Straßen", &config(NonAsciiMode::Transliterate, Transliteration::German)), "ueber-strassen");
+        assert_eq!(slug("Çağrı 日本", &config(NonAsciiMode::Transliterate, Transliteration::German)), "cagri-日本");
+    }
+
+    #[test]
+    fn test_scripts_and_emoji() {
+        assert_eq!(slug_with("日本語の入門", Slugger::Mdbook), "日本語の入門");
+        assert_eq!(slug_with("快速开始：安装", Slugger::Mdbook), "快速开始安装");
+        assert_eq!(slug_with("日本語　入門", Slugger::Mdbook), "日本語-入門");
+        assert_eq!(slug_with("日本語　入門", Slugger::Github), "日本語入門");
+        assert_eq!(slug_with("한국어 문서", Slugger::Github), "한국어-문서");
+        assert_eq!(slug_with("Русский Текст", Slugger::Mdbook), "Русский-Текст");
+        assert_eq!(slug_with("Русский Текст", Slugger::Github), "русский-текст");
+        assert_eq!(slug_with("🚀 Launch", Slugger::Mdbook), "-launch");
+        assert_eq!(slug_with("🚀 Launch", Slugger::Github), "-launch");
+        assert_eq!(slug_with("Team 👩‍💻 Setup", Slugger::Gitlab), "team-setup");
+        assert_eq!(slug_with("1️⃣ Step", Slugger::Mdbook), "1-step");
+        assert_eq!(slug_with("1️⃣ Step", Slugger::Github), "1\u{fe0f}\u{20e3}-step");
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let config = AnchorConfig {
+            rules: vec![
+                AnchorRule { find: Regex::new(r"C\+\+").unwrap(), replace: "cpp".to_string() },
+                AnchorRule { find: Regex::new("&").unwrap(), replace: "and".to_string() },
+            ],
+            ..Default::default()
+        };
+        assert_eq!(slug("C++ & Rust", &config), "cpp-and-rust");
+
+        let config = AnchorConfig {
+            strip: Some(Regex::new(r"[^\w\s.]").unwrap()),
+            separator: Some("_".to_string()),
+            case: Some(AnchorCase::Keep),
+            ..Default::default()
+        };
+        assert_eq!(slug("Version 2.0 (Beta)!", &config), "Version_2.0_Beta");
+        let config = AnchorConfig { slugger: Slugger::ObsidianPublish, case: Some(AnchorCase::Lower), ..Default::default() };
+        assert_eq!(slug("Getting Started", &config), "getting+started");
+        let config = AnchorConfig { case: Some(AnchorCase::Upper), separator: Some(String::new()), ..Default::default() };
+        assert_eq!(slug("Über uns", &config), "ÜBERUNS");
+    }
+
+    #[test]
+    fn test_percent_output() {
+        let config = AnchorConfig { output: AnchorOutput::Percent, ..Default::default() };
+        assert_eq!(slug("What's New?", &config), "what%27s-new%3F");
+        assert_eq!(slug("Einführung: `run`", &config), "einf%C3%BChrung%3A-run");
+        assert_eq!(slug("Foo - Bar", &config), "foo---bar");
+
+        let config = AnchorConfig { slugger: Slugger::ObsidianPublish, ..config };
+        assert_eq!(slug("Über (uns)", &config), "%C3%9Cber+%28uns%29");
+        assert_eq!(heading_anchor("Über (uns)", Some(&heading_ids("# Über (uns)\n\n# Über (uns)", &config)), &config), "%C3%9Cber+%28uns%29");
+    }
+
+    #[test]
+    fn test_heading_text() {
+        assert_eq!(heading_text("The `run` command"), "The run command");
+        assert_eq!(heading_text("Using `` `ticks` ``"), "Using `ticks`");
+        assert_eq!(heading_text("**Bold**, *italic* and __also__ _this_"), "Bold, italic and also this");
+        assert_eq!(heading_text("snake_case and `snake_case`"), "snake_case and snake_case");
+        assert_eq!(heading_text("See [the guide](guide.md) and [[Setup|setup]] or [[Install#Linux]]"), "See the guide and setup or Install");
+        assert_eq!(heading_text("Press <kbd>Ctrl</kbd> ~~now~~ ==here== \\*"), "Press Ctrl now here *");
+
+        let config = AnchorConfig::default();
+        assert_eq!(slug("The `run` command", &config), "the-run-command");
+        assert_eq!(slug("The _run_ command", &config), "the-run-command");
+        assert_eq!(slug("Why `__init__`?", &config), "why-__init__");
+    }
+
+    #[test]
+    fn test_heading_anchor() {
+        let note = "# Guide\n\n## Part 1\n\n### Examples\n\n```\n# Examples\n```\n\n## Part 2\n\n### Examples\n\n## Examples";
+        let config = AnchorConfig::default();
+        let headings = heading_ids(note, &config);
+        let note = Some(headings.as_slice());
+        assert_eq!(heading_anchor("Examples", note, &config), "examples");
+        assert_eq!(heading_anchor("Part 2#Examples", note, &config), "examples-1");
+        assert_eq!(heading_anchor("Guide#Part 2#Examples", note, &config), "examples-1");
+        assert_eq!(heading_anchor("Part 1#Part 2#Examples", note, &config), "examples");
+        assert_eq!(heading_anchor("Part 2#Examples", None, &config), "examples");
+        assert_eq!(heading_anchor("Missing", note, &config), "missing");
+    }
+
+    #[test]
+    fn test_check_anchors() {
+        let book = serde_json::json!({"items": [
+            {"Chapter": {"path": "intro.md", "content": "# Intro\n\n[Setup](guide/setup.md#linux) [Old](guide/setup.md#windows) [Top](#intro)\n\n`[x](#nowhere)`"}},
+            {"Chapter": {"path": "guide/setup.md", "content": "# Setup\n\n## Linux\n\nText <span id=\"step\"></span>\n\n[Step](#step) [Back](../intro.md#intro) [Site](https://example.com#gone)"}},
+        ]});
+        let with_mode = |validate| LinkConfig { anchors: AnchorConfig { validate, ..Default::default() }, ..Default::default() };
+        let error = check_anchors(&book, &with_mode(AnchorValidation::Error)).unwrap_err().to_string();
+        assert_eq!(error, "Links to missing headings: 'guide/setup.md#windows' in 'intro.md'");
+        assert!(check_anchors(&book, &with_mode(AnchorValidation::Warn)).is_ok());
+
+        // Only the processed chapter counts, not the note it was made from
+        let book = serde_json::json!({"items": [
+            {"Chapter": {"path": "note.md", "source_path": "note.md", "content": "Plain heading text\n\n[Old](#old-heading)"}},
+        ]});
+        let error = check_anchors(&book, &with_mode(AnchorValidation::Error)).unwrap_err().to_string();
+        assert_eq!(error, "Links to missing headings: '#old-heading' in 'note.md'");
+    }
+
+    #[test]
+    fn test_heading_attributes() {
+        let config = AnchorConfig::default();
+        let note = "## Setup {#install .wide}\n\n## Setup\n\n## Usage {.wide}";
+        let headings = heading_ids(note, &config);
+        let ids: Vec<&str> = headings.iter().map(|heading| heading.id.as_str()).collect();
+        assert_eq!(ids, ["install", "setup", "usage"]);
+        assert_eq!(heading_anchor("Setup", Some(&headings), &config), "install");
+
+        let book = serde_json::json!({"items": [
+            {"Chapter": {"path": "setup.md", "source_path": "setup.md", "content": "## Setup {#install}\n\n[here](#install)"}},
+        ]});
+        let config = LinkConfig { anchors: AnchorConfig { validate: AnchorValidation::Error, ..Default::default() }, ..Default::default() };
+        assert!(check_anchors(&book, &config).is_ok());
+    }
+
+    #[test]
+    fn test_sluggers() {
+        assert_eq!(slug_with("Getting Started", Slugger::Mdbook), "getting-started");
+        assert_eq!(slug_with("snake_case Names", Slugger::Mdbook), "snake_case-names");
+        assert_eq!(slug_with("Über Uns", Slugger::Mdbook), "Über-uns");
+        assert_eq!(slug_with("Über Uns", Slugger::Github), "über-uns");
+        assert_eq!(slug_with("Foo - Bar", Slugger::Github), "foo---bar");
+        assert_eq!(slug_with("Foo - Bar", Slugger::Gitlab), "foo-bar");
+        assert_eq!(slug_with("Getting  Started (v2)", Slugger::ObsidianPublish), "Getting+Started+%28v2%29");
+    }
+}
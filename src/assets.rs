@@ -0,0 +1,80 @@
+//! Copying of attachments kept outside the src directory into the book.
+
+use crate::vault::Vault;
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::Path;
+
+
+/// Returns true if `dest` is missing or older than `source`.
+///
+/// # Arguments
+/// - `source` - The original file.
+/// - `dest` - The copy inside the src directory.
+///
+/// # Returns
+/// Whether the file has to be copied.
+fn is_stale(source: &Path, dest: &Path) -> bool {
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    match (modified(source), modified(dest)) {
+        (Some(source), Some(dest)) => source > dest,
+        _ => true,
+    }
+}
+
+
+/// Copy all external attachments used by the book into the src directory.
+///
+/// Up-to-date copies are left alone, so `mdbook serve` doesn't rebuild forever
+/// because the preprocessor touched files in src.
+///
+/// # Arguments
+/// - `vault` - The vault that recorded which attachments were used.
+/// - `src_dir` - The book's source directory.
+///
+/// # Returns
+/// The number of copied files, or an I/O error.
+pub fn copy_used_assets(vault: &Vault, src_dir: &Path) -> Result<usize> {
+    let mut copied = 0;
+    for (path, source) in vault.used_external_assets() {
+        let dest = src_dir.join(&path);
+        if !is_stale(&source, &dest) {
+            continue;
+        }
+        if let Some(parent) = dest.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::copy(&source, &dest)
+            .with_context(|| format!("Failed to copy '{}' to '{}'", source.display(), dest.display()))?;
+        copied += 1;
+    }
+    Ok(copied)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_used_assets() {
+        let dir = std::env::temp_dir().join(format!("obsidian-links-assets-{}", std::process::id()));
+        let attachments = dir.join("vault/attachments");
+        let src = dir.join("book/src");
+        fs::create_dir_all(attachments.join("sub")).unwrap();
+        fs::create_dir_all(&src).unwrap();
+        fs::write(attachments.join("sub/used.png"), "png").unwrap();
+        fs::write(attachments.join("unused.png"), "png").unwrap();
+
+        let mut vault = Vault::load(&src).unwrap();
+        vault.load_external(&attachments, "attachments").unwrap();
+        assert_eq!(vault.asset("used.png"), Some("attachments/sub/used.png"));
+
+        assert_eq!(copy_used_assets(&vault, &src).unwrap(), 1);
+        assert!(src.join("attachments/sub/used.png").is_file());
+        assert!(!src.join("attachments/unused.png").exists());
+        assert_eq!(copy_used_assets(&vault, &src).unwrap(), 0);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,95 @@
+//! Anchors for Obsidian block IDs (`^block-id`).
+
+use crate::markdown::{self, FenceTracker};
+use regex::Regex;
+use std::sync::LazyLock;
+
+
+/// A block ID marker at the end of a line, with the space before it and the ID as groups.
+static BLOCK_ID: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(^|\s)\^([A-Za-z0-9-]+)[ \t]*$").unwrap());
+
+
+/// Replace block ID markers outside of code with empty anchors.
+///
+/// A trailing ` ^id` becomes `<span id="id"></span>` at the end of its line.
+/// An ID on a line of its own marks the block above it, e.g. a table, and
+/// becomes an anchor on its own line.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+///
+/// # Returns
+/// The content with block anchors.
+pub fn convert_block_ids(content: &str) -> String {
+    replace_block_ids(content, |space, id| format!("{}<span id=\"{}\"></span>", space, id))
+}
+
+
+/// Remove block ID markers outside of code, e.g. from the copy of a block
+/// that is embedded elsewhere, so its ID stays unique.
+///
+/// # Arguments
+/// - `content` - The content.
+///
+/// # Returns
+/// The content without block ID markers.
+pub fn remove_block_ids(content: &str) -> String {
+    replace_block_ids(content, |_, _| String::new())
+}
+
+
+/// Replace block ID markers outside of code.
+///
+/// # Arguments
+/// - `content` - The content.
+/// - `replace` - Builds the replacement from the space before the marker
+///   (empty for an ID on a line of its own) and the ID.
+///
+/// # Returns
+/// The content with the markers replaced.
+fn replace_block_ids(content: &str, replace: impl Fn(&str, &str) -> String) -> String {
+    let mut fences = FenceTracker::default();
+
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| {
+            if fences.is_code(line) {
+                return line.to_string();
+            }
+            // Only markers after the last code span are block IDs
+            let parts = markdown::code_spans(line);
+            match parts.last() {
+                Some((false, last)) => {
+                    let converted = BLOCK_ID.replace(last, |caps: &regex::Captures| {
+                        let space = if caps[1].is_empty() { "" } else { " " };
+                        replace(space, &caps[2])
+                    });
+                    let head: String = parts[..parts.len() - 1].iter().map(|(_, part)| *part).collect();
+                    format!("{}{}", head, converted)
+                }
+                _ => line.to_string(),
+            }
+        })
+        .collect();
+    lines.join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_block_ids() {
+        let input = "Some paragraph ^quote1\n\n| a |\n|---|\n\n^table-1\n\n`x ^code`\n```\nfenced ^fence\n```\n2^10";
+        assert_eq!(
+            convert_block_ids(input),
+            "Some paragraph <span id=\"quote1\"></span>\n\n| a |\n|---|\n\n<span id=\"table-1\"></span>\n\n\
+             `x ^code`\n```\nfenced ^fence\n```\n2^10"
+        );
+        assert_eq!(
+            remove_block_ids(input),
+            "Some paragraph\n\n| a |\n|---|\n\n\n\n`x ^code`\n```\nfenced ^fence\n```\n2^10"
+        );
+    }
+}
@@ -0,0 +1,148 @@
+//! Helpers for reading and extending the Book JSON sent by mdBook.
+
+use serde_json::{Value, json};
+
+
+/// Collect all chapters of the book, depth-first in book order.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+///
+/// # Returns
+/// The `Chapter` objects of all items and their sub-items.
+pub fn chapters(book: &Value) -> Vec<&Value> {
+    fn collect<'a>(items: &'a Value, chapters: &mut Vec<&'a Value>) {
+        for item in items.as_array().into_iter().flatten() {
+            if let Some(chapter) = item.get("Chapter") {
+                chapters.push(chapter);
+                if let Some(sub_items) = chapter.get("sub_items") {
+                    collect(sub_items, chapters);
+                }
+            }
+        }
+    }
+
+    let mut chapters = Vec::new();
+    if let Some(items) = book.get("items") {
+        collect(items, &mut chapters);
+    }
+    chapters
+}
+
+
+/// Remove the chapters for which `keep` returns false, including their sub-items.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `keep` - Decides whether a `Chapter` object stays in the book.
+///
+/// # Returns
+/// The number of removed chapters, not counting their sub-items.
+pub fn retain_chapters(book: &mut Value, keep: &impl Fn(&Value) -> bool) -> usize {
+    fn retain(items: &mut Value, keep: &impl Fn(&Value) -> bool) -> usize {
+        let Some(array) = items.as_array_mut() else { return 0 };
+        let before = array.len();
+        array.retain(|item| item.get("Chapter").is_none_or(keep));
+        let mut removed = before - array.len();
+        for chapter in array.iter_mut().filter_map(|item| item.get_mut("Chapter")) {
+            if let Some(sub_items) = chapter.get_mut("sub_items") {
+                removed += retain(sub_items, keep);
+            }
+        }
+        removed
+    }
+
+    book.get_mut("items").map_or(0, |items| retain(items, keep))
+}
+
+
+/// Call `visit` for every chapter of the book, depth-first in book order.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `visit` - Called with every `Chapter` object.
+pub fn for_each_chapter(book: &mut Value, visit: &mut impl FnMut(&mut Value)) {
+    fn walk(items: &mut Value, visit: &mut impl FnMut(&mut Value)) {
+        for item in items.as_array_mut().into_iter().flatten() {
+            if let Some(chapter) = item.get_mut("Chapter") {
+                visit(chapter);
+                if let Some(sub_items) = chapter.get_mut("sub_items") {
+                    walk(sub_items, visit);
+                }
+            }
+        }
+    }
+
+    if let Some(items) = book.get_mut("items") {
+        walk(items, visit);
+    }
+}
+
+
+/// Append a generated, unnumbered chapter to the end of the book.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `name` - The chapter title shown in the sidebar.
+/// - `path` - The chapter path relative to the src directory.
+/// - `content` - The Markdown content of the chapter.
+pub fn push_chapter(book: &mut Value, name: &str, path: &str, content: String) {
+    let chapter = json!({ "Chapter": {
+        "name": name,
+        "content": content,
+        "number": null,
+        "sub_items": [],
+        "path": path,
+        "source_path": null,
+        "parent_names": [],
+    } });
+
+    if let Some(items) = book.get_mut("items").and_then(|i| i.as_array_mut()) {
+        items.push(chapter);
+    }
+}
+
+
+/// Append a part title to the end of the book.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `title` - The title of the part.
+pub fn push_part(book: &mut Value, title: &str) {
+    if let Some(items) = book.get_mut("items").and_then(|i| i.as_array_mut()) {
+        items.push(json!({ "PartTitle": title }));
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_chapters_and_push_chapter() {
+        let mut book = json!({ "items": [
+            { "Chapter": { "name": "A", "sub_items": [{ "Chapter": { "name": "B", "sub_items": [] } }] } },
+            "Separator",
+        ] });
+        push_part(&mut book, "Generated");
+        push_chapter(&mut book, "C", "c.md", "# C".to_string());
+
+        let names: Vec<&str> = chapters(&book).iter().map(|c| c["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["A", "B", "C"]);
+        assert_eq!(chapters(&book)[2]["path"], "c.md");
+        assert_eq!(book["items"][2]["PartTitle"], "Generated");
+    }
+
+    #[test]
+    fn test_retain_chapters() {
+        let mut book = json!({ "items": [
+            { "Chapter": { "name": "A", "sub_items": [{ "Chapter": { "name": "B", "sub_items": [] } }] } },
+            { "Chapter": { "name": "C", "sub_items": [{ "Chapter": { "name": "D", "sub_items": [] } }] } },
+        ] });
+        assert_eq!(retain_chapters(&mut book, &|c: &Value| c["name"] != "B" && c["name"] != "C"), 2);
+
+        let names: Vec<&str> = chapters(&book).iter().map(|c| c["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["A"]);
+    }
+}
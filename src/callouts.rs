@@ -0,0 +1,369 @@
+//! Conversion of Obsidian callouts (`> [!note] Title`).
+
+use crate::config::{CalloutConfig, CalloutMode};
+use crate::markdown::FenceTracker;
+use regex::Regex;
+use std::sync::LazyLock;
+
+
+/// The first line of a callout (`[!note]- Title`) without its `>`.
+static HEADER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\[!([\w-]+)\]([+-]?)(?:\s+(.*))?$").unwrap());
+
+/// Markdown links (`[text](url)`) in callout titles.
+static TITLE_LINKS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\]]*)\]\(([^)\s]*)\)").unwrap());
+
+
+/// A parsed callout block.
+#[derive(Debug, PartialEq)]
+struct Callout<'a> {
+    /// The callout type as written, e.g. `note` or `tldr`.
+    kind: String,
+    /// The title after the type, possibly empty.
+    title: &'a str,
+    /// Whether the callout is foldable (`+` or `-`), and if so, whether it starts folded.
+    folded: Option<bool>,
+    /// The body lines with one level of quoting removed.
+    body: Vec<&'a str>,
+}
+
+
+/// Remove one level of blockquote markers from a line.
+///
+/// # Arguments
+/// - `line` - The line to unquote.
+///
+/// # Returns
+/// The line without `>` and the following space, or `None` if it isn't quoted.
+fn unquote(line: &str) -> Option<&str> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+    let rest = trimmed.strip_prefix('>')?;
+    Some(rest.strip_prefix(' ').unwrap_or(rest))
+}
+
+
+/// Parse the callout starting at `lines[0]`.
+///
+/// # Arguments
+/// - `lines` - The remaining lines of the document.
+///
+/// # Returns
+/// The callout and the number of lines it spans, or `None` if no callout starts here.
+fn parse_callout<'a>(lines: &[&'a str]) -> Option<(Callout<'a>, usize)> {
+    let header = unquote(lines[0])?;
+    let caps = HEADER.captures(header.trim_end())?;
+
+    let body: Vec<&str> = lines[1..].iter().map_while(|line| unquote(line)).collect();
+    let callout = Callout {
+        kind: caps[1].to_lowercase(),
+        title: caps.get(3).map_or("", |m| m.as_str().trim()),
+        folded: match &caps[2] {
+            "-" => Some(true),
+            "+" => Some(false),
+            _ => None,
+        },
+        body,
+    };
+    let len = callout.body.len() + 1;
+    Some((callout, len))
+}
+
+
+/// The bundled stylesheet for callouts rendered as HTML.
+pub const CALLOUT_CSS: &str = include_str!("callouts.css");
+
+
+/// Map an Obsidian callout type to its canonical type.
+///
+/// The canonical types are the same as mdbook-admonish's directives.
+/// Types configured in `callouts.types` take precedence over the built-in
+/// ones; unknown types use the configured fallback, or `note`.
+///
+/// # Arguments
+/// - `kind` - The lowercased callout type.
+/// - `config` - The callout settings.
+///
+/// # Returns
+/// The canonical type, or the configured directive or class.
+fn canonical_kind<'a>(kind: &str, config: &'a CalloutConfig) -> &'a str {
+    if let Some((_, mapped)) = config.types.iter().find(|(name, _)| name.eq_ignore_ascii_case(kind)) {
+        return mapped;
+    }
+
+    match kind {
+        "abstract" | "summary" | "tldr" => "abstract",
+        "info" => "info",
+        "todo" => "todo",
+        "tip" | "hint" | "important" => "tip",
+        "success" | "check" | "done" => "success",
+        "question" | "help" | "faq" => "question",
+        "warning" | "caution" | "attention" => "warning",
+        "failure" | "fail" | "missing" => "failure",
+        "danger" | "error" => "danger",
+        "bug" => "bug",
+        "example" => "example",
+        "quote" | "cite" => "quote",
+        _ => config.fallback.as_deref().unwrap_or("note"),
+    }
+}
+
+
+/// Capitalize the first letter of a callout type for use as default title.
+///
+/// # Arguments
+/// - `kind` - The callout type.
+///
+/// # Returns
+/// The capitalized type, e.g. `Tldr`.
+fn default_title(kind: &str) -> String {
+    let mut chars = kind.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect::<String>().replace('-', " "),
+        None => String::new(),
+    }
+}
+
+
+/// Render a callout as an mdbook-admonish block.
+///
+/// The fence is made longer than any fence inside the body so code blocks
+/// in callouts stay intact. Folded callouts become collapsible blocks;
+/// admonish has no expanded collapsible, so `+` callouts are rendered open.
+///
+/// # Arguments
+/// - `callout` - The callout to render.
+/// - `directive` - The admonish directive for the callout type.
+/// - `body` - The converted body of the callout.
+///
+/// # Returns
+/// The fenced admonish block.
+fn render_admonish(callout: &Callout, directive: &str, body: &str) -> String {
+    let title = match callout.title {
+        "" if directive != callout.kind => default_title(&callout.kind),
+        title => title.to_string(),
+    };
+    let title = match title.as_str() {
+        "" => String::new(),
+        title => format!(" title=\"{}\"", title.replace('\\', "\\\\").replace('"', "\\\"")),
+    };
+
+    let longest = body
+        .lines()
+        .map(|line| line.trim_start().chars().take_while(|&c| c == '`').count())
+        .max()
+        .unwrap_or(0);
+    let fence = "`".repeat((longest + 1).max(3));
+
+    let collapsible = if callout.folded == Some(true) { " collapsible=true" } else { "" };
+
+    format!("{}admonish {}{}{}\n{}\n{}", fence, directive, title, collapsible, body, fence)
+}
+
+
+/// Get the icon shown in the title of an HTML callout.
+///
+/// # Arguments
+/// - `kind` - The canonical callout type.
+///
+/// # Returns
+/// The icon character.
+fn icon(kind: &str) -> &'static str {
+    match kind {
+        "abstract" => "📋",
+        "info" => "ℹ️",
+        "todo" => "☑️",
+        "tip" => "💡",
+        "success" => "✅",
+        "question" => "❓",
+        "warning" => "⚠️",
+        "failure" => "❌",
+        "danger" => "⚡",
+        "bug" => "🐛",
+        "example" => "📑",
+        "quote" => "💬",
+        _ => "📝",
+    }
+}
+
+
+/// Render a callout as HTML styled by the bundled stylesheet.
+///
+/// The body is separated by blank lines so mdBook still renders it as Markdown.
+/// Foldable callouts use `<details>` with the title as `<summary>`.
+///
+/// # Arguments
+/// - `callout` - The callout to render.
+/// - `kind` - The class suffix for the callout type.
+/// - `body` - The converted body of the callout.
+///
+/// # Returns
+/// The callout as `<div class="callout callout-...">` block.
+fn render_html(callout: &Callout, kind: &str, body: &str) -> String {
+    // Markdown isn't rendered inside HTML, so links in the title become `<a>`
+    let title = match callout.title {
+        "" => default_title(&callout.kind),
+        title => TITLE_LINKS.replace_all(title, "<a href=\"$2\">$1</a>").into_owned(),
+    };
+
+    let (outer, open, heading) = match callout.folded {
+        Some(folded) => ("details", if folded { "" } else { " open" }, "summary"),
+        None => ("div", "", "div"),
+    };
+
+    format!(
+        "<{outer} class=\"callout callout-{}\" data-callout=\"{}\"{open}>\n\
+         <{heading} class=\"callout-title\"><span class=\"callout-icon\">{}</span> {}</{heading}>\n\
+         <div class=\"callout-content\">\n\n{}\n\n</div>\n</{outer}>",
+        kind,
+        callout.kind,
+        icon(kind),
+        title,
+        body
+    )
+}
+
+
+/// Convert all callouts in the content according to the configured mode.
+///
+/// Callouts inside fenced code blocks are left alone. Nested callouts are
+/// converted from the inside out, so the outer fence of an admonish block is
+/// always longer than the fences of the blocks inside it.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `config` - The callout settings.
+///
+/// # Returns
+/// The content with converted callouts.
+pub fn convert_callouts(content: &str, config: &CalloutConfig) -> String {
+    if config.mode == CalloutMode::None {
+        return content.to_string();
+    }
+
+    let lines: Vec<&str> = content.lines().collect();
+    let mut fences = FenceTracker::default();
+    let mut output: Vec<String> = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        if fences.is_code(lines[i]) {
+            output.push(lines[i].to_string());
+            i += 1;
+            continue;
+        }
+
+        match parse_callout(&lines[i..]) {
+            Some((callout, len)) => {
+                let kind = canonical_kind(&callout.kind, config);
+                let body = convert_callouts(&callout.body.join("\n"), config);
+                output.push(match config.mode {
+                    CalloutMode::Html => render_html(&callout, kind, &body),
+                    _ => render_admonish(&callout, kind, &body),
+                });
+                i += len;
+            }
+            None => {
+                output.push(lines[i].to_string());
+                i += 1;
+            }
+        }
+    }
+
+    let mut converted = output.join("\n");
+    if content.ends_with('\n') {
+        converted.push('\n');
+    }
+    converted
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn admonish() -> CalloutConfig {
+        CalloutConfig { mode: CalloutMode::Admonish, ..Default::default() }
+    }
+
+    #[test]
+    fn test_admonish_callouts() {
+        let input = "Intro\n\n> [!warning] Be \"careful\"\n> First line\n>\n> Second line\n\nAfter\n";
+        let expected = "Intro\n\n```admonish warning title=\"Be \\\"careful\\\"\"\nFirst line\n\nSecond line\n```\n\nAfter\n";
+        assert_eq!(convert_callouts(input, &admonish()), expected);
+
+        assert_eq!(convert_callouts("> [!TLDR]\n> Short", &admonish()), "```admonish abstract title=\"Tldr\"\nShort\n```");
+        assert_eq!(convert_callouts("> [!note]", &admonish()), "```admonish note\n\n```");
+    }
+
+    #[test]
+    fn test_html_callouts() {
+        let config = CalloutConfig { mode: CalloutMode::Html, ..Default::default() };
+        assert_eq!(
+            convert_callouts("> [!hint]\n> Use *this*", &config),
+            "<div class=\"callout callout-tip\" data-callout=\"hint\">\n\
+             <div class=\"callout-title\"><span class=\"callout-icon\">💡</span> Hint</div>\n\
+             <div class=\"callout-content\">\n\nUse *this*\n\n</div>\n</div>"
+        );
+        assert!(convert_callouts("> [!note] See [Setup](setup.md)", &config).contains("</span> See <a href=\"setup.md\">Setup</a></div>"));
+    }
+
+    #[test]
+    fn test_code_in_callouts() {
+        let input = "> [!example] Code\n> ```rust\n> fn main() {}\n> ```";
+        let expected = "````admonish example title=\"Code\"\n```rust\nfn main() {}\n```\n````";
+        assert_eq!(convert_callouts(input, &admonish()), expected);
+
+        let fenced = "```\n> [!note] Not a callout\n```";
+        assert_eq!(convert_callouts(fenced, &admonish()), fenced);
+    }
+
+    #[test]
+    fn test_custom_types() {
+        let mut config = admonish();
+        config.types.insert("theorem".to_string(), "info".to_string());
+        config.fallback = Some("tip".to_string());
+
+        assert_eq!(convert_callouts("> [!Theorem]\n> Proof", &config), "```admonish info title=\"Theorem\"\nProof\n```");
+        assert_eq!(convert_callouts("> [!recipe] Soup\n> Salt", &config), "```admonish tip title=\"Soup\"\nSalt\n```");
+        assert_eq!(convert_callouts("> [!warning]\n> Hot", &config), "```admonish warning\nHot\n```");
+
+        config.mode = CalloutMode::Html;
+        config.types.insert("recipe".to_string(), "recipe".to_string());
+        assert!(convert_callouts("> [!recipe]\n> Salt", &config).starts_with("<div class=\"callout callout-recipe\""));
+    }
+
+    #[test]
+    fn test_foldable_callouts() {
+        assert_eq!(
+            convert_callouts("> [!faq]- Why?\n> Because", &admonish()),
+            "```admonish question title=\"Why?\" collapsible=true\nBecause\n```"
+        );
+        assert_eq!(convert_callouts("> [!note]+\n> Open", &admonish()), "```admonish note\nOpen\n```");
+
+        let html = CalloutConfig { mode: CalloutMode::Html, ..Default::default() };
+        let folded = convert_callouts("> [!info]-\n> Hidden", &html);
+        assert!(folded.starts_with("<details class=\"callout callout-info\" data-callout=\"info\">\n<summary class=\"callout-title\">"));
+        assert!(folded.ends_with("</summary>\n<div class=\"callout-content\">\n\nHidden\n\n</div>\n</details>"));
+        assert!(convert_callouts("> [!info]+\n> Shown", &html).starts_with("<details class=\"callout callout-info\" data-callout=\"info\" open>"));
+    }
+
+    #[test]
+    fn test_nested_callouts() {
+        let input = "> [!note] Outer\n> Text\n> > [!tip] Inner\n> > > [!bug]\n> > > Deep\n> After";
+        let expected = "`````admonish note title=\"Outer\"\nText\n````admonish tip title=\"Inner\"\n\
+                        ```admonish bug\nDeep\n```\n````\nAfter\n`````";
+        assert_eq!(convert_callouts(input, &admonish()), expected);
+
+        let html = convert_callouts("> [!note]\n> > [!tip]\n> > Inner", &CalloutConfig { mode: CalloutMode::Html, ..Default::default() });
+        assert_eq!(html.matches("<div class=\"callout ").count(), 2);
+        assert!(html.contains("<div class=\"callout-content\">\n\n<div class=\"callout callout-tip\""));
+    }
+
+    #[test]
+    fn test_plain_blockquotes_and_disabled_mode() {
+        assert_eq!(convert_callouts("> Just a quote", &admonish()), "> Just a quote");
+        assert_eq!(convert_callouts("> [!note] Title", &CalloutConfig::default()), "> [!note] Title");
+    }
+}
@@ -0,0 +1,210 @@
+//! Static rendering of Obsidian Canvas (`.canvas`) files as chapters.
+
+use crate::book;
+use crate::markdown;
+use crate::vault::Vault;
+use crate::warn;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::fs;
+use std::path::Path;
+
+
+/// The JSON Canvas document stored in a `.canvas` file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Canvas {
+    nodes: Vec<Node>,
+    edges: Vec<Edge>,
+}
+
+
+/// A card on the canvas.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+struct Node {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    text: Option<String>,
+    file: Option<String>,
+    url: Option<String>,
+    label: Option<String>,
+    x: f64,
+    y: f64,
+}
+
+
+/// A connection between two cards.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Edge {
+    from_node: String,
+    to_node: String,
+    label: Option<String>,
+}
+
+
+/// Split a card into its title and body.
+///
+/// Text cards starting with a heading use the heading as title, other text
+/// cards are numbered. File and link cards use the file name or URL.
+///
+/// # Arguments
+/// - `node` - The card.
+/// - `index` - The position of the card in reading order, starting at 1.
+///
+/// # Returns
+/// The title and the Markdown body of the card.
+fn card(node: &Node, index: usize) -> (String, String) {
+    match node.kind.as_str() {
+        "file" => {
+            let file = node.file.as_deref().unwrap_or_default();
+            let file = file.strip_suffix(".md").unwrap_or(file);
+            let title = file.rsplit('/').next().unwrap_or(file);
+            (title.to_string(), format!("[[{}]]", file))
+        }
+        "link" => {
+            let url = node.url.as_deref().unwrap_or_default();
+            (url.to_string(), format!("<{}>", url))
+        }
+        "group" => (node.label.clone().unwrap_or_else(|| format!("Group {}", index)), String::new()),
+        _ => {
+            let text = node.text.as_deref().unwrap_or_default().trim();
+            let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+            match markdown::heading(first) {
+                Some((_, title)) => (title.to_string(), rest.trim().to_string()),
+                None => (format!("Card {}", index), text.to_string()),
+            }
+        }
+    }
+}
+
+
+/// Render a canvas as a Markdown chapter.
+///
+/// Cards become sections in reading order (top to bottom, left to right),
+/// followed by a list of the connections between them.
+///
+/// # Arguments
+/// - `name` - The name of the canvas, used as chapter title.
+/// - `json` - The content of the `.canvas` file.
+///
+/// # Returns
+/// The Markdown content, or an error if the JSON is invalid.
+pub fn render_canvas(name: &str, json: &str) -> Result<String> {
+    let mut canvas: Canvas = serde_json::from_str(json)?;
+    canvas.nodes.sort_by(|a, b| a.y.total_cmp(&b.y).then(a.x.total_cmp(&b.x)));
+
+    let cards: Vec<(String, String)> = canvas
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(i, node)| card(node, i + 1))
+        .collect();
+    let title = |id: &str| match canvas.nodes.iter().position(|n| n.id == id) {
+        Some(i) => cards[i].0.clone(),
+        None => id.to_string(),
+    };
+
+    let mut content = format!("# {}\n", name);
+    for (title, body) in &cards {
+        content.push_str(&format!("\n## {}\n", title));
+        if !body.is_empty() {
+            content.push_str(&format!("\n{}\n", body));
+        }
+    }
+
+    if !canvas.edges.is_empty() {
+        content.push_str("\n## Connections\n\n");
+        for edge in &canvas.edges {
+            let label = edge.label.as_deref().map(|l| format!(": {}", l)).unwrap_or_default();
+            content.push_str(&format!(
+                "- **{}** → **{}**{}\n",
+                title(&edge.from_node),
+                title(&edge.to_node),
+                label
+            ));
+        }
+    }
+
+    Ok(content)
+}
+
+
+/// Append a generated chapter for every canvas linked from the book.
+///
+/// The chapter of `boards/Plan.canvas` is placed at `boards/Plan.canvas.md`,
+/// which is where `[[Plan.canvas]]` links point to.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `vault` - The vault to find the canvas files in.
+/// - `src_dir` - The book's source directory.
+///
+/// # Returns
+/// A Result indicating success or failure.
+pub fn add_canvas_chapters(book: &mut Value, vault: &Vault, src_dir: &Path) -> Result<()> {
+    let re = Regex::new(r"\[\[([^#\|\]]+\.canvas)\s*\\?(?:[#\|][^\]]*)?\]\]").unwrap();
+
+    let mut canvases = BTreeSet::new();
+    for chapter in book::chapters(book) {
+        let content = chapter.get("content").and_then(|c| c.as_str()).unwrap_or_default();
+        let protected = markdown::protected_ranges(content);
+        for caps in re.captures_iter(content) {
+            // Links in code blocks and diagrams are examples
+            let start = caps.get(0).map_or(0, |m| m.start());
+            if protected.iter().any(|&(from, to)| (from..to).contains(&start)) {
+                continue;
+            }
+            match vault.asset(caps[1].trim()) {
+                Some(path) => {
+                    canvases.insert(path.to_string());
+                }
+                None => warn(&format!("Linked canvas '{}' not found", caps[1].trim())),
+            }
+        }
+    }
+
+    for path in canvases {
+        let json = fs::read_to_string(src_dir.join(&path))?;
+        let name = path.rsplit('/').next().unwrap_or(&path).trim_end_matches(".canvas");
+        let content = render_canvas(name, &json)
+            .with_context(|| format!("Failed to parse canvas '{}'", path))?;
+        book::push_chapter(book, name, &format!("{}.md", path), content);
+    }
+    Ok(())
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_canvas() {
+        let json = r##"{
+            "nodes": [
+                {"id": "b", "type": "file", "file": "notes/Setup.md", "x": 400, "y": 0},
+                {"id": "a", "type": "text", "text": "# Idea\nSome *text*", "x": 0, "y": 0},
+                {"id": "c", "type": "text", "text": "Loose thought", "x": 0, "y": 300},
+                {"id": "d", "type": "link", "url": "https://example.com", "x": 0, "y": 600}
+            ],
+            "edges": [{"id": "e", "fromNode": "a", "toNode": "b", "label": "needs"}]
+        }"##;
+
+        assert_eq!(
+            render_canvas("Plan", json).unwrap(),
+            "# Plan\n\n## Idea\n\nSome *text*\n\n## Setup\n\n[[notes/Setup]]\n\n## Card 3\n\nLoose thought\n\n\
+             ## https://example.com\n\n<https://example.com>\n\n## Connections\n\n- **Idea** → **Setup**: needs\n"
+        );
+    }
+
+    #[test]
+    fn test_invalid_canvas() {
+        assert!(render_canvas("Broken", "{ nodes").is_err());
+    }
+}
@@ -0,0 +1,545 @@
+//! Citations (`[@key]`, `[[@key]]`) resolved against a BibTeX or CSL-JSON bibliography.
+
+use crate::book;
+use crate::config::{BibliographyPlacement, CitationConfig, Config};
+use crate::markdown;
+use crate::paths;
+use anyhow::{Context, Result};
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fs;
+use std::sync::LazyLock;
+
+
+/// The start of a BibTeX entry with the entry type and citekey as groups.
+static BIBTEX_ENTRY: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"@(\w+)\s*[{(]\s*([^,\s]+)\s*,").unwrap());
+
+/// A `[[@key]]` or `[[@key|alias]]` citation with the citekey and alias as groups.
+static WIKILINK_CITATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\[@([^\]\|#]+?)\\?(?:\|([^\]]+))?\]\]").unwrap());
+
+/// A bracketed `[@key]` citation with its inside as group.
+static CITATION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\[\]]*@[^\[\]]*)\](?:[^(\[:]|$)").unwrap());
+
+/// One cited work in a citation: prefix, `-`, citekey and locator.
+static CITED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(.*?)(?:^|\s)(-?)@(\w(?:[\w:.#$%&+?<>~/-]*\w)?)(?:,\s*(.*))?$").unwrap());
+
+
+/// An entry of the bibliography.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Reference {
+    /// The citekey.
+    pub key: String,
+
+    /// The authors (or editors) as family and given names.
+    pub authors: Vec<(String, String)>,
+
+    /// The year of publication.
+    pub year: Option<String>,
+
+    /// The title of the work.
+    pub title: Option<String>,
+
+    /// The journal, book or publisher the work appeared in.
+    pub container: Option<String>,
+
+    /// A link to the work, from its URL or DOI.
+    pub url: Option<String>,
+}
+
+impl Reference {
+    /// Get the short author-date label used in citations, e.g. `Smith et al. 2020`.
+    ///
+    /// # Arguments
+    /// - `with_author` - Whether the authors are included, `false` for `[-@key]`.
+    ///
+    /// # Returns
+    /// The label.
+    pub fn label(&self, with_author: bool) -> String {
+        let year = self.year.clone().unwrap_or_else(|| "n.d.".to_string());
+        if !with_author {
+            return year;
+        }
+        let authors = match self.authors.as_slice() {
+            [] => self.title.clone().unwrap_or_else(|| self.key.clone()),
+            [(one, _)] => one.clone(),
+            [(one, _), (two, _)] => format!("{} and {}", one, two),
+            [(one, _), ..] => format!("{} et al.", one),
+        };
+        format!("{} {}", authors, year)
+    }
+
+    /// Render the full entry as shown in the bibliography.
+    ///
+    /// # Returns
+    /// The entry as Markdown, e.g. `Smith, J. (2020). *Title*. Journal.`
+    pub fn render(&self) -> String {
+        let authors: Vec<String> = self
+            .authors
+            .iter()
+            .map(|(family, given)| {
+                let initials: Vec<String> = given
+                    .split_whitespace()
+                    .filter_map(|name| name.chars().next())
+                    .map(|initial| format!("{}.", initial))
+                    .collect();
+                match initials.is_empty() {
+                    true => family.clone(),
+                    false => format!("{}, {}", family, initials.join(" ")),
+                }
+            })
+            .collect();
+        let mut parts = Vec::new();
+        if !authors.is_empty() {
+            parts.push(match authors.len() {
+                1 => authors[0].clone(),
+                n => format!("{}, & {}", authors[..n - 1].join(", "), authors[n - 1]),
+            });
+        }
+        parts.push(format!("({}).", self.year.as_deref().unwrap_or("n.d.")));
+        if let Some(title) = &self.title {
+            parts.push(format!("*{}*.", title.trim_end_matches('.')));
+        }
+        if let Some(container) = &self.container {
+            parts.push(format!("{}.", container.trim_end_matches('.')));
+        }
+        if let Some(url) = &self.url {
+            parts.push(format!("<{}>", url));
+        }
+        parts.join(" ")
+    }
+}
+
+
+/// The works that can be cited, by citekey.
+#[derive(Debug, Default)]
+pub struct Bibliography {
+    references: BTreeMap<String, Reference>,
+}
+
+impl Bibliography {
+    /// Load the configured bibliography file.
+    ///
+    /// Files ending in `.json` are read as CSL-JSON, all others as BibTeX.
+    ///
+    /// # Arguments
+    /// - `config` - The preprocessor settings.
+    ///
+    /// # Returns
+    /// The bibliography (empty if none is configured), or an error if the file
+    /// can't be read.
+    pub fn load(config: &Config) -> Result<Bibliography> {
+        let Some(file) = &config.citations.bibliography else {
+            return Ok(Bibliography::default());
+        };
+        let path = config.root.join(file);
+        let text = fs::read_to_string(&path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+        match paths::extension(&file.to_string_lossy()).as_deref() {
+            Some("json") => Bibliography::from_csl_json(&text).with_context(|| format!("Failed to parse '{}'", path.display())),
+            _ => Ok(Bibliography::from_bibtex(&text)),
+        }
+    }
+
+    /// Create a bibliography from references.
+    pub fn from_references(references: Vec<Reference>) -> Bibliography {
+        Bibliography { references: references.into_iter().map(|r| (r.key.clone(), r)).collect() }
+    }
+
+    /// Parse the entries of a BibTeX file.
+    ///
+    /// `@string`, `@preamble` and `@comment` entries are ignored.
+    ///
+    /// # Arguments
+    /// - `text` - The content of the `.bib` file.
+    ///
+    /// # Returns
+    /// The bibliography.
+    pub fn from_bibtex(text: &str) -> Bibliography {
+        let mut references = Vec::new();
+
+        for caps in BIBTEX_ENTRY.captures_iter(text) {
+            if matches!(caps[1].to_lowercase().as_str(), "string" | "preamble" | "comment") {
+                continue;
+            }
+            let start = caps.get(0).map_or(0, |m| m.end());
+            let fields = bibtex_fields(&text[start..]);
+            let field = |name: &str| fields.get(name).cloned();
+
+            let names = field("author").or_else(|| field("editor")).unwrap_or_default();
+            let year = field("year").or_else(|| field("date").map(|date| date.chars().take(4).collect()));
+            let url = field("url").or_else(|| field("doi").map(|doi| format!("https://doi.org/{}", doi)));
+            references.push(Reference {
+                key: caps[2].to_string(),
+                authors: names.split(" and ").filter_map(split_name).collect(),
+                year,
+                title: field("title"),
+                container: field("journal").or_else(|| field("booktitle")).or_else(|| field("publisher")),
+                url,
+            });
+        }
+        Bibliography::from_references(references)
+    }
+
+    /// Parse a CSL-JSON bibliography, as exported by Zotero.
+    ///
+    /// # Arguments
+    /// - `text` - The JSON array of items.
+    ///
+    /// # Returns
+    /// The bibliography or a parse error.
+    pub fn from_csl_json(text: &str) -> Result<Bibliography> {
+        let items: Vec<Value> = serde_json::from_str(text)?;
+        let string = |item: &Value, key: &str| item[key].as_str().map(str::to_string);
+
+        let references = items
+            .iter()
+            .filter_map(|item| {
+                let key = string(item, "id")?;
+                let names = item["author"].as_array().or_else(|| item["editor"].as_array());
+                let authors = names
+                    .into_iter()
+                    .flatten()
+                    .filter_map(|name| match (string(name, "family"), string(name, "literal")) {
+                        (Some(family), _) => Some((family, string(name, "given").unwrap_or_default())),
+                        (None, Some(literal)) => Some((literal, String::new())),
+                        _ => None,
+                    })
+                    .collect();
+                let year = match &item["issued"]["date-parts"][0][0] {
+                    Value::Number(year) => Some(year.to_string()),
+                    Value::String(year) => Some(year.clone()),
+                    _ => string(&item["issued"], "raw").map(|raw| raw.chars().take(4).collect()),
+                };
+                let url = string(item, "URL").or_else(|| string(item, "DOI").map(|doi| format!("https://doi.org/{}", doi)));
+                Some(Reference {
+                    key,
+                    authors,
+                    year,
+                    title: string(item, "title"),
+                    container: string(item, "container-title").or_else(|| string(item, "publisher")),
+                    url,
+                })
+            })
+            .collect();
+        Ok(Bibliography::from_references(references))
+    }
+
+    /// Get a reference by its citekey.
+    pub fn get(&self, key: &str) -> Option<&Reference> {
+        self.references.get(key)
+    }
+
+    /// Returns true if there are no references.
+    pub fn is_empty(&self) -> bool {
+        self.references.is_empty()
+    }
+}
+
+
+/// Parse the fields of a BibTeX entry up to its closing brace.
+///
+/// # Arguments
+/// - `text` - The entry text after the citekey.
+///
+/// # Returns
+/// The lowercased field names and their values without braces.
+fn bibtex_fields(text: &str) -> BTreeMap<String, String> {
+    let mut fields = BTreeMap::new();
+    let mut chars = text.char_indices().peekable();
+
+    loop {
+        // Field name up to `=`, or the end of the entry
+        let mut name = String::new();
+        while let Some(&(_, c)) = chars.peek() {
+            match c {
+                '=' | '}' | ')' | '@' => break,
+                ',' => name.clear(),
+                _ => name.push(c),
+            }
+            chars.next();
+        }
+        if chars.next().map(|(_, c)| c) != Some('=') {
+            break;
+        }
+        while chars.next_if(|&(_, c)| c.is_whitespace()).is_some() {}
+
+        let mut value = String::new();
+        match chars.peek().map(|&(_, c)| c) {
+            Some('{') => {
+                let mut depth = 0;
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        '{' => depth += 1,
+                        '}' if depth == 1 => break,
+                        '}' => depth -= 1,
+                        _ => {}
+                    }
+                    if c != '{' && c != '}' {
+                        value.push(c);
+                    }
+                }
+            }
+            Some('"') => {
+                chars.next();
+                for (_, c) in chars.by_ref() {
+                    match c {
+                        '"' => break,
+                        '{' | '}' => {}
+                        _ => value.push(c),
+                    }
+                }
+            }
+            _ => {
+                while let Some((_, c)) = chars.next_if(|&(_, c)| !matches!(c, ',' | '}' | ')')) {
+                    value.push(c);
+                }
+            }
+        }
+        let value = value.split_whitespace().collect::<Vec<_>>().join(" ").replace("\\&", "&").replace("--", "–");
+        fields.insert(name.trim().to_lowercase(), value.trim().to_string());
+    }
+    fields
+}
+
+
+/// Split a BibTeX name (`Family, Given` or `Given Family`) into its parts.
+fn split_name(name: &str) -> Option<(String, String)> {
+    let name = name.trim();
+    if name.is_empty() {
+        return None;
+    }
+    Some(match name.split_once(',') {
+        Some((family, given)) => (family.trim().to_string(), given.trim().to_string()),
+        None => match name.rsplit_once(' ') {
+            Some((given, family)) => (family.to_string(), given.to_string()),
+            None => (name.to_string(), String::new()),
+        },
+    })
+}
+
+
+/// One cited work of a citation like `[see @key, p. 4]`.
+struct Cited<'a> {
+    prefix: &'a str,
+    with_author: bool,
+    reference: &'a Reference,
+    locator: Option<&'a str>,
+}
+
+
+/// Find the citekeys cited in the content.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `bibliography` - The known references.
+///
+/// # Returns
+/// The known citekeys in order of their first citation.
+fn find_citations(content: &str, bibliography: &Bibliography) -> Vec<String> {
+    let mut keys: Vec<String> = Vec::new();
+    let _ = convert(content, bibliography, |reference| {
+        if !keys.contains(&reference.key) {
+            keys.push(reference.key.clone());
+        }
+        String::new()
+    });
+    keys
+}
+
+
+/// Replace all citations of known works outside of code.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `bibliography` - The known references.
+/// - `target` - Called with every cited work, returns the link target.
+///
+/// # Returns
+/// The content with formatted citations.
+fn convert(content: &str, bibliography: &Bibliography, mut target: impl FnMut(&Reference) -> String) -> String {
+    markdown::map_text(content, |text| {
+        let text = WIKILINK_CITATION.replace_all(text, |caps: &Captures| match bibliography.get(caps[1].trim()) {
+            Some(reference) => {
+                let label = caps.get(2).map_or_else(|| reference.label(true), |alias| alias.as_str().to_string());
+                format!("[{}]({})", label, target(reference))
+            }
+            None => caps[0].to_string(),
+        });
+
+        let mut output = String::new();
+        let mut rest = 0;
+        while let Some(caps) = CITATION.captures_at(&text, rest) {
+            let inner = caps.get(1).unwrap();
+            let items: Option<Vec<Cited>> = inner
+                .as_str()
+                .split(';')
+                .map(|item| {
+                    let caps = CITED.captures(item.trim())?;
+                    Some(Cited {
+                        prefix: caps.get(1).map_or("", |m| m.as_str()).trim(),
+                        with_author: caps[2].is_empty(),
+                        reference: bibliography.get(&caps[3])?,
+                        locator: caps.get(4).map(|m| m.as_str().trim()),
+                    })
+                })
+                .collect();
+
+            output.push_str(&text[rest..inner.start() - 1]);
+            match items {
+                Some(items) => {
+                    let cited: Vec<String> = items
+                        .into_iter()
+                        .map(|item| {
+                            let link = format!("[{}]({})", item.reference.label(item.with_author), target(item.reference));
+                            let mut cited = match item.prefix {
+                                "" => link,
+                                prefix => format!("{} {}", prefix, link),
+                            };
+                            if let Some(locator) = item.locator {
+                                cited.push_str(&format!(", {}", locator));
+                            }
+                            cited
+                        })
+                        .collect();
+                    output.push_str(&format!("({})", cited.join("; ")));
+                }
+                None => output.push_str(&text[inner.start() - 1..inner.end() + 1]),
+            }
+            rest = inner.end() + 1;
+        }
+        output.push_str(&text[rest..]);
+        output
+    })
+}
+
+
+/// Render the bibliography entries of the cited works.
+///
+/// # Arguments
+/// - `keys` - The cited citekeys.
+/// - `bibliography` - The known references.
+///
+/// # Returns
+/// One list item per work, sorted by author and year, with an anchor to link to.
+fn render_entries(keys: &[String], bibliography: &Bibliography) -> String {
+    let mut references: Vec<&Reference> = keys.iter().filter_map(|key| bibliography.get(key)).collect();
+    references.sort_by_key(|r| (r.authors.first().map(|(family, _)| family.to_lowercase()), r.year.clone()));
+    references
+        .iter()
+        .map(|r| format!("- <span id=\"ref-{}\"></span>{}", r.key, r.render()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
+/// Format the citations of a chapter and add the bibliography it needs.
+///
+/// With the bibliography at the end of each chapter, the cited works are
+/// listed below a heading at the end of the chapter. Otherwise citations link
+/// to the bibliography chapter of the book.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `bibliography` - The known references.
+/// - `config` - The citation settings.
+///
+/// # Returns
+/// The content with formatted citations.
+pub fn convert_citations(content: &str, source: Option<&str>, bibliography: &Bibliography, config: &CitationConfig) -> String {
+    if bibliography.is_empty() {
+        return content.to_string();
+    }
+
+    let page = match config.placement {
+        BibliographyPlacement::Book => paths::percent_encode(&paths::relative(source.unwrap_or_default(), &config.page)),
+        _ => String::new(),
+    };
+    let converted = convert(content, bibliography, |reference| format!("{}#ref-{}", page, reference.key));
+
+    let keys = find_citations(content, bibliography);
+    match config.placement == BibliographyPlacement::Chapter && !keys.is_empty() {
+        true => format!("{}\n\n## {}\n\n{}\n", converted.trim_end(), config.title, render_entries(&keys, bibliography)),
+        false => converted,
+    }
+}
+
+
+/// Append a chapter listing every work cited in the book, if enabled.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `bibliography` - The known references.
+/// - `config` - The citation settings.
+pub fn add_bibliography_chapter(book: &mut Value, bibliography: &Bibliography, config: &CitationConfig) {
+    if config.placement != BibliographyPlacement::Book || bibliography.is_empty() {
+        return;
+    }
+
+    let mut keys: Vec<String> = Vec::new();
+    for chapter in book::chapters(book) {
+        for key in find_citations(chapter["content"].as_str().unwrap_or_default(), bibliography) {
+            if !keys.contains(&key) {
+                keys.push(key);
+            }
+        }
+    }
+    if !keys.is_empty() {
+        let content = format!("# {}\n\n{}\n", config.title, render_entries(&keys, bibliography));
+        book::push_chapter(book, &config.title, &config.page, content);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bibliography() -> Bibliography {
+        Bibliography::from_bibtex(
+            "@article{smith2020,\n  author = {Smith, John and Doe, Ann},\n  title = {{On Things}},\n  journal = \"Journal of Stuff\",\n  year = 2020,\n}\n\
+             @string{foo = \"bar\"}\n@book{lee19, author = {Kim Lee}, title = {A Book}, publisher = {Press}, year = {2019}}",
+        )
+    }
+
+    #[test]
+    fn test_bibtex() {
+        let bibliography = bibliography();
+        let smith = bibliography.get("smith2020").unwrap();
+        assert_eq!(smith.authors, [("Smith".to_string(), "John".to_string()), ("Doe".to_string(), "Ann".to_string())]);
+        assert_eq!(smith.label(true), "Smith and Doe 2020");
+        assert_eq!(smith.render(), "Smith, J., & Doe, A. (2020). *On Things*. Journal of Stuff.");
+        assert_eq!(bibliography.get("lee19").unwrap().render(), "Lee, K. (2019). *A Book*. Press.");
+        assert!(bibliography.get("foo").is_none());
+    }
+
+    #[test]
+    fn test_csl_json() {
+        let json = r#"[{"id": "a", "author": [{"family": "Roe", "given": "Jo"}, {"literal": "ACME"}, {"family": "Poe"}],
+                        "issued": {"date-parts": [[2001, 5]]}, "title": "T", "DOI": "10.1/x"}]"#;
+        let bibliography = Bibliography::from_csl_json(json).unwrap();
+        let reference = bibliography.get("a").unwrap();
+        assert_eq!(reference.label(true), "Roe et al. 2001");
+        assert_eq!(reference.url.as_deref(), Some("https://doi.org/10.1/x"));
+    }
+
+    #[test]
+    fn test_convert_citations() {
+        let config = CitationConfig::default();
+        let input = "As shown [see @smith2020, p. 4; -@lee19] and [[@lee19]].\nMail [me@example.com] or [@unknown] or `[@smith2020]`.";
+        assert_eq!(
+            convert_citations(input, Some("a.md"), &bibliography(), &config),
+            "As shown (see [Smith and Doe 2020](#ref-smith2020), p. 4; [2019](#ref-lee19)) and [Lee 2019](#ref-lee19).\n\
+             Mail [me@example.com] or [@unknown] or `[@smith2020]`.\n\n## References\n\n\
+             - <span id=\"ref-lee19\"></span>Lee, K. (2019). *A Book*. Press.\n\
+             - <span id=\"ref-smith2020\"></span>Smith, J., & Doe, A. (2020). *On Things*. Journal of Stuff.\n"
+        );
+
+        let book = CitationConfig { placement: BibliographyPlacement::Book, ..Default::default() };
+        assert_eq!(
+            convert_citations("[@lee19]", Some("guide/a.md"), &bibliography(), &book),
+            "([Lee 2019](../references.md#ref-lee19))"
+        );
+    }
+}
@@ -0,0 +1,1534 @@
+//! Preprocessor settings derived from the mdBook context.
+
+use crate::dates::Date;
+use crate::obsidian::ObsidianSettings;
+use crate::paths;
+use anyhow::{Context, Result};
+use regex::Regex;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+
+/// Settings used while processing a book.
+///
+/// Everything except `root`, `src_dir` and `excluded` is read from the
+/// `[preprocessor.obsidian-links]` table of `book.toml`.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct Config {
+    /// Absolute path of the book's root directory.
+    #[serde(skip)]
+    pub root: PathBuf,
+
+    /// Absolute path of the book's source directory.
+    #[serde(skip)]
+    pub src_dir: PathBuf,
+
+    /// The Obsidian vault root (containing `.obsidian`), relative to the book root.
+    pub vault_dir: Option<PathBuf>,
+
+    /// Settings for converted wikilinks.
+    pub links: LinkConfig,
+
+    /// Settings for transcluded notes.
+    pub embeds: EmbedConfig,
+
+    /// Settings for embedded attachments.
+    pub media: MediaConfig,
+
+    /// Settings for attachments kept outside the src directory.
+    pub assets: AssetConfig,
+
+    /// Settings for callouts.
+    pub callouts: CalloutConfig,
+
+    /// Settings for inline formatting such as highlights.
+    pub formatting: FormattingConfig,
+
+    /// Settings for `#tags`.
+    pub tags: TagConfig,
+
+    /// Settings for YAML frontmatter.
+    pub frontmatter: FrontmatterConfig,
+
+    /// Settings for Dataview syntax.
+    pub dataview: DataviewConfig,
+
+    /// Settings for embedded search blocks.
+    pub search: SearchConfig,
+
+    /// Settings for tasks with extended statuses.
+    pub tasks: TaskConfig,
+
+    /// Settings for citations.
+    pub citations: CitationConfig,
+
+    /// The vault's "Excluded files" filters, relative to the vault root.
+    #[serde(skip)]
+    pub excluded: Vec<ExcludeFilter>,
+}
+
+
+/// A filter of the vault's "Excluded files" setting.
+#[derive(Debug, Clone)]
+pub enum ExcludeFilter {
+    /// A folder or file path matching everything below it.
+    Path(String),
+    /// A regex, written as `/pattern/`.
+    Pattern(Regex),
+}
+
+impl ExcludeFilter {
+    /// Parse a filter as written in the vault's settings.
+    ///
+    /// # Arguments
+    /// - `filter` - The filter, e.g. `Templates/` or `/\.excalidraw\.md$/`.
+    ///
+    /// # Returns
+    /// The filter, or an error for an invalid regex.
+    pub fn new(filter: &str) -> Result<ExcludeFilter> {
+        match filter.strip_prefix('/').and_then(|f| f.strip_suffix('/')) {
+            Some(pattern) => Regex::new(pattern)
+                .map(ExcludeFilter::Pattern)
+                .with_context(|| format!("Invalid excluded files pattern '{}'", filter)),
+            None => Ok(ExcludeFilter::Path(filter.to_string())),
+        }
+    }
+
+    /// Returns true if the filter matches a path relative to the vault root.
+    fn matches(&self, path: &str) -> bool {
+        match self {
+            ExcludeFilter::Path(filter) => !filter.is_empty() && path.starts_with(filter.as_str()),
+            ExcludeFilter::Pattern(re) => re.is_match(path),
+        }
+    }
+}
+
+
+/// Extensions of files that are linked directly instead of as notes.
+const ASSET_EXTENSIONS: &[&str] = &[
+    "png", "jpg", "jpeg", "gif", "bmp", "svg", "webp", "avif",
+    "mp3", "m4a", "ogg", "wav", "flac", "3gp",
+    "mp4", "webm", "ogv", "mov", "mkv",
+    "pdf", "zip", "csv", "json", "txt",
+];
+
+
+/// The format of daily notes unless the vault sets one.
+const DAILY_FORMAT: &str = "YYYY-MM-DD";
+
+
+/// The words of a note name that humanizing capitalizes.
+static WORDS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"[^\s/]+").unwrap());
+
+
+/// Load a file mapping note names to URLs.
+///
+/// Files ending in `.json` are read as a JSON object, all others as TOML,
+/// both with note names as keys and URLs as values.
+///
+/// # Arguments
+/// - `path` - The path of the file.
+///
+/// # Returns
+/// The URLs by lowercased note name without `.md`, or an error if the file
+/// can't be read or parsed.
+fn load_url_map(path: &Path) -> Result<HashMap<String, String>> {
+    let text = fs::read_to_string(path).with_context(|| format!("Failed to read '{}'", path.display()))?;
+    let urls: HashMap<String, String> = match paths::extension(&path.to_string_lossy()).as_deref() {
+        Some("json") => serde_json::from_str(&text).with_context(|| format!("Failed to parse '{}'", path.display()))?,
+        _ => toml::from_str(&text).with_context(|| format!("Failed to parse '{}'", path.display()))?,
+    };
+    Ok(urls
+        .into_iter()
+        .map(|(name, url)| (paths::strip_note_extension(&name).to_lowercase(), url))
+        .collect())
+}
+
+
+/// Settings for converted wikilinks (`[preprocessor.obsidian-links.links]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct LinkConfig {
+    /// The extension appended to note links, e.g. `.md`.
+    pub extension: String,
+
+    /// Extensions of files that are linked as-is, without the note extension.
+    pub asset_extensions: Vec<String>,
+
+    /// The display text of links without an alias.
+    pub display: LinkDisplay,
+
+    /// The sources of the display text of links without an alias, tried in
+    /// order; replaces `display` unless empty.
+    pub display_fallback: Vec<DisplaySource>,
+
+    /// How the heading of links to a section shows in their display text.
+    pub section_display: SectionDisplay,
+
+    /// How note names are turned into display text, e.g. `api_error_codes`
+    /// into `API Error Codes`.
+    pub humanize: HumanizeMode,
+
+    /// Words written as given when title-casing display text, e.g. `API`.
+    pub acronyms: Vec<String>,
+
+    /// How links inside raw HTML blocks are handled.
+    pub html_blocks: HtmlBlockMode,
+
+    /// How links inside headings are converted.
+    pub headings: HeadingLinkMode,
+
+    /// Which note a link gets when several notes share its name.
+    pub ambiguous: AmbiguityPolicy,
+
+    /// What happens to links whose target isn't in the vault or the book.
+    pub unresolved: UnresolvedLinkMode,
+
+    /// How links to draft chapters, which have no page, are shown.
+    pub drafts: DraftLinkMode,
+
+    /// Whether a placeholder chapter is added for every note that is linked
+    /// but doesn't exist yet.
+    pub stubs: bool,
+
+    /// The title of the part the placeholder chapters are placed under, empty for none.
+    pub stub_part: String,
+
+    /// The file names of the published chapters.
+    pub file_names: FileNameMode,
+
+    /// A regex matching ordering prefixes (`01 - `) that are removed from the
+    /// display text of links without an alias.
+    #[serde(deserialize_with = "optional_regex")]
+    pub display_prefix: Option<Regex>,
+
+    /// The notes links to a folder point to, e.g. `{name}` for `Projects/Projects.md`
+    /// or `index` for `Projects/index.md`; empty for none.
+    pub folder_notes: Vec<String>,
+
+    /// A regex matching unique IDs (`^\d{12}$`) that link to the note whose
+    /// file name starts with them, as in a Zettelkasten.
+    #[serde(deserialize_with = "optional_regex")]
+    pub uid_pattern: Option<Regex>,
+
+    /// New names of renamed or moved notes by their old name, so links to the
+    /// old name keep working.
+    pub renamed: BTreeMap<String, String>,
+
+    /// Where daily notes are kept, so `[[2024-05-01]]` links to the note of the day.
+    pub daily_notes: PeriodicNoteConfig,
+
+    /// Where weekly notes (`[[2024-W18]]`) are kept.
+    pub weekly_notes: PeriodicNoteConfig,
+
+    /// Where monthly notes (`[[2024-05]]`) are kept.
+    pub monthly_notes: PeriodicNoteConfig,
+
+    /// Where quarterly notes (`[[2024-Q2]]`) are kept.
+    pub quarterly_notes: PeriodicNoteConfig,
+
+    /// Where yearly notes (`[[2024]]`) are kept.
+    pub yearly_notes: PeriodicNoteConfig,
+
+    /// How the anchors of links to headings are generated.
+    pub anchors: AnchorConfig,
+
+    /// The language folders of a translated book (`src/en`, `src/de`), the
+    /// default language first; links prefer notes in their own language.
+    pub languages: Vec<String>,
+
+    /// How paths in links are written; defaults to the vault's "New link format".
+    pub format: Option<LinkFormat>,
+
+    /// Whether Markdown links (`[text](Note.md)`) are resolved like wikilinks;
+    /// defaults to the vault's "Use [[Wikilinks]]" setting being off.
+    pub markdown_links: Option<bool>,
+
+    /// A TOML or JSON file mapping note names to the URLs links to them point
+    /// to, e.g. for notes published elsewhere; relative to the book root.
+    pub external_targets: Option<PathBuf>,
+
+    /// Other books by name and base URL; `[[handbook/Onboarding]]` links to
+    /// the `Onboarding.html` page of the book named `handbook`.
+    pub books: BTreeMap<String, String>,
+
+    /// The URLs of `external_targets` by lowercased note name.
+    #[serde(skip)]
+    pub external_urls: HashMap<String, String>,
+
+    /// The path of the src directory relative to the vault root, e.g. `book/src`.
+    #[serde(skip)]
+    pub vault_prefix: String,
+}
+
+impl Default for LinkConfig {
+    fn default() -> Self {
+        LinkConfig {
+            extension: ".md".to_string(),
+            asset_extensions: ASSET_EXTENSIONS.iter().map(|e| e.to_string()).collect(),
+            display: LinkDisplay::default(),
+            display_fallback: Vec::new(),
+            section_display: SectionDisplay::default(),
+            humanize: HumanizeMode::default(),
+            acronyms: Vec::new(),
+            html_blocks: HtmlBlockMode::default(),
+            headings: HeadingLinkMode::default(),
+            ambiguous: AmbiguityPolicy::default(),
+            unresolved: UnresolvedLinkMode::default(),
+            drafts: DraftLinkMode::default(),
+            stubs: false,
+            stub_part: "Unwritten notes".to_string(),
+            file_names: FileNameMode::default(),
+            display_prefix: None,
+            folder_notes: Vec::new(),
+            languages: Vec::new(),
+            uid_pattern: None,
+            renamed: BTreeMap::new(),
+            daily_notes: PeriodicNoteConfig::with_format(DAILY_FORMAT),
+            weekly_notes: PeriodicNoteConfig::default(),
+            monthly_notes: PeriodicNoteConfig::default(),
+            quarterly_notes: PeriodicNoteConfig::default(),
+            yearly_notes: PeriodicNoteConfig::default(),
+            anchors: AnchorConfig::default(),
+            format: None,
+            markdown_links: None,
+            external_targets: None,
+            books: BTreeMap::new(),
+            external_urls: HashMap::new(),
+            vault_prefix: String::new(),
+        }
+    }
+}
+
+impl LinkConfig {
+    /// Get the note extension with a leading dot.
+    ///
+    /// # Returns
+    /// The extension, e.g. `.md`, or an empty string for none.
+    pub fn note_extension(&self) -> String {
+        match self.extension.trim().trim_start_matches('.') {
+            "" => String::new(),
+            ext => format!(".{}", ext),
+        }
+    }
+
+    /// Get the extension of links to a note or chapter of the book.
+    ///
+    /// mdBook finds chapters by their file, so with a Markdown extension the
+    /// links keep the file's own `.md` or `.markdown`; other extensions, such
+    /// as `.html`, replace it.
+    ///
+    /// # Arguments
+    /// - `path` - The path of the chapter, e.g. `notes/Readme.markdown`.
+    ///
+    /// # Returns
+    /// The extension with a leading dot, or an empty string for none.
+    pub fn chapter_extension(&self, path: &str) -> String {
+        let extension = self.note_extension();
+        let own = &path[paths::strip_note_extension(path).len()..];
+        match extension.to_lowercase().as_str() {
+            ".md" | ".markdown" if !own.is_empty() => own.to_string(),
+            _ => extension,
+        }
+    }
+
+    /// Returns true if files with the extension are linked as-is.
+    ///
+    /// # Arguments
+    /// - `ext` - The lowercased extension without the dot.
+    ///
+    /// # Returns
+    /// Whether the extension is an asset extension.
+    pub fn is_asset_extension(&self, ext: &str) -> bool {
+        self.asset_extensions
+            .iter()
+            .any(|a| a.trim_start_matches('.').eq_ignore_ascii_case(ext))
+    }
+
+    /// Look up the external URL of a note.
+    ///
+    /// # Arguments
+    /// - `name` - The note name or path, with or without `.md`.
+    ///
+    /// # Returns
+    /// The URL from `external_targets`, if the note is mapped.
+    pub fn external_url(&self, name: &str) -> Option<&str> {
+        let name = paths::strip_note_extension(name).to_lowercase();
+        self.external_urls.get(&name).map(String::as_str)
+    }
+
+    /// Find the kind of periodic note a link names, daily notes first.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the link.
+    ///
+    /// # Returns
+    /// The settings of the notes whose format the name is written in, if any.
+    pub fn periodic_notes(&self, name: &str) -> Option<&PeriodicNoteConfig> {
+        [&self.daily_notes, &self.weekly_notes, &self.monthly_notes, &self.quarterly_notes, &self.yearly_notes]
+            .into_iter()
+            .find(|notes| notes.date(name).is_some())
+    }
+
+    /// Look up the new name of a renamed note.
+    ///
+    /// # Arguments
+    /// - `name` - The old note name or path, with or without `.md`.
+    ///
+    /// # Returns
+    /// The new name from `renamed`, if the note was renamed.
+    pub fn renamed_to(&self, name: &str) -> Option<&str> {
+        let name = paths::strip_note_extension(name);
+        self.renamed
+            .iter()
+            .find(|(old, _)| paths::strip_note_extension(old).eq_ignore_ascii_case(name))
+            .map(|(_, new)| new.as_str())
+    }
+
+    /// Look up the page of a link to another book.
+    ///
+    /// # Examples
+    /// `handbook/Getting Started` becomes `https://docs.example.com/handbook/Getting%20Started.html`
+    ///
+    /// # Arguments
+    /// - `name` - The note path, starting with the name of the book.
+    ///
+    /// # Returns
+    /// The URL of the page, if the path starts with a configured book.
+    pub fn book_url(&self, name: &str) -> Option<String> {
+        let (book, page) = name.split_once('/')?;
+        let base = self.books.get(book)?;
+        let page = paths::strip_note_extension(page);
+        Some(format!("{}/{}.html", base.trim_end_matches('/'), paths::percent_encode(page)))
+    }
+
+    /// Remove the ordering prefix from the file name of a note name.
+    ///
+    /// # Examples
+    /// `guide/01 - Introduction` becomes `guide/Introduction`
+    ///
+    /// # Arguments
+    /// - `name` - The note name.
+    ///
+    /// # Returns
+    /// The name without prefix, or the name itself if nothing would be left.
+    pub fn strip_display_prefix(&self, name: &str) -> String {
+        let Some(re) = &self.display_prefix else { return name.to_string() };
+
+        let (folder, file) = match name.rsplit_once('/') {
+            Some((folder, file)) => (format!("{}/", folder), file),
+            None => (String::new(), name),
+        };
+        match re.replace(file, "").trim_start() {
+            "" => name.to_string(),
+            stripped => format!("{}{}", folder, stripped),
+        }
+    }
+
+    /// Turn a note name into the display text of a link without an alias.
+    ///
+    /// The ordering prefix is removed, then the name is humanized.
+    ///
+    /// # Examples
+    /// `api_error_codes` becomes `API Error Codes` with `humanize = "title"`
+    /// and `acronyms = ["API"]`
+    ///
+    /// # Arguments
+    /// - `name` - The note name.
+    ///
+    /// # Returns
+    /// The display text.
+    pub fn display_name(&self, name: &str) -> String {
+        let name = self.strip_display_prefix(name);
+        if self.humanize == HumanizeMode::Off {
+            return name;
+        }
+
+        let name = name.replace(['_', '-'], " ");
+        if self.humanize != HumanizeMode::Title {
+            return name;
+        }
+        WORDS
+            .replace_all(&name, |caps: &regex::Captures| {
+                let word = &caps[0];
+                if let Some(acronym) = self.acronyms.iter().find(|a| a.to_lowercase() == word.to_lowercase()) {
+                    return acronym.clone();
+                }
+                let mut chars = word.chars();
+                chars.next().map_or_else(String::new, |first| first.to_uppercase().chain(chars).collect())
+            })
+            .into_owned()
+    }
+}
+
+
+/// Where notes named after a date are kept, like daily notes
+/// (`[preprocessor.obsidian-links.links.daily-notes]`) or weekly notes
+/// (`weekly-notes`, also `monthly-notes`, `quarterly-notes` and `yearly-notes`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct PeriodicNoteConfig {
+    /// The folder relative to the vault root; defaults to the vault's setting.
+    pub folder: Option<String>,
+
+    /// The Moment.js format of the note paths below the folder, e.g.
+    /// `YYYY-MM-DD`; defaults to the vault's setting, or else the usual
+    /// format of the period if the folder is set. Daily notes always have one.
+    pub format: Option<String>,
+
+    /// The Moment.js format of the display text of links without an alias,
+    /// e.g. `MMMM D, YYYY`; empty to show the name.
+    pub display: String,
+}
+
+impl PeriodicNoteConfig {
+    /// Create the settings of notes with a format, e.g. `YYYY-MM-DD`.
+    pub fn with_format(format: &str) -> PeriodicNoteConfig {
+        PeriodicNoteConfig { format: Some(format.to_string()), ..Default::default() }
+    }
+
+    /// Get the format of the note paths.
+    ///
+    /// # Returns
+    /// The format, if the notes are used.
+    fn format(&self) -> Option<&str> {
+        self.format.as_deref().filter(|f| !f.trim().is_empty())
+    }
+
+    /// Fill in the settings the book doesn't set.
+    ///
+    /// # Arguments
+    /// - `folder` - The folder set in the vault, if any.
+    /// - `format` - The format set in the vault, if any.
+    /// - `default_format` - The usual format of the period.
+    /// - `always` - Whether the notes are used even without any settings.
+    fn fill(&mut self, folder: Option<String>, format: Option<String>, default_format: &str, always: bool) {
+        self.folder = self.folder.take().or(folder);
+        self.format = self.format.take().filter(|f| !f.trim().is_empty()).or(format.filter(|f| !f.trim().is_empty()));
+        if self.format.is_none() && (always || self.folder.is_some()) {
+            self.format = Some(default_format.to_string());
+        }
+    }
+
+    /// Read the date of a link to a note.
+    ///
+    /// Links are written with the whole path format or, like in Obsidian,
+    /// only its file name part.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the link.
+    ///
+    /// # Returns
+    /// The date, if the name is written in the format.
+    pub fn date(&self, name: &str) -> Option<Date> {
+        let format = self.format()?;
+        let file_format = format.rsplit('/').next().unwrap_or(format);
+        Date::parse(name, format).or_else(|| Date::parse(name, file_format))
+    }
+
+    /// Get the path of the note of a date.
+    ///
+    /// # Examples
+    /// `2024-05-01` becomes `Journal/2024/2024-05-01` for the folder `Journal`
+    /// and the format `YYYY/YYYY-MM-DD`
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the link.
+    ///
+    /// # Returns
+    /// The path relative to the vault root, if the name is a date in the format.
+    pub fn path(&self, name: &str) -> Option<String> {
+        let date = self.date(name)?;
+        let folder = self.folder.as_deref().unwrap_or_default().trim_matches('/');
+        Some(format!("{}/{}", folder, date.format(self.format()?)).trim_start_matches('/').to_string())
+    }
+
+    /// Get the display text of a link to the note of a date.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the link.
+    ///
+    /// # Returns
+    /// The date in the display format, if one is set and the name is a date.
+    pub fn display_text(&self, name: &str) -> Option<String> {
+        if self.display.is_empty() {
+            return None;
+        }
+        self.date(name).map(|date| date.format(&self.display))
+    }
+}
+
+
+/// How the anchors of links to headings are generated
+/// (`[preprocessor.obsidian-links.links.anchors]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AnchorConfig {
+    /// The renderer whose heading IDs the anchors match.
+    pub slugger: Slugger,
+
+    /// Whether runs of hyphens (`foo---bar`) are collapsed into one;
+    /// defaults to what the renderer does.
+    pub collapse_hyphens: Option<bool>,
+
+    /// What happens to letters outside ASCII, like `ü` in `Einführung`.
+    pub non_ascii: NonAsciiMode,
+
+    /// How letters are written in ASCII when `non_ascii` is `transliterate`.
+    pub transliteration: Transliteration,
+
+    /// What happens to links whose anchor matches no heading of their chapter.
+    pub validate: AnchorValidation,
+
+    /// Whether anchors are slugs or the headings percent-encoded.
+    pub output: AnchorOutput,
+
+    /// Regex replacements applied to headings in order, before anything else.
+    pub rules: Vec<AnchorRule>,
+
+    /// A regex of the characters removed from headings, e.g. `[^\w\s-]`;
+    /// defaults to the slugger's.
+    #[serde(deserialize_with = "optional_regex")]
+    pub strip: Option<Regex>,
+
+    /// What whitespace is replaced with; defaults to the slugger's `-` or `+`.
+    pub separator: Option<String>,
+
+    /// The case of the letters; defaults to the slugger's.
+    pub case: Option<AnchorCase>,
+}
+
+
+/// A replacement applied to headings before they are turned into anchors.
+#[derive(Debug, Clone, Deserialize)]
+pub struct AnchorRule {
+    /// The regex to find, e.g. `C\+\+`.
+    #[serde(deserialize_with = "regex")]
+    pub find: Regex,
+
+    /// The text to replace it with, which may refer to groups as `$1`.
+    #[serde(default)]
+    pub replace: String,
+}
+
+
+/// Compile a regex setting while the configuration is read.
+fn regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Regex, D::Error> {
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(|err| serde::de::Error::custom(format!("invalid pattern '{}': {}", pattern, err)))
+}
+
+
+/// Compile an optional regex setting while the configuration is read.
+fn optional_regex<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Regex>, D::Error> {
+    regex(deserializer).map(Some)
+}
+
+
+/// The renderer whose heading IDs anchors match.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Slugger {
+    /// mdBook: ASCII letters lowercased, whitespace as `-`, everything but
+    /// letters, digits, `-` and `_` removed, like emoji.
+    #[default]
+    Mdbook,
+    /// GitHub: all letters lowercased, spaces as `-`, everything but letters,
+    /// combining marks, digits, `-` and connectors like `_` removed.
+    Github,
+    /// GitLab: like GitHub, with runs of `-` collapsed into one by default.
+    Gitlab,
+    /// Obsidian Publish: the heading as written, spaces as `+` and other
+    /// punctuation percent-encoded.
+    ObsidianPublish,
+}
+
+
+/// What happens to letters outside ASCII in anchors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NonAsciiMode {
+    /// Keep them, as mdBook does (`einführung`).
+    #[default]
+    Keep,
+    /// Percent-encode their UTF-8 bytes (`einf%C3%BChrung`).
+    Encode,
+    /// Write them in ASCII (`einfuhrung` or `einfuehrung`).
+    Transliterate,
+}
+
+
+/// How letters outside ASCII are written in ASCII.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Transliteration {
+    /// Without their accents (`ü` as `u`, `ß` as `ss`).
+    #[default]
+    Ascii,
+    /// German umlauts as two letters (`ü` as `ue`), the others without accents.
+    German,
+}
+
+
+/// The form of anchors.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorOutput {
+    /// Characters the renderer leaves out are removed or transliterated.
+    #[default]
+    Slug,
+    /// All characters are kept and the UTF-8 bytes of those other than ASCII
+    /// letters, digits and `-._~` percent-encoded; only case and whitespace
+    /// change.
+    Percent,
+}
+
+
+/// The case of the letters of anchors.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorCase {
+    /// All letters lowercased.
+    Lower,
+    /// All letters uppercased.
+    Upper,
+    /// The letters as written.
+    Keep,
+}
+
+
+/// What happens to links whose anchor matches no heading.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnchorValidation {
+    /// Leave them alone.
+    Off,
+    /// Print a warning for each of them.
+    #[default]
+    Warn,
+    /// Fail the build.
+    Error,
+}
+
+
+/// How links inside headings are converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HeadingLinkMode {
+    /// Convert them to links like everywhere else.
+    #[default]
+    Link,
+    /// Keep only their display text, so headings are plain text.
+    Text,
+}
+
+
+/// Which note a link gets when several notes share its name.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AmbiguityPolicy {
+    /// The note closest to the vault root, then the first by path.
+    #[default]
+    First,
+    /// The note closest to the chapter containing the link.
+    Closest,
+    /// Fail the build.
+    Error,
+}
+
+
+/// What happens to links whose target doesn't exist.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UnresolvedLinkMode {
+    /// Convert them like all other links.
+    #[default]
+    Convert,
+    /// Keep the wikilink as written.
+    Keep,
+    /// Replace them with their display text.
+    Text,
+    /// Render their display text as `<span class="obsidian-unresolved">`.
+    Broken,
+    /// Fail the build.
+    Error,
+}
+
+
+/// How links to draft chapters are shown.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DraftLinkMode {
+    /// Their display text.
+    #[default]
+    Text,
+    /// Their display text as `<span class="obsidian-draft">`.
+    Styled,
+}
+
+
+/// How paths in links are written, like Obsidian's "New link format" setting.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkFormat {
+    /// The shortest path that identifies the note, often just its name.
+    #[default]
+    Shortest,
+    /// Paths relative to the note containing the link.
+    Relative,
+    /// Paths relative to the vault root.
+    Absolute,
+}
+
+
+/// The file names of the published chapters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileNameMode {
+    /// Keep the names of the notes.
+    #[default]
+    Keep,
+    /// Lowercase the names and replace spaces with hyphens (`My Note.md` becomes `my-note.md`).
+    Slug,
+}
+
+
+/// How links inside raw HTML blocks (`<details>`, `<table>`, ...) are handled.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HtmlBlockMode {
+    /// Convert them to Markdown links like everywhere else.
+    #[default]
+    Convert,
+    /// Leave them as written.
+    Skip,
+    /// Convert them to `<a>` elements, which work inside HTML.
+    Html,
+}
+
+
+/// The display text of links without an alias.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LinkDisplay {
+    /// The note name as written in the link.
+    #[default]
+    Name,
+    /// The frontmatter `title` or first H1 of the target note, else the name.
+    Title,
+}
+
+
+/// A source of the display text of links without an alias.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DisplaySource {
+    /// The frontmatter `title` of the target note.
+    Title,
+    /// The first H1 of the target note.
+    Heading,
+    /// The note name as written in the link.
+    Name,
+    /// The file name of the target note, without `.md`.
+    File,
+    /// The path of the target note, without `.md`.
+    Path,
+}
+
+
+/// How the heading of links to a section (`[[Note#Heading]]`) without an
+/// alias shows in their display text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SectionDisplay {
+    /// Only the note, `Note`.
+    #[default]
+    Note,
+    /// The note and the heading like in Obsidian, `Note > Heading`.
+    Obsidian,
+    /// Only the heading, `Heading`; links to blocks show the note.
+    Heading,
+}
+
+
+/// How note names are turned into display text.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HumanizeMode {
+    /// Keep the name as written.
+    #[default]
+    Off,
+    /// Replace underscores and hyphens with spaces.
+    Spaces,
+    /// Replace them with spaces and capitalize every word.
+    Title,
+}
+
+
+/// Settings for transcluded notes (`[preprocessor.obsidian-links.embeds]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct EmbedConfig {
+    /// How far headings of embedded notes are demoted.
+    pub heading_shift: HeadingShift,
+
+    /// The container wrapped around embedded content.
+    pub wrapper: EmbedWrapper,
+}
+
+
+/// The container wrapped around embedded content.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbedWrapper {
+    /// Insert the content as-is.
+    #[default]
+    None,
+    /// Quote the content and add the attribution as the last line.
+    Blockquote,
+    /// Wrap the content in an `<aside>` element.
+    Aside,
+    /// Wrap the content in a collapsible `<details>` element.
+    Details,
+}
+
+
+/// Settings for embedded attachments (`[preprocessor.obsidian-links.media]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct MediaConfig {
+    /// The `width` attribute of embedded videos, e.g. `640` or `"100%"`.
+    pub video_width: Option<Dimension>,
+
+    /// The `preload` attribute of embedded videos (`none`, `metadata` or `auto`).
+    pub video_preload: Option<String>,
+
+    /// Whether embedded videos are muted.
+    pub video_muted: bool,
+}
+
+
+/// Settings for attachments kept outside the src directory
+/// (`[preprocessor.obsidian-links.assets]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct AssetConfig {
+    /// Directories searched for attachments, relative to the book root.
+    pub dirs: Vec<PathBuf>,
+
+    /// The directory inside src that used attachments are copied to.
+    pub copy_to: String,
+}
+
+impl Default for AssetConfig {
+    fn default() -> Self {
+        AssetConfig { dirs: Vec::new(), copy_to: "attachments".to_string() }
+    }
+}
+
+
+/// Settings for callouts (`[preprocessor.obsidian-links.callouts]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CalloutConfig {
+    /// How callouts are converted.
+    pub mode: CalloutMode,
+
+    /// Custom callout types mapped to admonish directives or CSS classes,
+    /// e.g. `theorem = "info"`.
+    pub types: BTreeMap<String, String>,
+
+    /// The directive or class used for unknown types (default `note`).
+    pub fallback: Option<String>,
+}
+
+
+/// How callouts are converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalloutMode {
+    /// Leave callouts as blockquotes.
+    #[default]
+    None,
+    /// Convert callouts to mdbook-admonish blocks.
+    Admonish,
+    /// Convert callouts to HTML styled by the bundled stylesheet.
+    Html,
+}
+
+
+/// Settings for inline formatting (`[preprocessor.obsidian-links.formatting]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FormattingConfig {
+    /// How `==highlights==` are converted.
+    pub highlight: HighlightStyle,
+
+    /// What happens to `%% comments %%`.
+    pub comments: CommentStyle,
+
+    /// What happens to leftover Templater expressions (`<% tp.date.now() %>`).
+    pub templater: TemplaterStyle,
+
+    /// The text Templater expressions are replaced with when stripped.
+    pub templater_replacement: String,
+
+    /// Whether single newlines in paragraphs become line breaks, like in Obsidian.
+    pub line_breaks: bool,
+
+    /// What happens to `<!-- HTML comments -->`.
+    pub html_comments: HtmlCommentStyle,
+}
+
+
+/// How `==highlights==` are converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HighlightStyle {
+    /// Convert to `<mark>text</mark>`.
+    #[default]
+    Mark,
+    /// Convert to `**text**`.
+    Bold,
+    /// Leave highlights unchanged.
+    None,
+}
+
+
+/// What happens to `%% comments %%`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentStyle {
+    /// Remove comments.
+    #[default]
+    Strip,
+    /// Convert comments to HTML comments, hidden but present in the page source.
+    Html,
+    /// Leave comments unchanged.
+    Keep,
+}
+
+
+/// What happens to `<!-- HTML comments -->`. Their content is never converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HtmlCommentStyle {
+    /// Leave comments in the page source.
+    #[default]
+    Keep,
+    /// Remove comments.
+    Strip,
+}
+
+
+/// Settings for `#tags` (`[preprocessor.obsidian-links.tags]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TagConfig {
+    /// How tags are converted.
+    pub mode: TagMode,
+
+    /// The directory of the tag pages, relative to the src directory.
+    pub dir: String,
+
+    /// Whether to generate a page per tag; always done in `link` mode.
+    pub pages: bool,
+
+    /// The title of the part the tag pages are placed under, empty for none.
+    pub part: String,
+}
+
+impl Default for TagConfig {
+    fn default() -> Self {
+        TagConfig { mode: TagMode::default(), dir: "tags".to_string(), pages: false, part: "Tags".to_string() }
+    }
+}
+
+
+/// How `#tags` are converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TagMode {
+    /// Leave tags as text.
+    #[default]
+    Keep,
+    /// Remove tags.
+    Strip,
+    /// Render tags as `<span class="obsidian-tag">` badges.
+    Badge,
+    /// Render tags as badges linking to the tag's page.
+    Link,
+}
+
+
+/// Settings for YAML frontmatter (`[preprocessor.obsidian-links.frontmatter]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct FrontmatterConfig {
+    /// What happens to the frontmatter of chapters.
+    pub mode: FrontmatterMode,
+
+    /// The properties shown in `metadata` mode, in order.
+    pub keys: Vec<String>,
+
+    /// Whether chapters with `publish: false` are removed from the book.
+    pub exclude_unpublished: bool,
+
+    /// The property checked by `exclude-unpublished`.
+    pub publish_key: String,
+}
+
+impl Default for FrontmatterConfig {
+    fn default() -> Self {
+        FrontmatterConfig {
+            mode: FrontmatterMode::default(),
+            keys: ["author", "date", "tags", "status"].iter().map(|k| k.to_string()).collect(),
+            exclude_unpublished: false,
+            publish_key: "publish".to_string(),
+        }
+    }
+}
+
+
+/// What happens to the frontmatter of chapters.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FrontmatterMode {
+    /// Remove the frontmatter.
+    #[default]
+    Strip,
+    /// Leave the frontmatter unchanged.
+    Keep,
+    /// Replace the frontmatter with a list of selected properties.
+    Metadata,
+}
+
+
+/// Settings for Dataview syntax (`[preprocessor.obsidian-links.dataview]`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct DataviewConfig {
+    /// How `key:: value` inline fields are converted.
+    pub inline_fields: InlineFieldStyle,
+
+    /// Whether ` ```dataview ` query blocks are replaced with their result.
+    pub queries: bool,
+}
+
+
+/// How Dataview `key:: value` inline fields are converted.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InlineFieldStyle {
+    /// Leave fields unchanged.
+    #[default]
+    Keep,
+    /// Hide fields in HTML comments.
+    Comment,
+    /// Render fields as `**Key:** value`.
+    Bold,
+    /// Render fields on their own line as definition list entries.
+    Definition,
+}
+
+
+/// What happens to leftover Templater expressions.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TemplaterStyle {
+    /// Replace expressions with `templater-replacement`, by default nothing.
+    Strip,
+    /// Leave expressions unchanged, as `<%` can be ordinary text.
+    #[default]
+    Keep,
+}
+
+
+/// Settings for embedded search blocks (`[preprocessor.obsidian-links.search]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct SearchConfig {
+    /// What happens to ` ```query ` blocks.
+    pub mode: SearchMode,
+
+    /// The title of the placeholder callout.
+    pub title: String,
+}
+
+impl Default for SearchConfig {
+    fn default() -> Self {
+        SearchConfig { mode: SearchMode::default(), title: "Search results".to_string() }
+    }
+}
+
+
+/// What happens to ` ```query ` blocks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchMode {
+    /// Leave the blocks as code blocks.
+    #[default]
+    Keep,
+    /// Remove the blocks.
+    Strip,
+    /// Replace the blocks with a callout naming the query.
+    Placeholder,
+}
+
+
+/// An HTML size attribute given either in pixels or as a CSS value.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum Dimension {
+    /// A size in pixels, e.g. `640`.
+    Pixels(u32),
+    /// Any other value, e.g. `"100%"`.
+    Css(String),
+}
+
+impl std::fmt::Display for Dimension {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Dimension::Pixels(pixels) => write!(f, "{}", pixels),
+            Dimension::Css(value) => write!(f, "{}", value),
+        }
+    }
+}
+
+
+/// How far headings of embedded content are demoted.
+///
+/// In `book.toml` this is either a number of levels or `"auto"`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HeadingShift {
+    /// Demote by a fixed number of levels.
+    Levels(usize),
+    /// Demote so the embedded headings nest below the heading preceding the embed.
+    Auto,
+}
+
+impl Default for HeadingShift {
+    fn default() -> Self {
+        HeadingShift::Levels(0)
+    }
+}
+
+impl<'de> Deserialize<'de> for HeadingShift {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Levels(usize),
+            Mode(String),
+        }
+
+        match Raw::deserialize(deserializer)? {
+            Raw::Levels(levels) => Ok(HeadingShift::Levels(levels)),
+            Raw::Mode(mode) if mode == "auto" => Ok(HeadingShift::Auto),
+            Raw::Mode(mode) => Err(serde::de::Error::custom(format!(
+                "invalid heading-shift '{}', expected a number or \"auto\"", mode
+            ))),
+        }
+    }
+}
+
+
+impl Config {
+    /// Build the configuration from the preprocessor context sent by mdBook.
+    ///
+    /// The source directory is `root` joined with `book.src` (default `src`).
+    ///
+    /// # Arguments
+    /// - `context` - The JSON value of the preprocessor context.
+    ///
+    /// # Returns
+    /// The resolved configuration or an error for invalid settings.
+    pub fn from_context(context: &Value) -> Result<Config> {
+        let mut config = match context.pointer("/config/preprocessor/obsidian-links") {
+            Some(table) => Config::deserialize(table)
+                .context("Invalid [preprocessor.obsidian-links] configuration")?,
+            None => Config::default(),
+        };
+
+        config.root = context
+            .get("root")
+            .and_then(|r| r.as_str())
+            .map(PathBuf::from)
+            .unwrap_or_default();
+        let src = context
+            .pointer("/config/book/src")
+            .and_then(|s| s.as_str())
+            .unwrap_or("src");
+        config.src_dir = config.root.join(src);
+
+        if let Some(file) = &config.links.external_targets {
+            config.links.external_urls = load_url_map(&config.root.join(file))?;
+        }
+
+        // Link settings that aren't configured follow the vault's settings
+        let mut note_settings = Default::default();
+        if let Some(vault_dir) = config.vault_root() {
+            let settings = ObsidianSettings::load(&vault_dir)?;
+            config.links.format = config.links.format.or(settings.link_format());
+            config.links.markdown_links = config.links.markdown_links.or(settings.use_markdown_links);
+            config.excluded = settings.user_ignore_filters.iter().map(|filter| ExcludeFilter::new(filter)).collect::<Result<_>>()?;
+            note_settings = (settings.daily_notes, settings.periodic_notes);
+
+            let vault_dir = paths::normalize(&vault_dir.to_string_lossy().replace('\\', "/"));
+            let src_dir = paths::normalize(&config.src_dir.to_string_lossy().replace('\\', "/"));
+            if let Some(prefix) = src_dir.strip_prefix(&vault_dir).filter(|p| p.is_empty() || p.starts_with('/')) {
+                config.links.vault_prefix = prefix.trim_start_matches('/').to_string();
+            }
+        }
+
+        let (core, periodic) = note_settings;
+        let plugin = periodic.daily.used();
+        let links = &mut config.links;
+        links.daily_notes.fill(core.folder.or(plugin.folder), core.format.or(plugin.format), DAILY_FORMAT, true);
+        for (notes, vault, default_format) in [
+            (&mut links.weekly_notes, periodic.weekly, "gggg-[W]ww"),
+            (&mut links.monthly_notes, periodic.monthly, "YYYY-MM"),
+            (&mut links.quarterly_notes, periodic.quarterly, "YYYY-[Q]Q"),
+            (&mut links.yearly_notes, periodic.yearly, "YYYY"),
+        ] {
+            let vault = vault.used();
+            notes.fill(vault.folder, vault.format, default_format, false);
+        }
+        Ok(config)
+    }
+
+    /// Locate the Obsidian vault root.
+    ///
+    /// Without an explicit `vault-dir`, the src directory and then the book
+    /// root are used if they contain an `.obsidian` folder.
+    ///
+    /// # Returns
+    /// The absolute path of the vault root, if there is a vault.
+    pub fn vault_root(&self) -> Option<PathBuf> {
+        match &self.vault_dir {
+            Some(dir) => Some(self.root.join(dir)),
+            None => [&self.src_dir, &self.root]
+                .into_iter()
+                .find(|dir| dir.join(".obsidian").is_dir())
+                .cloned(),
+        }
+    }
+
+    /// Returns true if a file is matched by the vault's "Excluded files" setting.
+    ///
+    /// Filters are folder or file paths matching everything below them, or
+    /// regexes written as `/pattern/`.
+    ///
+    /// # Arguments
+    /// - `path` - The path relative to the src directory.
+    ///
+    /// # Returns
+    /// Whether the file is excluded.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let path = match self.links.vault_prefix.is_empty() {
+            true => path.to_string(),
+            false => format!("{}/{}", self.links.vault_prefix, path),
+        };
+        self.excluded.iter().any(|filter| filter.matches(&path))
+    }
+}
+
+
+/// Settings for tasks (`[preprocessor.obsidian-links.tasks]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct TaskConfig {
+    /// Whether statuses other than `[ ]` and `[x]` become styled checkboxes.
+    pub statuses: bool,
+
+    /// Custom status characters mapped to the names used in CSS classes,
+    /// e.g. `R = "review"`.
+    pub names: BTreeMap<String, String>,
+}
+
+impl Default for TaskConfig {
+    fn default() -> Self {
+        TaskConfig { statuses: true, names: BTreeMap::new() }
+    }
+}
+
+
+/// Settings for citations (`[preprocessor.obsidian-links.citations]`).
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default, rename_all = "kebab-case")]
+pub struct CitationConfig {
+    /// The BibTeX or CSL-JSON file relative to the book root; citations are
+    /// only converted if it is set.
+    pub bibliography: Option<PathBuf>,
+
+    /// Where the cited works are listed.
+    pub placement: BibliographyPlacement,
+
+    /// The heading of the bibliography.
+    pub title: String,
+
+    /// The path of the bibliography chapter relative to the src directory.
+    pub page: String,
+}
+
+impl Default for CitationConfig {
+    fn default() -> Self {
+        CitationConfig {
+            bibliography: None,
+            placement: BibliographyPlacement::default(),
+            title: "References".to_string(),
+            page: "references.md".to_string(),
+        }
+    }
+}
+
+
+/// Where the works cited in the book are listed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BibliographyPlacement {
+    /// At the end of each chapter citing them.
+    #[default]
+    Chapter,
+    /// In a chapter added to the end of the book.
+    Book,
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_src_dir_from_context() {
+        let context = json!({ "root": "/book", "config": { "book": { "src": "pages" } } });
+        assert_eq!(Config::from_context(&context).unwrap().src_dir, PathBuf::from("/book/pages"));
+
+        let context = json!({ "root": "/book", "config": {} });
+        assert_eq!(Config::from_context(&context).unwrap().src_dir, PathBuf::from("/book/src"));
+    }
+
+    #[test]
+    fn test_url_map() {
+        let dir = std::env::temp_dir().join(format!("obsidian-links-urls-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("urls.toml"), "\"Private/Journal.md\" = \"https://example.com/journal\"\n").unwrap();
+        fs::write(dir.join("urls.json"), r#"{ "Ideas": "https://example.com/ideas" }"#).unwrap();
+
+        let urls = load_url_map(&dir.join("urls.toml")).unwrap();
+        assert_eq!(urls.get("private/journal").map(String::as_str), Some("https://example.com/journal"));
+        let urls = load_url_map(&dir.join("urls.json")).unwrap();
+        assert_eq!(urls.get("ideas").map(String::as_str), Some("https://example.com/ideas"));
+        assert!(load_url_map(&dir.join("missing.toml")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_display_name() {
+        let mut links = LinkConfig { humanize: HumanizeMode::Spaces, ..Default::default() };
+        assert_eq!(links.display_name("api_error_codes"), "api error codes");
+        links.humanize = HumanizeMode::Title;
+        links.acronyms = vec!["API".to_string()];
+        assert_eq!(links.display_name("api_error_codes"), "API Error Codes");
+        assert_eq!(links.display_name("guide/über-uns"), "Guide/Über Uns");
+        links.display_prefix = Some(Regex::new(r"^\d+_").unwrap());
+        assert_eq!(links.display_name("01_getting_started"), "Getting Started");
+    }
+
+    #[test]
+    fn test_daily_notes() {
+        assert_eq!(PeriodicNoteConfig::default().path("2024-05-01"), None);
+        let mut daily = PeriodicNoteConfig::with_format(DAILY_FORMAT);
+        assert_eq!(daily.path("2024-05-01").as_deref(), Some("2024-05-01"));
+        assert_eq!(daily.display_text("2024-05-01"), None);
+
+        daily.folder = Some("/Journal/".to_string());
+        daily.format = Some("YYYY/MM/YYYY-MM-DD".to_string());
+        daily.display = "MMMM D, YYYY".to_string();
+        assert_eq!(daily.path("2024-05-01").as_deref(), Some("Journal/2024/05/2024-05-01"));
+        assert_eq!(daily.path("2024/05/2024-05-01").as_deref(), Some("Journal/2024/05/2024-05-01"));
+        assert_eq!(daily.display_text("2024-05-01").as_deref(), Some("May 1, 2024"));
+        assert_eq!(daily.path("Meeting notes"), None);
+
+        let mut weekly = PeriodicNoteConfig::default();
+        weekly.fill(Some("Reviews".to_string()), None, "gggg-[W]ww", false);
+        assert_eq!(weekly.path("2024-W18").as_deref(), Some("Reviews/2024-W18"));
+        let mut yearly = PeriodicNoteConfig::default();
+        yearly.fill(None, Some(String::new()), "YYYY", false);
+        assert_eq!(yearly.path("2024"), None);
+
+        let links = LinkConfig { monthly_notes: PeriodicNoteConfig::with_format("YYYY-MM"), ..Default::default() };
+        assert_eq!(links.periodic_notes("2024-05").and_then(|notes| notes.format.as_deref()), Some("YYYY-MM"));
+        assert_eq!(links.periodic_notes("2024-05-01").and_then(|notes| notes.format.as_deref()), Some("YYYY-MM-DD"));
+        assert!(links.periodic_notes("2024").is_none());
+    }
+
+    #[test]
+    fn test_excluded_files() {
+        let filters = |filters: &[&str]| filters.iter().map(|filter| ExcludeFilter::new(filter).unwrap()).collect();
+        let mut config = Config { excluded: filters(&["Templates/", "/\\.excalidraw\\.md$/", "Private.md"]), ..Default::default() };
+        assert!(config.is_excluded("Templates/Daily.md"));
+        assert!(config.is_excluded("notes/Drawing.excalidraw.md"));
+        assert!(config.is_excluded("Private.md"));
+        assert!(!config.is_excluded("notes/Templates.md"));
+
+        config.links.vault_prefix = "book/src".to_string();
+        assert!(!config.is_excluded("Templates/Daily.md"));
+        config.excluded = filters(&["book/src/Templates"]);
+        assert!(config.is_excluded("Templates/Daily.md"));
+        assert!(ExcludeFilter::new("/[/").is_err());
+    }
+
+    #[test]
+    fn test_heading_shift() {
+        let with = |shift: Value| json!({ "config": { "preprocessor": { "obsidian-links": {
+            "command": "mdbook-obsidian-links",
+            "embeds": { "heading-shift": shift }
+        } } } });
+
+        let config = Config::from_context(&with(json!(2))).unwrap();
+        assert_eq!(config.embeds.heading_shift, HeadingShift::Levels(2));
+        let config = Config::from_context(&with(json!("auto"))).unwrap();
+        assert_eq!(config.embeds.heading_shift, HeadingShift::Auto);
+        assert!(Config::from_context(&with(json!("sideways"))).is_err());
+    }
+
+    #[test]
+    fn test_anchor_patterns() {
+        let with = |anchors: Value| json!({ "config": { "preprocessor": { "obsidian-links": {
+            "links": { "anchors": anchors }
+        } } } });
+
+        let config = Config::from_context(&with(json!({ "rules": [{ "find": "C\\+\\+", "replace": "cpp" }], "strip": "[.]" }))).unwrap();
+        assert_eq!(config.links.anchors.rules[0].find.replace_all("C++", "cpp"), "cpp");
+        assert!(config.links.anchors.strip.unwrap().is_match("."));
+        assert!(Config::from_context(&with(json!({ "rules": [{ "find": "(" }] }))).is_err());
+        assert!(Config::from_context(&with(json!({ "strip": "[" }))).is_err());
+    }
+
+    #[test]
+    fn test_embed_wrapper() {
+        let context = json!({ "config": { "preprocessor": { "obsidian-links": {
+            "embeds": { "wrapper": "details" }
+        } } } });
+        assert_eq!(Config::from_context(&context).unwrap().embeds.wrapper, EmbedWrapper::Details);
+        assert_eq!(Config::default().embeds.wrapper, EmbedWrapper::None);
+    }
+
+    #[test]
+    fn test_callout_types() {
+        let context = json!({ "config": { "preprocessor": { "obsidian-links": {
+            "callouts": { "mode": "html", "types": { "Theorem": "info" }, "fallback": "tip" }
+        } } } });
+        let callouts = Config::from_context(&context).unwrap().callouts;
+        assert_eq!(callouts.mode, CalloutMode::Html);
+        assert_eq!(callouts.types["Theorem"], "info");
+        assert_eq!(callouts.fallback.as_deref(), Some("tip"));
+    }
+}
@@ -0,0 +1,545 @@
+//! Conversion of Dataview syntax (`key:: value` inline fields, query blocks).
+
+use crate::config::{DataviewConfig, InlineFieldStyle};
+use crate::frontmatter::{self, Frontmatter};
+use crate::markdown::{self, FenceTracker};
+use crate::paths;
+use crate::tags;
+use crate::vault::Vault;
+use crate::warn;
+use regex::{Captures, Regex};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+
+/// An inline field on a line of its own, with the indentation (including any
+/// blockquote or callout markers), list marker, key and value as groups.
+static FIELD_LINE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^((?:[ \t]*>)*[ \t]*)([-*+]\s+)?([\p{L}\p{N}_][\p{L}\p{N}_ -]*?)::(?:\s+(.*?))?\s*$").unwrap());
+
+/// Inline fields in brackets (`[key:: value]`) and parentheses (`(key:: value)`).
+static INLINE_FIELD: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[([\p{L}\p{N}_][^\[\]:]*?)::\s*([^\]]*)\]|\(([\p{L}\p{N}_][^():]*?)::\s*([^)]*)\)").unwrap()
+});
+
+/// The supported subset of the query language.
+static QUERY: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r#"(?is)^\s*(TABLE|LIST)(\s+WITHOUT\s+ID)?(?:\s+(.*?))??(?:\s+FROM\s+(.*?))?(?:\s+WHERE\s+(.*?))?(?:\s+SORT\s+(\S+)(?:\s+(ASC|DESC))?)?(?:\s+LIMIT\s+(\d+))?\s*$"#,
+    )
+    .unwrap()
+});
+
+/// A shown field with a column label (`rating AS "Stars"`).
+static FIELD_ALIAS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(?i)^(.*?)\s+AS\s+"?([^"]*)"?$"#).unwrap());
+
+/// The `or` between alternatives.
+static OR: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s+or\s+").unwrap());
+
+/// The `and` between terms.
+static AND: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)\s+and\s+").unwrap());
+
+/// A `contains(field, value)` condition.
+static CONTAINS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(?i)^contains\(\s*([\w.-]+)\s*,\s*(.+?)\s*\)$").unwrap());
+
+/// A comparison of a field with a value.
+static COMPARISON: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^([\w.-]+)\s*(!=|>=|<=|=|>|<)\s*(.+)$").unwrap());
+
+
+/// Render a single field in the configured style.
+///
+/// # Arguments
+/// - `key` - The field name.
+/// - `value` - The field value.
+/// - `style` - How the field is rendered.
+///
+/// # Returns
+/// The rendered field.
+fn render_field(key: &str, value: &str, style: InlineFieldStyle) -> String {
+    match style {
+        InlineFieldStyle::Comment => format!("<!-- {}:: {} -->", key, value.replace("--", "- -")),
+        InlineFieldStyle::Definition => format!("{}\n: {}", key, value),
+        _ => format!("**{}:** {}", key, value),
+    }
+}
+
+
+/// Convert Dataview inline fields outside of code according to the configured style.
+///
+/// Fields on their own line (`Key:: value`, also as list item) and inline
+/// fields in brackets (`[key:: value]`) are converted. Fields in parentheses
+/// (`(key:: value)`) show only their value, like in Dataview. Definition lists
+/// need a line of their own, so inline fields use bold keys instead.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `config` - The Dataview settings.
+///
+/// # Returns
+/// The content with converted fields.
+pub fn convert_inline_fields(content: &str, config: &DataviewConfig) -> String {
+    let style = config.inline_fields;
+    if style == InlineFieldStyle::Keep {
+        return content.to_string();
+    }
+
+    let inline_style = if style == InlineFieldStyle::Definition { InlineFieldStyle::Bold } else { style };
+
+    let mut fences = FenceTracker::default();
+    let mut lines: Vec<String> = Vec::new();
+    let mut needs_blank = false;
+    for line in content.split('\n') {
+        if fences.is_code(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        if let Some(caps) = FIELD_LINE.captures(line) {
+            let (indent, item, key) = (&caps[1], caps.get(2).map_or("", |m| m.as_str()), &caps[3]);
+            let value = caps.get(4).map_or("", |m| m.as_str());
+            match style {
+                InlineFieldStyle::Definition if item.is_empty() && indent.is_empty() => {
+                    if lines.last().is_some_and(|l| !l.trim().is_empty()) {
+                        lines.push(String::new());
+                    }
+                    lines.push(render_field(key, value, style));
+                    needs_blank = true;
+                }
+                InlineFieldStyle::Comment => lines.push(format!("{}{}", indent, render_field(key, value, style))),
+                _ => lines.push(format!("{}{}{}", indent, item, render_field(key, value, inline_style))),
+            }
+            continue;
+        }
+
+        if needs_blank && !line.trim().is_empty() {
+            lines.push(String::new());
+        }
+        needs_blank = false;
+
+        let converted: String = markdown::code_spans(line)
+            .into_iter()
+            .map(|(code, part)| match code {
+                true => part.to_string(),
+                false => INLINE_FIELD
+                    .replace_all(part, |caps: &Captures| match caps.get(1) {
+                        Some(key) => render_field(key.as_str().trim(), caps[2].trim(), inline_style),
+                        None if inline_style == InlineFieldStyle::Comment => {
+                            render_field(caps[3].trim(), caps[4].trim(), inline_style)
+                        }
+                        None => caps[4].trim().to_string(),
+                    })
+                    .into_owned(),
+            })
+            .collect();
+        lines.push(converted);
+    }
+    lines.join("\n")
+}
+
+
+/// A note as seen by Dataview queries.
+#[derive(Debug)]
+pub struct Page {
+    /// The path relative to the vault root.
+    path: String,
+    /// The frontmatter properties and inline fields, by lowercased name.
+    fields: HashMap<String, Vec<String>>,
+    /// The lowercased tags without `#`.
+    tags: Vec<String>,
+}
+
+impl Page {
+    /// Collect the fields and tags of a note.
+    ///
+    /// # Arguments
+    /// - `path` - The path of the note.
+    /// - `content` - The note content.
+    ///
+    /// # Returns
+    /// The page.
+    pub fn new(path: &str, content: &str) -> Page {
+        let (yaml, body) = frontmatter::split(content);
+        let frontmatter = yaml.map(Frontmatter::parse).unwrap_or_default();
+
+        let mut fields: HashMap<String, Vec<String>> = HashMap::new();
+        for (key, values) in frontmatter.entries() {
+            fields.entry(key.to_lowercase()).or_default().extend(values.iter().cloned());
+        }
+        let mut fences = FenceTracker::default();
+        for line in body.lines() {
+            if let (false, Some(caps)) = (fences.is_code(line), FIELD_LINE.captures(line)) {
+                let value = caps.get(4).map_or("", |m| m.as_str());
+                fields.entry(caps[3].to_lowercase()).or_default().push(value.to_string());
+            }
+        }
+
+        let mut tags: Vec<String> = fields
+            .get("tags")
+            .into_iter()
+            .flatten()
+            .map(|t| t.trim_start_matches('#').to_lowercase())
+            .collect();
+        tags.extend(tags::find_tags(body).iter().map(|t| t.to_lowercase()));
+
+        Page { path: path.to_string(), fields, tags }
+    }
+
+    /// Get the values of a field; `file.*` fields describe the note itself.
+    ///
+    /// # Arguments
+    /// - `name` - The field name, e.g. `file.name` or `status`.
+    ///
+    /// # Returns
+    /// The values of the field, empty if it isn't set.
+    fn field(&self, name: &str) -> Vec<String> {
+        let stem = paths::strip_note_extension(&self.path);
+        let file_name = stem.rsplit('/').next().unwrap_or(stem);
+        match name.to_lowercase().as_str() {
+            "file.name" => vec![file_name.to_string()],
+            "file.path" => vec![self.path.clone()],
+            "file.folder" => vec![stem.rsplit_once('/').map_or("", |(folder, _)| folder).to_string()],
+            "file.link" => vec![format!("[[{}|{}]]", stem, file_name)],
+            "file.tags" => self.tags.iter().map(|t| format!("#{}", t)).collect(),
+            other => self.fields.get(other).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Check whether the page has a tag or one of its nested tags.
+    fn has_tag(&self, tag: &str) -> bool {
+        let tag = tag.trim_start_matches('#').to_lowercase();
+        self.tags.iter().any(|t| *t == tag || t.starts_with(&format!("{}/", tag)))
+    }
+}
+
+
+/// A parsed `TABLE` or `LIST` query.
+#[derive(Debug, PartialEq)]
+struct Query {
+    /// Whether the query is a `LIST`.
+    list: bool,
+    /// Whether the file column is omitted (`WITHOUT ID`).
+    without_id: bool,
+    /// The shown fields and their column labels.
+    fields: Vec<(String, String)>,
+    /// The `FROM` source, if any.
+    from: Option<String>,
+    /// The `WHERE` condition, if any.
+    filter: Option<String>,
+    /// The `SORT` field and whether the order is descending.
+    sort: Option<(String, bool)>,
+    /// The `LIMIT`, if any.
+    limit: Option<usize>,
+}
+
+
+/// Parse the supported subset of the Dataview query language.
+///
+/// # Arguments
+/// - `text` - The content of the query block.
+///
+/// # Returns
+/// The query, or `None` if it isn't supported.
+fn parse_query(text: &str) -> Option<Query> {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let caps = QUERY.captures(&text)?;
+
+    let fields = caps
+        .get(3)
+        .map(|m| m.as_str())
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|f| !f.is_empty())
+        .map(|field| match FIELD_ALIAS.captures(field) {
+            Some(alias) => (alias[1].trim().to_string(), alias[2].to_string()),
+            None => (field.to_string(), field.to_string()),
+        })
+        .collect();
+
+    Some(Query {
+        list: caps[1].eq_ignore_ascii_case("list"),
+        without_id: caps.get(2).is_some(),
+        fields,
+        from: caps.get(4).map(|m| m.as_str().to_string()),
+        filter: caps.get(5).map(|m| m.as_str().to_string()),
+        sort: caps.get(6).map(|m| (m.as_str().to_string(), caps.get(7).is_some_and(|o| o.as_str().eq_ignore_ascii_case("desc")))),
+        limit: caps.get(8).and_then(|m| m.as_str().parse().ok()),
+    })
+}
+
+
+/// Split an expression into alternatives of conjunctions (`a and b or c`).
+///
+/// # Arguments
+/// - `expr` - The expression.
+///
+/// # Returns
+/// The terms, grouped by `or` first and `and` second.
+fn split_logic(expr: &str) -> Vec<Vec<String>> {
+    OR.split(expr)
+        .map(|group| AND.split(group).map(|t| t.trim().to_string()).collect())
+        .collect()
+}
+
+
+/// Remove the quotes around a literal.
+fn literal(value: &str) -> &str {
+    let value = value.trim();
+    value.strip_prefix('"').and_then(|v| v.strip_suffix('"')).unwrap_or(value)
+}
+
+
+/// Compare two values numerically if both are numbers, else as strings.
+fn compare(a: &str, b: &str) -> Ordering {
+    match (a.parse::<f64>(), b.parse::<f64>()) {
+        (Ok(a), Ok(b)) => a.total_cmp(&b),
+        _ => a.cmp(b),
+    }
+}
+
+
+/// Check whether a page belongs to a `FROM` source (`#tag`, `"folder"`).
+///
+/// # Arguments
+/// - `page` - The page.
+/// - `source` - The source expression.
+///
+/// # Returns
+/// Whether the page matches.
+fn matches_source(page: &Page, source: &str) -> bool {
+    split_logic(source).iter().any(|group| {
+        group.iter().all(|term| {
+            let (negated, term) = match term.strip_prefix('-') {
+                Some(term) => (true, term),
+                None => (false, term.as_str()),
+            };
+            let matched = match term.strip_prefix('#') {
+                Some(tag) => page.has_tag(tag),
+                None => {
+                    let folder = literal(term).trim_matches('/');
+                    page.path.starts_with(&format!("{}/", folder)) || paths::strip_note_extension(&page.path) == folder
+                }
+            };
+            matched != negated
+        })
+    })
+}
+
+
+/// Check whether a page fulfills a `WHERE` condition.
+///
+/// Supported are comparisons (`status = "done"`, `rating >= 3`),
+/// `contains(field, "value")`, and bare fields checked for being set.
+///
+/// # Arguments
+/// - `page` - The page.
+/// - `condition` - The condition expression.
+///
+/// # Returns
+/// Whether the page matches.
+fn matches_condition(page: &Page, condition: &str) -> bool {
+    split_logic(condition).iter().any(|group| {
+        group.iter().all(|term| {
+            let (negated, term) = match term.strip_prefix('!') {
+                Some(term) => (true, term.trim()),
+                None => (false, term.as_str()),
+            };
+            let values = |field: &str| page.field(field);
+            let matched = if let Some(caps) = CONTAINS.captures(term) {
+                values(&caps[1]).iter().any(|v| v.contains(literal(&caps[2])))
+            } else if let Some(caps) = COMPARISON.captures(term) {
+                let (values, expected) = (values(&caps[1]), literal(&caps[3]));
+                match &caps[2] {
+                    "=" => values.iter().any(|v| compare(v, expected) == Ordering::Equal),
+                    "!=" => values.iter().all(|v| compare(v, expected) != Ordering::Equal),
+                    op => values.first().is_some_and(|v| {
+                        let ordering = compare(v, expected);
+                        match op {
+                            ">" => ordering == Ordering::Greater,
+                            "<" => ordering == Ordering::Less,
+                            ">=" => ordering != Ordering::Less,
+                            _ => ordering != Ordering::Greater,
+                        }
+                    }),
+                }
+            } else {
+                values(term).iter().any(|v| !v.is_empty() && v != "false")
+            };
+            matched != negated
+        })
+    })
+}
+
+
+/// Run a query against the notes and render the result as Markdown.
+///
+/// Links to the notes are written as wikilinks, which the link pass converts.
+///
+/// # Arguments
+/// - `query` - The query.
+/// - `pages` - All notes of the vault.
+///
+/// # Returns
+/// A table for `TABLE` queries, a list for `LIST` queries.
+fn render_query(query: &Query, pages: &[Page]) -> String {
+    let mut results: Vec<&Page> = pages
+        .iter()
+        .filter(|p| query.from.as_deref().is_none_or(|from| matches_source(p, from)))
+        .filter(|p| query.filter.as_deref().is_none_or(|filter| matches_condition(p, filter)))
+        .collect();
+    if let Some((field, descending)) = &query.sort {
+        results.sort_by(|a, b| {
+            let ordering = compare(&a.field(field).join(", "), &b.field(field).join(", "));
+            if *descending { ordering.reverse() } else { ordering }
+        });
+    }
+    results.truncate(query.limit.unwrap_or(usize::MAX));
+
+    if results.is_empty() {
+        return "*No results*".to_string();
+    }
+    let cell = |page: &Page, field: &str| match field {
+        "file.link" => page.field(field).join(""),
+        _ => page.field(field).join(", ").replace('|', "\\|").replace('\n', " "),
+    };
+
+    if query.list {
+        return results
+            .iter()
+            .map(|page| {
+                let link = cell(page, "file.link");
+                match query.fields.first() {
+                    Some((field, _)) if query.without_id => format!("- {}", cell(page, field)),
+                    Some((field, _)) => format!("- {}: {}", link, cell(page, field)),
+                    None => format!("- {}", link),
+                }
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+    }
+
+    let mut columns: Vec<(&str, &str)> = Vec::new();
+    if !query.without_id {
+        columns.push(("file.link", "File"));
+    }
+    columns.extend(query.fields.iter().map(|(field, label)| (field.as_str(), label.as_str())));
+
+    let mut table = vec![
+        format!("| {} |", columns.iter().map(|(_, label)| *label).collect::<Vec<_>>().join(" | ")),
+        format!("|{}", " --- |".repeat(columns.len())),
+    ];
+    for page in results {
+        let cells: Vec<String> = columns.iter().map(|(field, _)| cell(page, field)).collect();
+        table.push(format!("| {} |", cells.join(" | ")));
+    }
+    table.join("\n")
+}
+
+
+/// Replace ` ```dataview ` query blocks with their static result.
+///
+/// Queries that aren't supported are reported and left as code blocks.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `vault` - The vault whose notes are queried.
+/// - `config` - The Dataview settings.
+///
+/// # Returns
+/// The content with rendered queries.
+pub fn render_queries(content: &str, vault: &Vault, config: &DataviewConfig) -> String {
+    if !config.queries || !content.contains("```dataview") {
+        return content.to_string();
+    }
+
+    let pages = vault.pages();
+    markdown::map_code_blocks(content, "dataview", |text| match parse_query(text) {
+        Some(query) => Some(render_query(&query, &pages)),
+        None => {
+            warn(&format!("Unsupported Dataview query '{}'", text.trim()));
+            None
+        }
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_style(style: InlineFieldStyle) -> DataviewConfig {
+        DataviewConfig { inline_fields: style, ..Default::default() }
+    }
+
+    #[test]
+    fn test_inline_fields() {
+        let input = "Author:: Jane\n- Status:: draft\nRated [rating:: 5] (mood:: happy) `[code:: x]`\n```\nKey:: code\n```\nstd::vector";
+        assert_eq!(
+            convert_inline_fields(input, &with_style(InlineFieldStyle::Bold)),
+            "**Author:** Jane\n- **Status:** draft\nRated **rating:** 5 happy `[code:: x]`\n```\nKey:: code\n```\nstd::vector"
+        );
+        assert_eq!(
+            convert_inline_fields("Intro\nAuthor:: Jane\nDate:: today\nText", &with_style(InlineFieldStyle::Definition)),
+            "Intro\n\nAuthor\n: Jane\n\nDate\n: today\n\nText"
+        );
+        assert_eq!(
+            convert_inline_fields("> Status:: done\n> [!note]\n> - Key:: value", &with_style(InlineFieldStyle::Definition)),
+            "> **Status:** done\n> [!note]\n> - **Key:** value"
+        );
+        assert_eq!(
+            convert_inline_fields(">> Key:: value", &with_style(InlineFieldStyle::Comment)),
+            ">> <!-- Key:: value -->"
+        );
+        assert_eq!(
+            convert_inline_fields("Author:: Jane\nSee [due:: soon]", &with_style(InlineFieldStyle::Comment)),
+            "<!-- Author:: Jane -->\nSee <!-- due:: soon -->"
+        );
+        assert_eq!(convert_inline_fields("Author:: Jane", &DataviewConfig::default()), "Author:: Jane");
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = parse_query("TABLE rating AS \"Stars\", status\nFROM #recipe WHERE rating >= 3 SORT rating DESC LIMIT 5").unwrap();
+        assert_eq!(
+            query,
+            Query {
+                list: false,
+                without_id: false,
+                fields: vec![("rating".to_string(), "Stars".to_string()), ("status".to_string(), "status".to_string())],
+                from: Some("#recipe".to_string()),
+                filter: Some("rating >= 3".to_string()),
+                sort: Some(("rating".to_string(), true)),
+                limit: Some(5),
+            }
+        );
+        assert!(parse_query("LIST").unwrap().list);
+        assert_eq!(parse_query("TASK FROM #x"), None);
+    }
+
+    #[test]
+    fn test_render_queries() {
+        let vault = Vault::from_notes(&[
+            ("recipes/soup.md", "---\ntags: [recipe]\nrating: 4\n---\n# Soup"),
+            ("recipes/cake.md", "---\nrating: 5\n---\n#recipe/dessert\nstatus:: done"),
+            ("recipes/toast.md", "---\ntags: [recipe]\nrating: 2\n---\n"),
+            ("notes/other.md", "rating:: 5"),
+            ("notes/x.markdown", "rating:: 1"),
+        ]);
+        let config = DataviewConfig { queries: true, ..Default::default() };
+
+        let table = "```dataview\nTABLE rating, status FROM #recipe WHERE rating > 3 SORT rating DESC\n```";
+        assert_eq!(
+            render_queries(table, &vault, &config),
+            "| File | rating | status |\n| --- | --- | --- |\n\
+             | [[recipes/cake|cake]] | 5 | done |\n| [[recipes/soup|soup]] | 4 |  |"
+        );
+        let list = "```dataview\nLIST FROM \"notes\"\n```";
+        assert_eq!(render_queries(list, &vault, &config), "- [[notes/other|other]]\n- [[notes/x|x]]");
+        let single = "```dataview\nLIST file.name FROM \"notes/x\"\n```";
+        assert_eq!(render_queries(single, &vault, &config), "- [[notes/x|x]]: x");
+        assert_eq!(render_queries(list, &vault, &DataviewConfig::default()), list);
+        let example = format!("````md\n{}\n````", list);
+        assert_eq!(render_queries(&example, &vault, &config), example);
+    }
+}
@@ -0,0 +1,293 @@
+//! Dates in the Moment.js formats Obsidian names daily notes with.
+
+const MONTHS: [&str; 12] = [
+    "January", "February", "March", "April", "May", "June",
+    "July", "August", "September", "October", "November", "December",
+];
+
+const WEEKDAYS: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+/// The tokens of a format, longest first so `MMMM` wins over `MM`.
+const TOKENS: [&str; 18] = [
+    "YYYY", "GGGG", "gggg", "MMMM", "dddd", "MMM", "ddd", "YY", "MM", "Do", "DD", "WW", "ww", "M", "D", "W", "w", "Q",
+];
+
+
+/// A calendar date.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Date {
+    pub year: i32,
+    pub month: u32,
+    pub day: u32,
+}
+
+
+/// A part of a date format: a token such as `YYYY` or literal text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Token(&'static str),
+    Literal(String),
+}
+
+
+/// Split a Moment.js format into tokens and literal text.
+///
+/// Text in square brackets (`[W]`) is always literal.
+///
+/// # Arguments
+/// - `format` - The format, e.g. `YYYY-[W]WW`.
+///
+/// # Returns
+/// The parts of the format in order.
+fn tokenize(format: &str) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut literal = String::new();
+    let mut rest = format;
+    while let Some(c) = rest.chars().next() {
+        if c == '[' && let Some(end) = rest.find(']') {
+            literal.push_str(&rest[1..end]);
+            rest = &rest[end + 1..];
+        } else if let Some(token) = TOKENS.iter().find(|t| rest.starts_with(**t)) {
+            if !literal.is_empty() {
+                parts.push(Part::Literal(std::mem::take(&mut literal)));
+            }
+            parts.push(Part::Token(token));
+            rest = &rest[token.len()..];
+        } else {
+            literal.push(c);
+            rest = &rest[c.len_utf8()..];
+        }
+    }
+    if !literal.is_empty() {
+        parts.push(Part::Literal(literal));
+    }
+    parts
+}
+
+
+/// Count the days since 1970-01-01.
+fn days_from_civil(year: i32, month: u32, day: u32) -> i64 {
+    let year = i64::from(year) - i64::from(month <= 2);
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month = i64::from(month);
+    let day_of_year = (153 * (month + if month > 2 { -3 } else { 9 }) + 2) / 5 + i64::from(day) - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146097 + day_of_era - 719468
+}
+
+
+/// Get the date a number of days after 1970-01-01.
+fn civil_from_days(days: i64) -> Date {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month_index + 2) / 5 + 1) as u32;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 } as u32;
+    let year = (year_of_era + era * 400 + i64::from(month <= 2)) as i32;
+    Date { year, month, day }
+}
+
+
+impl Date {
+    /// Create a date if it exists in the calendar.
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Date> {
+        let date = Date { year, month, day };
+        let valid = (1..=12).contains(&month) && day >= 1 && civil_from_days(date.days()) == date;
+        valid.then_some(date)
+    }
+
+    /// Count the days since 1970-01-01.
+    fn days(&self) -> i64 {
+        days_from_civil(self.year, self.month, self.day)
+    }
+
+    /// Get the day of the week, 0 for Monday.
+    pub fn weekday(&self) -> u32 {
+        (self.days() + 3).rem_euclid(7) as u32
+    }
+
+    /// Get the ISO 8601 week-numbering year and week.
+    ///
+    /// # Returns
+    /// The year the week's Thursday is in, and the week from 1 to 53.
+    pub fn iso_week(&self) -> (i32, u32) {
+        let thursday = self.days() - i64::from(self.weekday()) + 3;
+        let year = civil_from_days(thursday).year;
+        let week = (thursday - days_from_civil(year, 1, 1)) / 7 + 1;
+        (year, week as u32)
+    }
+
+    /// Get the Monday of an ISO 8601 week.
+    ///
+    /// # Arguments
+    /// - `year` - The week-numbering year.
+    /// - `week` - The week from 1 to 53.
+    ///
+    /// # Returns
+    /// The Monday, if the year has the week.
+    pub fn from_iso_week(year: i32, week: u32) -> Option<Date> {
+        let jan4 = days_from_civil(year, 1, 4);
+        let monday = jan4 - (jan4 + 3).rem_euclid(7) + (i64::from(week) - 1) * 7;
+        let date = civil_from_days(monday);
+        (week >= 1 && date.iso_week() == (year, week)).then_some(date)
+    }
+
+    /// Write the date in a Moment.js format.
+    ///
+    /// Supported are `YYYY`, `YY`, `Q`, `MMMM`, `MMM`, `MM`, `M`, `Do`, `DD`,
+    /// `D`, `dddd`, `ddd`, ISO weeks as `GGGG`, `WW`, `W` (and their locale
+    /// variants `gggg`, `ww`, `w`, treated the same) and `[literal]` text.
+    ///
+    /// # Examples
+    /// `MMMM D, YYYY` gives `May 1, 2024`
+    ///
+    /// # Arguments
+    /// - `format` - The format.
+    ///
+    /// # Returns
+    /// The formatted date.
+    pub fn format(&self, format: &str) -> String {
+        let (week_year, week) = self.iso_week();
+        let month = MONTHS[self.month as usize - 1];
+        let weekday = WEEKDAYS[self.weekday() as usize];
+        tokenize(format)
+            .into_iter()
+            .map(|part| match part {
+                Part::Literal(text) => text,
+                Part::Token(token) => match token {
+                    "YYYY" => format!("{:04}", self.year),
+                    "YY" => format!("{:02}", self.year.rem_euclid(100)),
+                    "GGGG" | "gggg" => format!("{:04}", week_year),
+                    "Q" => ((self.month - 1) / 3 + 1).to_string(),
+                    "MMMM" => month.to_string(),
+                    "MMM" => month[..3].to_string(),
+                    "MM" => format!("{:02}", self.month),
+                    "M" => self.month.to_string(),
+                    "Do" => format!("{}{}", self.day, ordinal_suffix(self.day)),
+                    "DD" => format!("{:02}", self.day),
+                    "D" => self.day.to_string(),
+                    "dddd" => weekday.to_string(),
+                    "ddd" => weekday[..3].to_string(),
+                    "WW" | "ww" => format!("{:02}", week),
+                    _ => week.to_string(),
+                },
+            })
+            .collect()
+    }
+
+    /// Read a date written in a Moment.js format.
+    ///
+    /// Formats without a day give the first day of their month, quarter or
+    /// year, week formats the Monday of the week.
+    ///
+    /// # Arguments
+    /// - `text` - The text, e.g. `2024-05-01`.
+    /// - `format` - The format, e.g. `YYYY-MM-DD`.
+    ///
+    /// # Returns
+    /// The date, if the whole text matches the format.
+    pub fn parse(text: &str, format: &str) -> Option<Date> {
+        let (mut year, mut week_year, mut month, mut day, mut week) = (None, None, None, None, None);
+        let mut rest = text;
+        for part in tokenize(format) {
+            let token = match part {
+                Part::Literal(literal) => {
+                    rest = rest.strip_prefix(literal.as_str())?;
+                    continue;
+                }
+                Part::Token(token) => token,
+            };
+            match token {
+                "MMMM" | "MMM" | "dddd" | "ddd" => {
+                    let full = token.len() == 4;
+                    let names: &[&str] = if token.starts_with('M') { &MONTHS } else { &WEEKDAYS };
+                    let (index, len) = names.iter().enumerate().find_map(|(i, name)| {
+                        let name = if full { name } else { &name[..3] };
+                        rest.get(..name.len()).filter(|r| r.eq_ignore_ascii_case(name)).map(|_| (i, name.len()))
+                    })?;
+                    if token.starts_with('M') {
+                        month = Some(index as u32 + 1);
+                    }
+                    rest = &rest[len..];
+                }
+                _ => {
+                    let (min, max) = match token {
+                        "YYYY" | "GGGG" | "gggg" => (4, 4),
+                        "YY" | "MM" | "DD" | "WW" | "ww" => (2, 2),
+                        "Q" => (1, 1),
+                        _ => (1, 2),
+                    };
+                    let digits = rest.chars().take(max).take_while(char::is_ascii_digit).count();
+                    if digits < min {
+                        return None;
+                    }
+                    let value: u32 = rest[..digits].parse().ok()?;
+                    rest = &rest[digits..];
+                    if token == "Do" {
+                        rest = rest.strip_prefix(ordinal_suffix(value))?;
+                    }
+                    match token {
+                        "YYYY" => year = Some(value as i32),
+                        "YY" => year = Some(2000 + value as i32),
+                        "GGGG" | "gggg" => week_year = Some(value as i32),
+                        "Q" => month = Some(value.checked_sub(1)? * 3 + 1).filter(|_| value <= 4),
+                        "MM" | "M" => month = Some(value),
+                        "WW" | "ww" | "W" | "w" => week = Some(value),
+                        _ => day = Some(value),
+                    }
+                }
+            }
+        }
+        if !rest.is_empty() {
+            return None;
+        }
+
+        match week {
+            Some(week) => Date::from_iso_week(week_year.or(year)?, week),
+            None => Date::new(year.or(week_year)?, month.unwrap_or(1), day.unwrap_or(1)),
+        }
+    }
+}
+
+
+/// Get the English ordinal suffix of a day, e.g. `st` for 1.
+fn ordinal_suffix(day: u32) -> &'static str {
+    match (day % 10, day % 100) {
+        (_, 11..=13) => "th",
+        (1, _) => "st",
+        (2, _) => "nd",
+        (3, _) => "rd",
+        _ => "th",
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let date = Date::new(2024, 5, 1).unwrap();
+        assert_eq!(date.format("YYYY-MM-DD"), "2024-05-01");
+        assert_eq!(date.format("MMMM D, YYYY"), "May 1, 2024");
+        assert_eq!(date.format("dddd, Do MMM YY"), "Wednesday, 1st May 24");
+        assert_eq!(date.format("GGGG-[W]WW"), "2024-W18");
+        assert_eq!(Date::new(2021, 1, 3).unwrap().format("GGGG-[W]WW [Q]Q"), "2020-W53 Q1");
+    }
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(Date::parse("2024-05-01", "YYYY-MM-DD"), Date::new(2024, 5, 1));
+        assert_eq!(Date::parse("May 1st, 2024", "MMMM Do, YYYY"), Date::new(2024, 5, 1));
+        assert_eq!(Date::parse("2024-W18", "GGGG-[W]WW"), Date::new(2024, 4, 29));
+        assert_eq!(Date::parse("2024-05", "YYYY-MM"), Date::new(2024, 5, 1));
+        assert_eq!(Date::parse("2024-02-30", "YYYY-MM-DD"), None);
+        assert_eq!(Date::parse("2024-05-01 Notes", "YYYY-MM-DD"), None);
+        assert_eq!(Date::parse("Meeting", "YYYY-MM-DD"), None);
+    }
+}
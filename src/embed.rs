@@ -0,0 +1,641 @@
+//! Transclusion of Obsidian embeds (`![[note]]`).
+
+use crate::blocks;
+use crate::config::{Config, EmbedWrapper, HeadingShift};
+use crate::frontmatter;
+use crate::markdown::{self, FenceTracker};
+use crate::media;
+use crate::paths;
+use crate::vault::Vault;
+use crate::warn;
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+
+/// An embed (`![[note#section|alias]]`) with the target, fragment and alias as groups.
+static EMBED: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"!\[\[([^#\|\]]+?)\\?(?:#([^\|\]]+?)\\?)?(?:\|([^\]]*))?\]\]").unwrap());
+
+
+/// Returns true if the embed target refers to a Markdown note.
+///
+/// Targets without an extension and targets ending in `.md` or `.markdown`
+/// are notes;
+/// everything else (images, PDFs, ...) is an attachment. Excalidraw drawings
+/// are attachments even when stored as `.excalidraw.md`.
+///
+/// # Arguments
+/// - `target` - The embed target as written in the wikilink.
+///
+/// # Returns
+/// Whether the target is a note.
+fn is_note_target(target: &str) -> bool {
+    match paths::extension(target) {
+        Some(_) => paths::is_note_file(target) && !target.ends_with(".excalidraw.md"),
+        None => true,
+    }
+}
+
+
+/// Extract the section under a heading from a note.
+///
+/// The section starts with the heading line and ends before the next heading
+/// of equal or higher level. Headings are matched case-insensitively.
+///
+/// # Arguments
+/// - `note` - The content of the note.
+/// - `title` - The heading text to look for.
+///
+/// # Returns
+/// The section including its heading, or `None` if the heading doesn't exist.
+fn extract_section(note: &str, title: &str) -> Option<String> {
+    let title = title.trim().to_lowercase();
+    let mut fences = FenceTracker::default();
+    let mut section: Vec<&str> = Vec::new();
+    let mut level = None;
+
+    for line in note.lines() {
+        let heading = if fences.is_code(line) { None } else { markdown::heading(line) };
+        match (level, heading) {
+            (None, Some((l, text))) if text.to_lowercase() == title => {
+                level = Some(l);
+                section.push(line);
+            }
+            (Some(current), Some((l, _))) if l <= current => break,
+            (Some(_), _) => section.push(line),
+            (None, _) => {}
+        }
+    }
+
+    level.map(|_| section.join("\n").trim_end().to_string())
+}
+
+
+/// Extract the block carrying a block ID (`^id`) from a note.
+///
+/// The block is the list item or paragraph whose last line ends with `^id`.
+/// An ID on a line of its own refers to the block above it, which is how
+/// Obsidian marks quotes, tables and other structured blocks.
+/// The ID marker itself is removed from the result.
+///
+/// # Arguments
+/// - `note` - The content of the note.
+/// - `id` - The block ID without the leading caret.
+///
+/// # Returns
+/// The block, or `None` if no block carries the ID.
+pub fn extract_block(note: &str, id: &str) -> Option<String> {
+    let marker = format!("^{}", id.trim());
+    let lines: Vec<&str> = note.lines().collect();
+    let mut fences = FenceTracker::default();
+    let code: Vec<bool> = lines.iter().map(|line| fences.is_code(line)).collect();
+
+    let index = (0..lines.len()).find(|&i| {
+        let line = lines[i].trim_end();
+        !code[i] && (line.trim_start() == marker || line.ends_with(&format!(" {}", marker)))
+    })?;
+
+    let (end, last) = if lines[index].trim() == marker {
+        let end = (0..index).rev().find(|&i| !lines[i].trim().is_empty())?;
+        (end, lines[end].to_string())
+    } else {
+        (index, lines[index].trim_end().trim_end_matches(&marker).trim_end().to_string())
+    };
+
+    if markdown::is_list_item(&last) {
+        return Some(last.trim_start().to_string());
+    }
+
+    let start = (0..end)
+        .rev()
+        .find(|&i| lines[i].trim().is_empty())
+        .map_or(0, |i| i + 1);
+    let mut block: Vec<String> = lines[start..end].iter().map(|l| l.to_string()).collect();
+    block.push(last);
+    Some(block.join("\n"))
+}
+
+
+/// Find the containers a line opens: blockquotes and callouts (`>`), list
+/// items and footnote definitions.
+///
+/// # Examples
+/// `> - item` gives the prefix `>   `, `[^1]: text` gives four spaces
+///
+/// # Arguments
+/// - `line` - The line up to the embed.
+///
+/// # Returns
+/// The length of the container markers and the prefix that continues them
+/// on the following lines.
+fn container_prefix(line: &str) -> (usize, String) {
+    let mut prefix = String::new();
+    let mut length = 0;
+    loop {
+        let rest = &line[length..];
+        let indent = rest.len() - rest.trim_start_matches([' ', '\t']).len();
+        let marked = &rest[indent..];
+        let footnote = marked.starts_with("[^").then(|| marked.find("]:")).flatten();
+        let (marker, continuation) = if marked.starts_with('>') {
+            let marker = if marked.starts_with("> ") { 2 } else { 1 };
+            (indent + marker, format!("{}> ", &rest[..indent]))
+        } else if markdown::is_list_item(marked) {
+            let marker = marked.find(' ').unwrap_or(0) + 1;
+            (indent + marker, " ".repeat(indent + marker))
+        } else if let Some(end) = footnote.filter(|_| length == 0) {
+            let marker = if marked[end + 2..].starts_with(' ') { end + 3 } else { end + 2 };
+            (indent + marker, "    ".to_string())
+        } else {
+            prefix.push_str(&rest[..indent]);
+            return (length + indent, prefix);
+        };
+        length += marker;
+        prefix.push_str(&continuation);
+    }
+}
+
+
+/// Prefix all lines but the first, so they continue the blockquote, list
+/// item or footnote definition the first line is part of.
+///
+/// # Arguments
+/// - `content` - The content to prefix.
+/// - `prefix` - The prefix of the containers, from [`container_prefix`].
+///
+/// # Returns
+/// The prefixed content; blank lines only keep the container markers.
+fn continue_containers(content: &str, prefix: &str) -> String {
+    let lines: Vec<String> = content
+        .split('\n')
+        .enumerate()
+        .map(|(i, line)| match (i, line.trim().is_empty()) {
+            (0, _) => line.to_string(),
+            (_, true) => prefix.trim_end().to_string(),
+            (_, false) => format!("{}{}", prefix, line),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+
+/// The levels of the headings preceding the embeds of a content, found in
+/// one pass as the embeds are visited in order.
+#[derive(Default)]
+struct HeadingLevels {
+    /// The fences of the lines scanned so far.
+    fences: FenceTracker,
+    /// The byte offset up to which whole lines have been scanned.
+    scanned: usize,
+    /// The level of the last heading scanned, or 0.
+    level: usize,
+}
+
+impl HeadingLevels {
+    /// Find the level of the last heading before an offset in the content.
+    ///
+    /// # Arguments
+    /// - `content` - The content to scan, the same for every call.
+    /// - `offset` - The byte offset to stop at, not before that of the last call.
+    ///
+    /// # Returns
+    /// The heading level, or 0 if no heading precedes the offset.
+    fn before(&mut self, content: &str, offset: usize) -> usize {
+        let line_start = content[..offset].rfind('\n').map_or(0, |i| i + 1);
+        if line_start > self.scanned {
+            for line in content[self.scanned..line_start].lines() {
+                if let (false, Some((level, _))) = (self.fences.is_code(line), markdown::heading(line)) {
+                    self.level = level;
+                }
+            }
+            self.scanned = line_start;
+        }
+
+        // The line of the embed itself may be a heading
+        let line = &content[line_start..offset];
+        match markdown::heading(line) {
+            Some((level, _)) if !line.is_empty() && !self.fences.clone().is_code(line) => level,
+            _ => self.level,
+        }
+    }
+}
+
+
+/// Demote all headings in the content by a number of levels.
+///
+/// Headings are never demoted below level 6.
+///
+/// # Arguments
+/// - `content` - The content whose headings are shifted.
+/// - `levels` - The number of levels to demote.
+///
+/// # Returns
+/// The content with shifted headings.
+fn shift_headings(content: &str, levels: usize) -> String {
+    if levels == 0 {
+        return content.to_string();
+    }
+
+    let mut fences = FenceTracker::default();
+    content
+        .lines()
+        .map(|line| match (fences.is_code(line), markdown::heading(line)) {
+            (false, Some((level, _))) => {
+                let trimmed = line.trim_start();
+                let shifted = (level + levels).min(6);
+                format!("{}{}", "#".repeat(shifted), &trimmed[level..])
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+
+/// Compute how far embedded content has to be demoted.
+///
+/// # Arguments
+/// - `shift` - The configured heading shift.
+/// - `embedded` - The embedded content.
+/// - `host_level` - The level of the heading preceding the embed in the host.
+///
+/// # Returns
+/// The number of levels to demote.
+fn shift_levels(shift: HeadingShift, embedded: &str, host_level: usize) -> usize {
+    match shift {
+        HeadingShift::Levels(levels) => levels,
+        HeadingShift::Auto => {
+            let mut fences = FenceTracker::default();
+            let top = embedded
+                .lines()
+                .filter(|line| !fences.is_code(line))
+                .filter_map(|line| markdown::heading(line).map(|(level, _)| level))
+                .min();
+            top.map_or(0, |top| (host_level + 1).saturating_sub(top))
+        }
+    }
+}
+
+
+/// Wrap embedded content in the configured container with an attribution.
+///
+/// The attribution is written as a wikilink, so the link conversion turns it
+/// into a link back to the embedded note.
+///
+/// # Arguments
+/// - `content` - The embedded content.
+/// - `target` - The embedded note as written in the embed.
+/// - `fragment` - The heading or block fragment of the embed, if any.
+/// - `wrapper` - The container to use.
+///
+/// # Returns
+/// The wrapped content.
+fn wrap(content: &str, target: &str, fragment: Option<&str>, wrapper: EmbedWrapper) -> String {
+    let link = match fragment {
+        Some(heading) if !heading.starts_with('^') => format!("[[{}#{}|{}]]", target, heading, target),
+        _ => format!("[[{}]]", target),
+    };
+    let attribution = format!("*Embedded from {}*", link);
+
+    match wrapper {
+        EmbedWrapper::None => content.to_string(),
+        EmbedWrapper::Blockquote => content
+            .lines()
+            .chain(["", attribution.as_str()])
+            .map(|line| if line.is_empty() { ">".to_string() } else { format!("> {}", line) })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        EmbedWrapper::Aside => format!(
+            "<aside class=\"obsidian-embed\">\n\n{}\n\n{}\n\n</aside>",
+            content, attribution
+        ),
+        EmbedWrapper::Details => format!(
+            "<details class=\"obsidian-embed\" open>\n<summary>{}</summary>\n\n{}\n\n{}\n\n</details>",
+            target, content, attribution
+        ),
+    }
+}
+
+
+/// Replace all note embeds in the content with the content of the embedded notes.
+///
+/// `![[note#Heading]]` embeds only the section under the heading and
+/// `![[note#^id]]` only the block carrying the block ID. Embedded notes are
+/// expanded recursively. Embeds of notes or sections that don't exist, and
+/// embeds that would form a cycle, are turned into plain wikilinks so the
+/// link conversion still produces a link. Embedded attachments like images
+/// are rendered by [`media::render_embed`], embedded URLs by
+/// [`media::render_remote`].
+///
+/// Headings of embedded content are demoted according to the configured
+/// heading shift, then the content is wrapped in the configured container.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `vault` - The vault to read embedded notes from.
+/// - `config` - The preprocessor settings.
+///
+/// # Returns
+/// The content with embeds replaced.
+pub fn expand_embeds(content: &str, source: Option<&str>, vault: &Vault, config: &Config) -> String {
+    let source = source.map(|s| s.replace('\\', "/"));
+    let mut stack: Vec<String> = source.iter().cloned().collect();
+    expand(content, source.as_deref(), vault, config, &mut stack)
+}
+
+
+/// Describe an embed cycle for a warning.
+///
+/// # Examples
+/// `["a.md", "b.md"]` and `a.md` become `a.md -> b.md -> a.md`
+///
+/// # Arguments
+/// - `stack` - The chain of embeds currently being expanded.
+/// - `key` - The embed that closes the cycle.
+///
+/// # Returns
+/// The cycle path starting at the first occurrence of `key`.
+fn describe_cycle(stack: &[String], key: &str) -> String {
+    let start = stack.iter().position(|k| k == key).unwrap_or(0);
+    let mut path: Vec<&str> = stack[start..].iter().map(String::as_str).collect();
+    path.push(key);
+    path.join(" -> ")
+}
+
+
+/// Expand embeds with the chain of embeds that led to this content.
+///
+/// Every embed is identified by the note path plus its fragment, so embedding
+/// different sections of the same note is not mistaken for a cycle.
+///
+/// # Arguments
+/// - `content` - The content to expand.
+/// - `source` - The path of the chapter the content ends up in.
+/// - `vault` - The vault to read embedded notes from.
+/// - `config` - The preprocessor settings.
+/// - `stack` - The embeds currently being expanded, outermost first.
+///
+/// # Returns
+/// The content with embeds replaced.
+fn expand(
+    content: &str,
+    source: Option<&str>,
+    vault: &Vault,
+    config: &Config,
+    stack: &mut Vec<String>,
+) -> String {
+    if !content.contains("![[") {
+        return content.to_string();
+    }
+    let protected = markdown::protected_ranges(content);
+    let mut levels = HeadingLevels::default();
+
+    EMBED.replace_all(content, |caps: &Captures| {
+        // Embeds in code and math are examples, not embeds, and so are escaped ones
+        let start = caps.get(0).map_or(0, |m| m.start());
+        if protected.iter().any(|&(from, to)| (from..to).contains(&start)) || markdown::is_escaped(content, start) {
+            return caps[0].to_string();
+        }
+
+        let target = caps[1].trim();
+        if paths::is_url(target) {
+            let url = caps[0][3..].split(['|', ']']).next().unwrap_or(target).trim();
+            return media::render_remote(url, caps.get(3).map(|m| m.as_str().trim()));
+        }
+        if !is_note_target(target) {
+            let alias = caps.get(3).map(|m| m.as_str().trim());
+            let fragment = caps.get(2).map(|m| m.as_str());
+            return media::render_embed(target, fragment, alias, source, vault, &config.media)
+                .unwrap_or_else(|| caps[0].to_string());
+        }
+
+        let Some(path) = vault.resolve(target, source) else {
+            warn(&format!("Embedded note '{}' not found", target));
+            return caps[0][1..].to_string();
+        };
+        let fragment = caps.get(2).map(|m| m.as_str().trim());
+        let key = match fragment {
+            Some(fragment) => format!("{}#{}", path, fragment),
+            None => path.to_string(),
+        };
+
+        if stack.contains(&key) {
+            warn(&format!("Embed cycle detected: {}", describe_cycle(stack, &key)));
+            return caps[0][1..].to_string();
+        }
+
+        let (_, note) = frontmatter::split(vault.note(path).unwrap_or_default());
+        let embedded = match fragment {
+            Some(fragment) => match fragment.strip_prefix('^') {
+                Some(id) => extract_block(note, id),
+                None => extract_section(note, fragment),
+            },
+            None => Some(note.trim_start_matches(['\r', '\n']).trim_end().to_string()),
+        };
+        // Block IDs stay with the original blocks, so element IDs stay unique
+        let embedded = embedded.map(|embedded| blocks::remove_block_ids(&embedded));
+
+        match embedded {
+            Some(embedded) => {
+                stack.push(key);
+                let expanded = expand(&embedded, source, vault, config, stack);
+                stack.pop();
+
+                let start = caps.get(0).unwrap().start();
+                let host_level = levels.before(content, start);
+                let levels = shift_levels(config.embeds.heading_shift, &expanded, host_level);
+                let shifted = shift_headings(&expanded, levels);
+                let wrapped = wrap(&shifted, target, fragment, config.embeds.wrapper);
+
+                // Embeds in callouts, lists and footnote definitions stay inside them
+                let line = &content[content[..start].rfind('\n').map_or(0, |i| i + 1)..start];
+                let (markers, prefix) = container_prefix(line);
+                let mut block = continue_containers(&wrapped, &prefix);
+
+                // Content of several lines starts and ends its own block
+                if block.contains('\n') {
+                    let end = caps.get(0).unwrap().end();
+                    let after = content[end..].split('\n').next().unwrap_or_default();
+                    let separator = format!("\n{}\n{}", prefix.trim_end(), prefix);
+                    if !line[markers..].trim().is_empty() {
+                        block.insert_str(0, &separator);
+                    }
+                    if !after.trim().is_empty() {
+                        block.push_str(&separator);
+                    }
+                }
+                block
+            }
+            None => {
+                warn(&format!("Embedded section '{}' not found", key));
+                caps[0][1..].to_string()
+            }
+        }
+    }).to_string()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn embed(content: &str, vault: &Vault) -> String {
+        expand_embeds(content, None, vault, &Config::default())
+    }
+
+    #[test]
+    fn test_expand_note_embed() {
+        let vault = Vault::from_notes(&[("note.md", "Embedded *content*\n")]);
+        assert_eq!(embed("Before\n\n![[note]]\n\nAfter", &vault), "Before\n\nEmbedded *content*\n\nAfter");
+        assert_eq!(embed("![[note.md|alias]]", &vault), "Embedded *content*");
+        assert_eq!(embed("| ![[note\\|alias]] |", &vault), "| Embedded *content* |");
+
+        let vault = Vault::from_notes(&[("meta.md", "---\ntags: [x]\n---\n\nBody\n")]);
+        assert_eq!(embed("![[meta]]", &vault), "Body");
+
+        let example = "~~~markdown\n![[note]]\n~~~\n`![[note]]` \\![[note]]";
+        assert_eq!(embed(example, &vault), example);
+    }
+
+    #[test]
+    fn test_footnote_embed() {
+        let vault = Vault::from_notes(&[("quote.md", "First paragraph\n\nSecond [[link]]\n")]);
+        assert_eq!(
+            embed("Text[^1]\n\n[^1]: ![[quote]]\n\nAfter", &vault),
+            "Text[^1]\n\n[^1]: First paragraph\n\n    Second [[link]]\n\nAfter"
+        );
+    }
+
+    #[test]
+    fn test_container_embeds() {
+        let vault = Vault::from_notes(&[("other.md", "# Other\n\n## Sec One\n\ntext\nmore")]);
+        assert_eq!(
+            embed("> [!note]\n> ![[Other#Sec One]]\n> after", &vault),
+            "> [!note]\n> ## Sec One\n>\n> text\n> more\n> after"
+        );
+        assert_eq!(
+            embed("- item\n  ![[Other#Sec One]]\n- next", &vault),
+            "- item\n  ## Sec One\n\n  text\n  more\n- next"
+        );
+        assert_eq!(embed("> - ![[Other#Sec One]]", &vault), "> - ## Sec One\n>\n>   text\n>   more");
+    }
+
+    #[test]
+    fn test_section_embed() {
+        let note = "# Note\n\nIntro\n\n## Setup\n\nSteps\n\n```sh\n# comment\n```\n\n### Details\n\nMore\n\n## Usage\n\nUse it";
+        let vault = Vault::from_notes(&[("note.md", note)]);
+        assert_eq!(
+            embed("![[note#setup]]", &vault),
+            "## Setup\n\nSteps\n\n```sh\n# comment\n```\n\n### Details\n\nMore"
+        );
+        assert_eq!(embed("![[note#Usage]]", &vault), "## Usage\n\nUse it");
+        assert_eq!(embed("![[note#Missing]]", &vault), "[[note#Missing]]");
+        assert_eq!(embed("See [[data.zip]] ![[note#Usage]] then", &vault), "See [[data.zip]] \n\n## Usage\n\nUse it\n\n then");
+        assert_eq!(embed("> Quote ![[note#Usage]]", &vault), "> Quote \n>\n> ## Usage\n>\n> Use it");
+    }
+
+    #[test]
+    fn test_embedded_block_ids() {
+        let vault = Vault::from_notes(&[("other.md", "## Part\n\nText ^blk\n\n- item ^item")]);
+        assert_eq!(embed("![[other]]", &vault), "## Part\n\nText\n\n- item");
+        assert_eq!(embed("![[other#Part]]", &vault), "## Part\n\nText\n\n- item");
+    }
+
+    #[test]
+    fn test_block_embed() {
+        let note = "First line\nsecond line ^para\n\n- item one\n- item two ^item\n\n> quote\n\n^quote";
+        let vault = Vault::from_notes(&[("note.md", note)]);
+        assert_eq!(embed("![[note#^para]]", &vault), "First line\nsecond line");
+        assert_eq!(embed("![[note#^item]]", &vault), "- item two");
+        assert_eq!(embed("![[note#^quote]]", &vault), "> quote");
+        assert_eq!(embed("![[note#^missing]]", &vault), "[[note#^missing]]");
+    }
+
+    #[test]
+    fn test_nested_embeds() {
+        let vault = Vault::from_notes(&[("a.md", "A ![[b]]"), ("b.md", "B")]);
+        assert_eq!(embed("![[a]]", &vault), "A B");
+    }
+
+    #[test]
+    fn test_image_in_embedded_note() {
+        let vault = Vault::from_notes(&[("notes/a.md", "![[logo.png]]")]).with_assets(&["img/logo.png"]);
+        let expanded = expand_embeds("![[notes/a]]", Some("guide/host.md"), &vault, &Config::default());
+        assert_eq!(expanded, "![logo](../img/logo.png)");
+    }
+
+    #[test]
+    fn test_remote_embed() {
+        let vault = Vault::default();
+        assert_eq!(embed("![[https://example.com/a.png]]", &vault), "![a](https://example.com/a.png)");
+        assert_eq!(embed("![[https://example.com/#top|Home]]", &vault), "[Home](https://example.com/#top)");
+    }
+
+    #[test]
+    fn test_embed_cycles() {
+        let vault = Vault::from_notes(&[("a.md", "A ![[b]]"), ("b.md", "B ![[a]]"), ("self.md", "S ![[self]]")]);
+        assert_eq!(embed("![[a]]", &vault), "A B [[a]]");
+        assert_eq!(expand_embeds("Host ![[b]]", Some("a.md"), &vault, &Config::default()), "Host B [[a]]");
+        assert_eq!(embed("![[self]]", &vault), "S [[self]]");
+    }
+
+    #[test]
+    fn test_heading_shift() {
+        let vault = Vault::from_notes(&[("note.md", "# Note\n\n```\n# code\n```\n\n###### Deep")]);
+        let mut fixed = Config::default();
+        fixed.embeds.heading_shift = HeadingShift::Levels(2);
+        assert_eq!(
+            expand_embeds("![[note]]", None, &vault, &fixed),
+            "### Note\n\n```\n# code\n```\n\n###### Deep"
+        );
+
+        let mut auto = Config::default();
+        auto.embeds.heading_shift = HeadingShift::Auto;
+        assert_eq!(
+            expand_embeds("# Host\n\n## Part\n\n![[note]]", None, &vault, &auto),
+            "# Host\n\n## Part\n\n### Note\n\n```\n# code\n```\n\n###### Deep"
+        );
+        assert_eq!(expand_embeds("![[note]]", None, &vault, &auto), "# Note\n\n```\n# code\n```\n\n###### Deep");
+
+        // Every embed nests below the heading closest before it
+        let vault = Vault::from_notes(&[("part.md", "# Part")]);
+        assert_eq!(
+            expand_embeds("# Host\n\n![[part]]\n\n```\n## code\n```\n\n![[part]]\n\n### Deep\n\n![[part]]", None, &vault, &auto),
+            "# Host\n\n## Part\n\n```\n## code\n```\n\n## Part\n\n### Deep\n\n#### Part"
+        );
+    }
+
+    #[test]
+    fn test_wrappers() {
+        let vault = Vault::from_notes(&[("note.md", "## Part\n\nText")]);
+        let with = |wrapper| {
+            let mut config = Config::default();
+            config.embeds.wrapper = wrapper;
+            config
+        };
+
+        assert_eq!(
+            expand_embeds("![[note#Part]]", None, &vault, &with(EmbedWrapper::Blockquote)),
+            "> ## Part\n>\n> Text\n>\n> *Embedded from [[note#Part|note]]*"
+        );
+        assert_eq!(
+            expand_embeds("![[note]]", None, &vault, &with(EmbedWrapper::Aside)),
+            "<aside class=\"obsidian-embed\">\n\n## Part\n\nText\n\n*Embedded from [[note]]*\n\n</aside>"
+        );
+        assert!(expand_embeds("![[note]]", None, &vault, &with(EmbedWrapper::Details))
+            .starts_with("<details class=\"obsidian-embed\" open>\n<summary>note</summary>\n\n## Part"));
+    }
+
+    #[test]
+    fn test_describe_cycle() {
+        let stack = vec!["host.md".to_string(), "a.md".to_string(), "b.md".to_string()];
+        assert_eq!(describe_cycle(&stack, "a.md"), "a.md -> b.md -> a.md");
+    }
+
+    #[test]
+    fn test_missing_and_non_note_embeds() {
+        let vault = Vault::default();
+        assert_eq!(embed("![[missing]]", &vault), "[[missing]]");
+        assert_eq!(embed("![[archive.zip]]", &vault), "![[archive.zip]]");
+    }
+}
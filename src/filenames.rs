@@ -0,0 +1,205 @@
+//! Slugified file names for the published chapters.
+
+use crate::book;
+use crate::markdown;
+use crate::paths;
+use crate::warn;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+
+
+/// Turn a file or folder name into a slug.
+///
+/// # Examples
+/// `01 - My Note.md` becomes `01-my-note.md`
+///
+/// # Arguments
+/// - `name` - A single path component.
+///
+/// # Returns
+/// The lowercased name with hyphens instead of spaces and without other
+/// punctuation, or the name itself if nothing would be left.
+fn slug(name: &str) -> String {
+    let mut slug = String::new();
+    for c in name.chars().flat_map(char::to_lowercase) {
+        if c.is_alphanumeric() || matches!(c, '.' | '_') {
+            slug.push(c);
+        } else if (c.is_whitespace() || c == '-') && !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    let slug = slug.trim_end_matches('-').replace("-.", ".");
+    match slug.is_empty() || (slug.starts_with('.') && !name.starts_with('.')) {
+        true => name.to_string(),
+        false => slug,
+    }
+}
+
+
+/// Slugify every component of a path.
+///
+/// # Examples
+/// `My Notes/Getting Started.md` becomes `my-notes/getting-started.md`
+///
+/// # Arguments
+/// - `path` - The path relative to the src directory.
+///
+/// # Returns
+/// The slugified path.
+pub fn slugify(path: &str) -> String {
+    path.split('/')
+        .map(|part| match part {
+            "" | "." | ".." => part.to_string(),
+            _ => slug(part),
+        })
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+
+/// Split a path into the part before the extension and the extension.
+///
+/// # Arguments
+/// - `path` - The path, e.g. `guide/My Note.md`.
+///
+/// # Returns
+/// The path without extension and the extension including its dot.
+fn split_extension(path: &str) -> (&str, &str) {
+    let name_start = path.rfind('/').map_or(0, |i| i + 1);
+    match path[name_start..].rfind('.') {
+        Some(dot) if dot > 0 => path.split_at(name_start + dot),
+        _ => (path, ""),
+    }
+}
+
+
+/// Look up the new path of a file.
+///
+/// # Arguments
+/// - `path` - The path relative to the src directory.
+/// - `renamed` - The renamed chapters, without their extensions.
+///
+/// # Returns
+/// The new path, which is the path itself for files other than chapters.
+fn rename(path: &str, renamed: &HashMap<String, String>) -> String {
+    let (stem, extension) = split_extension(path);
+    match renamed.get(stem) {
+        Some(new) => format!("{}{}", new, extension),
+        None => path.to_string(),
+    }
+}
+
+
+/// Rewrite a relative link target of a chapter that moved or links to a renamed chapter.
+///
+/// # Arguments
+/// - `target` - The link target as written.
+/// - `from` - The previous path of the chapter.
+/// - `to` - The new path of the chapter.
+/// - `renamed` - The renamed chapters, without their extensions.
+///
+/// # Returns
+/// The new target, or `None` if it stays the same.
+fn rewrite_target(target: &str, from: &str, to: &str, renamed: &HashMap<String, String>) -> Option<String> {
+    if paths::is_url(target) || target.starts_with(['#', '/']) {
+        return None;
+    }
+    let (path, fragment) = match target.split_once('#') {
+        Some((path, fragment)) => (path, format!("#{}", fragment)),
+        None => (target, String::new()),
+    };
+
+    let resolved = paths::join(from, &paths::percent_decode(path));
+    let new = rename(&resolved, renamed);
+    if from == to && new == resolved {
+        return None;
+    }
+    Some(format!("{}{}", paths::percent_encode(&paths::relative(to, &new)), fragment))
+}
+
+
+/// Give all chapters slugified file names and update the links between them.
+///
+/// The `path` and `source_path` of every chapter are slugified. Links that
+/// point to a renamed chapter, or are written in one that moved to another
+/// folder, are rewritten. Chapters whose slug is taken keep their name.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+pub fn slugify_chapters(book: &mut Value) {
+    let mut renamed = HashMap::new();
+    let mut taken: HashMap<String, String> = HashMap::new();
+    for chapter in book::chapters(book) {
+        let Some(path) = chapter.get("path").and_then(Value::as_str) else { continue };
+        let slug = slugify(path);
+        if let Some(other) = taken.get(&slug).filter(|other| *other != path) {
+            warn(&format!("Chapters '{}' and '{}' have the same slug '{}', keeping '{}'", other, path, slug, path));
+            continue;
+        }
+        taken.insert(slug.clone(), path.to_string());
+        renamed.insert(split_extension(path).0.to_string(), split_extension(&slug).0.to_string());
+    }
+
+    let link_re = Regex::new(r#"(\]\(\s*<?|(?:href|src)=")([^\s()<>"]+)"#).unwrap();
+    book::for_each_chapter(book, &mut |chapter| {
+        let Some(path) = chapter.get("path").and_then(Value::as_str).map(str::to_string) else { return };
+        let new_path = rename(&path, &renamed);
+
+        if let Some(content) = chapter.get("content").and_then(Value::as_str) {
+            let converted = markdown::map_text(content, |text| {
+                link_re
+                    .replace_all(text, |caps: &regex::Captures| match rewrite_target(&caps[2], &path, &new_path, &renamed) {
+                        Some(target) => format!("{}{}", &caps[1], target),
+                        None => caps[0].to_string(),
+                    })
+                    .into_owned()
+            });
+            chapter["content"] = Value::String(converted);
+        }
+
+        if let Some(source_path) = chapter.get("source_path").and_then(Value::as_str) {
+            chapter["source_path"] = Value::String(rename(source_path, &renamed));
+        }
+        chapter["path"] = Value::String(new_path);
+    });
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("My Notes/01 - Getting Started.md"), "my-notes/01-getting-started.md");
+        assert_eq!(slugify("Q&A (draft) .md"), "qa-draft.md");
+        assert_eq!(slugify("../Übersicht.md"), "../übersicht.md");
+        assert_eq!(slugify("???.md"), "???.md");
+    }
+
+    #[test]
+    fn test_slugify_chapters() {
+        let mut book = json!({ "items": [
+            { "Chapter": {
+                "name": "Intro", "content": "[Setup](Guide/My%20Setup.md#step-1) ![Logo](img/logo.png) [Web](https://example.com)",
+                "path": "intro.md", "source_path": "intro.md", "sub_items": [
+                    { "Chapter": {
+                        "name": "Setup", "content": "[Intro](../intro.md) <img src=\"diagram.svg\"> `[x](../intro.md)`",
+                        "path": "Guide/My Setup.md", "source_path": "Guide/My Setup.md", "sub_items": [],
+                    } },
+                ],
+            } },
+        ] });
+        slugify_chapters(&mut book);
+
+        let intro = &book["items"][0]["Chapter"];
+        assert_eq!(intro["path"], "intro.md");
+        assert_eq!(intro["content"], "[Setup](guide/my-setup.md#step-1) ![Logo](img/logo.png) [Web](https://example.com)");
+        let setup = &intro["sub_items"][0]["Chapter"];
+        assert_eq!(setup["path"], "guide/my-setup.md");
+        assert_eq!(setup["source_path"], "guide/my-setup.md");
+        assert_eq!(setup["content"], "[Intro](../intro.md) <img src=\"../Guide/diagram.svg\"> `[x](../intro.md)`");
+    }
+}
@@ -0,0 +1,132 @@
+//! Conversion of Obsidian's inline footnotes (`^[text]`).
+
+use crate::markdown::{self, FenceTracker};
+use regex::Regex;
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+
+/// A footnote reference with the label as group.
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[\^([^\]\s]+)\]").unwrap());
+
+
+/// Find the end of an inline footnote.
+///
+/// Brackets inside the footnote must be balanced, and brackets inside code
+/// spans are ignored.
+///
+/// # Arguments
+/// - `line` - The line containing the footnote.
+/// - `start` - The byte offset of the text after `^[`.
+/// - `code` - The byte ranges of the line's code spans.
+///
+/// # Returns
+/// The byte offset of the closing `]`, if any.
+fn closing_bracket(line: &str, start: usize, code: &[(usize, usize)]) -> Option<usize> {
+    let mut depth = 0;
+    for (i, c) in line[start..].char_indices().map(|(i, c)| (start + i, c)) {
+        if code.iter().any(|&(from, to)| (from..to).contains(&i)) {
+            continue;
+        }
+        match c {
+            '[' => depth += 1,
+            ']' if depth == 0 => return Some(i),
+            ']' => depth -= 1,
+            _ => {}
+        }
+    }
+    None
+}
+
+
+/// Replace inline footnotes with numbered footnote references.
+///
+/// The footnote texts are appended to the chapter as definitions. Numbers
+/// already used as footnote labels in the chapter are skipped.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+///
+/// # Returns
+/// The content with reference-style footnotes.
+pub fn convert_inline_footnotes(content: &str) -> String {
+    let labels: HashSet<String> = REFERENCE.captures_iter(content).map(|caps| caps[1].to_string()).collect();
+    let mut number = 0;
+    let mut definitions: Vec<String> = Vec::new();
+
+    let mut fences = FenceTracker::default();
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| {
+            if fences.is_code(line) || !line.contains("^[") {
+                return line.to_string();
+            }
+
+            let mut code = Vec::new();
+            let mut offset = 0;
+            for (is_code, part) in markdown::code_spans(line) {
+                if is_code {
+                    code.push((offset, offset + part.len()));
+                }
+                offset += part.len();
+            }
+
+            let mut output = String::new();
+            let mut rest = 0;
+            while let Some(found) = line[rest..].find("^[").map(|i| rest + i) {
+                let in_code = code.iter().any(|&(from, to)| (from..to).contains(&found));
+                let escaped = line[..found].ends_with('\\');
+                let end = match in_code || escaped {
+                    true => None,
+                    false => closing_bracket(line, found + 2, &code),
+                };
+                let Some(end) = end else {
+                    output.push_str(&line[rest..found + 2]);
+                    rest = found + 2;
+                    continue;
+                };
+
+                number += 1;
+                while labels.contains(&number.to_string()) {
+                    number += 1;
+                }
+                output.push_str(&line[rest..found]);
+                output.push_str(&format!("[^{}]", number));
+                definitions.push(format!("[^{}]: {}", number, line[found + 2..end].trim()));
+                rest = end + 1;
+            }
+            output.push_str(&line[rest..]);
+            output
+        })
+        .collect();
+
+    let content = lines.join("\n");
+    match definitions.is_empty() {
+        true => content,
+        false => format!("{}\n\n{}\n", content.trim_end(), definitions.join("\n\n")),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inline_footnotes() {
+        assert_eq!(
+            convert_inline_footnotes("Text^[A note with [[Link]].] and more^[Second `]` one].\n"),
+            "Text[^1] and more[^2].\n\n[^1]: A note with [[Link]].\n\n[^2]: Second `]` one\n"
+        );
+        assert_eq!(
+            convert_inline_footnotes("One[^1] two^[inline]\n\n[^1]: Existing"),
+            "One[^1] two[^2]\n\n[^1]: Existing\n\n[^2]: inline\n"
+        );
+    }
+
+    #[test]
+    fn test_ignored_footnotes() {
+        let input = "`^[code]` \\^[escaped] ^[unclosed\n```\n^[fenced]\n```";
+        assert_eq!(convert_inline_footnotes(input), input);
+    }
+}
@@ -0,0 +1,255 @@
+//! Conversion of Obsidian's inline formatting (`==highlights==`, `%% comments %%`)
+//! and cleanup of leftover Templater expressions.
+
+use crate::config::{CommentStyle, FormattingConfig, HighlightStyle, HtmlCommentStyle, TemplaterStyle};
+use crate::markdown::{self, FenceTracker};
+use regex::Regex;
+use std::sync::LazyLock;
+
+
+/// A `==highlight==` with the highlighted text as group.
+static HIGHLIGHT: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"==([^=\s](?:[^=]*[^=\s])?)==").unwrap());
+
+
+/// Convert `==highlights==` outside of code according to the configured style.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `style` - How highlights are converted.
+///
+/// # Returns
+/// The content with converted highlights.
+fn convert_highlights(content: &str, style: HighlightStyle) -> String {
+    let replacement = match style {
+        HighlightStyle::Mark => "<mark>$1</mark>",
+        HighlightStyle::Bold => "**$1**",
+        HighlightStyle::None => return content.to_string(),
+    };
+
+    markdown::map_text(content, |text| HIGHLIGHT.replace_all(text, replacement).into_owned())
+}
+
+
+/// Remove or convert `%% comments %%`, which may span several lines.
+///
+/// Comments inside code are left alone. Lines that only contained a comment
+/// are removed entirely when stripping.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `style` - What happens to comments.
+///
+/// # Returns
+/// The content with converted comments.
+fn convert_comments(content: &str, style: CommentStyle) -> String {
+    let (open, close) = match style {
+        CommentStyle::Strip => ("", ""),
+        CommentStyle::Html => ("<!--", "-->"),
+        CommentStyle::Keep => return content.to_string(),
+    };
+    let keep_text = style == CommentStyle::Html;
+
+    let mut fences = FenceTracker::default();
+    let mut in_comment = false;
+    let mut lines = Vec::new();
+    for line in content.split('\n') {
+        if !in_comment && fences.is_code(line) {
+            lines.push(line.to_string());
+            continue;
+        }
+
+        let mut commented = in_comment;
+        let mut converted = String::new();
+        for (code, part) in markdown::code_spans(line) {
+            if code {
+                if !in_comment || keep_text {
+                    converted.push_str(part);
+                }
+                continue;
+            }
+            for (i, piece) in part.split("%%").enumerate() {
+                if i > 0 {
+                    converted.push_str(if in_comment { close } else { open });
+                    in_comment = !in_comment;
+                    commented = true;
+                }
+                if !in_comment || keep_text {
+                    converted.push_str(piece);
+                }
+            }
+        }
+
+        if !(style == CommentStyle::Strip && commented && converted.trim().is_empty() && !line.trim().is_empty()) {
+            lines.push(converted);
+        }
+    }
+    lines.join("\n")
+}
+
+
+/// Remove leftover Templater expressions (`<% ... %>`, `<%* ... %>`) outside of code.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `config` - The formatting settings.
+///
+/// # Returns
+/// The content with expressions replaced by the configured replacement.
+fn strip_templater(content: &str, config: &FormattingConfig) -> String {
+    match config.templater {
+        TemplaterStyle::Keep => content.to_string(),
+        TemplaterStyle::Strip => markdown::map_delimited(content, "<%", "%>", |_| config.templater_replacement.clone()),
+    }
+}
+
+
+/// Remove HTML comments (`<!-- ... -->`) outside of code, if enabled.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `style` - What happens to HTML comments.
+///
+/// # Returns
+/// The content without HTML comments, unless they're kept.
+fn strip_html_comments(content: &str, style: HtmlCommentStyle) -> String {
+    match style {
+        HtmlCommentStyle::Keep => content.to_string(),
+        HtmlCommentStyle::Strip => markdown::map_delimited(content, "<!--", "-->", |_| String::new()),
+    }
+}
+
+
+/// Turn single newlines inside paragraphs into hard line breaks, like Obsidian's
+/// strict line breaks setting being off.
+///
+/// Lines of code blocks, tables, lists, headings and HTML are left alone.
+/// Paragraphs inside blockquotes get breaks as well.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+///
+/// # Returns
+/// The content with a trailing double space on every broken line.
+fn convert_line_breaks(content: &str) -> String {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut fences = FenceTracker::default();
+    let (mut in_list, mut in_table) = (false, false);
+
+    // The quote depth of every line that is paragraph text
+    let paragraph: Vec<Option<usize>> = (0..lines.len())
+        .map(|i| {
+            if fences.is_code(lines[i]) {
+                return None;
+            }
+            let (depth, text) = markdown::unquote_all(lines[i]);
+            if text.trim().is_empty() {
+                (in_list, in_table) = (false, false);
+                return None;
+            }
+            if markdown::is_list_item(text) {
+                in_list = true;
+            }
+            if markdown::is_table_delimiter(text) {
+                in_table = true;
+            }
+            let next_is_delimiter = lines.get(i + 1).is_some_and(|next| markdown::is_table_delimiter(markdown::unquote_all(next).1));
+            let block = text.starts_with(['<', '\t', '|']) || text.starts_with("    ") || text.starts_with("$$");
+            let rule = text.trim().len() >= 3 && text.trim().chars().all(|c| matches!(c, '-' | '*' | '_'));
+            match in_list || in_table || next_is_delimiter || block || rule || markdown::heading(text).is_some() {
+                true => None,
+                false => Some(depth),
+            }
+        })
+        .collect();
+
+    let converted: Vec<String> = lines
+        .iter()
+        .enumerate()
+        .map(|(i, line)| match (paragraph[i], paragraph.get(i + 1).copied().flatten()) {
+            (Some(depth), Some(next)) if depth == next && !line.ends_with("  ") && !line.ends_with('\\') => {
+                format!("{}  ", line.trim_end())
+            }
+            _ => line.to_string(),
+        })
+        .collect();
+    converted.join("\n")
+}
+
+
+/// Convert all inline formatting in the content.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `config` - The formatting settings.
+///
+/// # Returns
+/// The converted content.
+pub fn convert_formatting(content: &str, config: &FormattingConfig) -> String {
+    let content = convert_comments(content, config.comments);
+    let content = strip_templater(&content, config);
+    let content = strip_html_comments(&content, config.html_comments);
+    let content = match config.line_breaks {
+        true => convert_line_breaks(&content),
+        false => content,
+    };
+    convert_highlights(&content, config.highlight)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_highlights() {
+        let input = "Some ==important== text and `a ==code== span`\n```\n==fenced==\n```\n";
+        assert_eq!(
+            convert_highlights(input, HighlightStyle::Mark),
+            "Some <mark>important</mark> text and `a ==code== span`\n```\n==fenced==\n```\n"
+        );
+        assert_eq!(convert_highlights("==a b== and ==c==", HighlightStyle::Bold), "**a b** and **c**");
+        assert_eq!(convert_highlights("if a == b == c", HighlightStyle::Mark), "if a == b == c");
+        assert_eq!(convert_highlights("==x==", HighlightStyle::None), "==x==");
+    }
+
+    #[test]
+    fn test_comments() {
+        let input = "Visible %%hidden%% text\n%%\nPrivate\nnotes\n%%\nAfter `%%code%%`\n```\n%% fenced %%\n```";
+        assert_eq!(
+            convert_comments(input, CommentStyle::Strip),
+            "Visible  text\nAfter `%%code%%`\n```\n%% fenced %%\n```"
+        );
+        assert_eq!(convert_comments("a %%b\nc%% d", CommentStyle::Html), "a <!--b\nc--> d");
+        assert_eq!(convert_comments("a %%b%%", CommentStyle::Keep), "a %%b%%");
+    }
+
+    #[test]
+    fn test_templater() {
+        let input = "Created <% tp.date.now(\"YYYY-MM-DD\") %>\n<%*\nlet x = 1;\n%>\nText `<% code %>`";
+        let config = FormattingConfig { templater: TemplaterStyle::Strip, ..Default::default() };
+        assert_eq!(strip_templater(input, &config), "Created \n\nText `<% code %>`");
+
+        let config = FormattingConfig { templater: TemplaterStyle::Strip, templater_replacement: "…".to_string(), ..Default::default() };
+        assert_eq!(strip_templater("Date: <% tp.date.now() %>", &config), "Date: …");
+        let text = "Price <% is %> text";
+        assert_eq!(strip_templater(text, &FormattingConfig::default()), text);
+    }
+
+    #[test]
+    fn test_line_breaks() {
+        let input = "First line\nsecond line\n\n- item\n- item\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n\
+                     > quoted\n> lines\n\n```\ncode\ncode\n```\n# Heading\nText\nlast";
+        assert_eq!(
+            convert_line_breaks(input),
+            "First line  \nsecond line\n\n- item\n- item\n\n| a | b |\n|---|---|\n| 1 | 2 |\n\n\
+             > quoted  \n> lines\n\n```\ncode\ncode\n```\n# Heading\nText  \nlast"
+        );
+    }
+
+    #[test]
+    fn test_html_comments() {
+        let input = "A <!-- [[draft]] --> B\n<!--\nold\n-->\n`<!-- code -->`";
+        assert_eq!(strip_html_comments(input, HtmlCommentStyle::Keep), input);
+        assert_eq!(strip_html_comments(input, HtmlCommentStyle::Strip), "A  B\n\n`<!-- code -->`");
+    }
+}
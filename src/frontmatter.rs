@@ -0,0 +1,331 @@
+//! Detection and parsing of YAML frontmatter at the start of notes.
+
+use crate::book;
+use crate::config::{FrontmatterConfig, FrontmatterMode};
+use crate::markdown;
+use serde_json::Value;
+
+
+/// The properties of a note, parsed from the simple YAML Obsidian writes.
+///
+/// Every value is a list; scalars are lists with one element.
+#[derive(Debug, Default, PartialEq)]
+pub struct Frontmatter {
+    entries: Vec<(String, Vec<String>)>,
+}
+
+impl Frontmatter {
+    /// Parse the top-level keys of the frontmatter.
+    ///
+    /// Supports scalars, flow lists (`[a, b]`), block lists (`- a`) and block
+    /// scalars (`|`, `>`); nested mappings are ignored.
+    ///
+    /// # Arguments
+    /// - `yaml` - The YAML between the `---` delimiters.
+    ///
+    /// # Returns
+    /// The parsed properties.
+    pub fn parse(yaml: &str) -> Frontmatter {
+        let mut entries: Vec<(String, Vec<String>)> = Vec::new();
+        let mut block: Option<(String, bool)> = None;
+
+        for line in yaml.lines() {
+            let indented = line.starts_with([' ', '\t']);
+            let trimmed = line.trim();
+            if trimmed.is_empty() || trimmed.starts_with('#') {
+                continue;
+            }
+
+            if indented {
+                let Some((_, values)) = entries.last_mut() else { continue };
+                match &mut block {
+                    Some((text, _)) => {
+                        if !text.is_empty() {
+                            text.push('\n');
+                        }
+                        text.push_str(trimmed);
+                    }
+                    None => {
+                        if let Some(item) = trimmed.strip_prefix('-') {
+                            values.push(unquote(item.trim()));
+                        }
+                    }
+                }
+                continue;
+            }
+
+            Self::finish_block(&mut entries, &mut block);
+            let Some((key, value)) = line.split_once(':') else { continue };
+            let value = value.trim();
+            let values = match value {
+                "" => Vec::new(),
+                "|" | "|-" | ">" | ">-" => {
+                    block = Some((String::new(), value.starts_with('>')));
+                    Vec::new()
+                }
+                _ if value.starts_with('[') && value.ends_with(']') => value[1..value.len() - 1]
+                    .split(',')
+                    .map(|v| unquote(v.trim()))
+                    .filter(|v| !v.is_empty())
+                    .collect(),
+                _ => vec![unquote(value)],
+            };
+            entries.push((key.trim().to_string(), values));
+        }
+        Self::finish_block(&mut entries, &mut block);
+
+        Frontmatter { entries }
+    }
+
+    /// Store a finished block scalar as the value of the last key.
+    fn finish_block(entries: &mut [(String, Vec<String>)], block: &mut Option<(String, bool)>) {
+        if let (Some((text, folded)), Some((_, values))) = (block.take(), entries.last_mut()) {
+            values.push(if folded { text.replace('\n', " ") } else { text });
+        }
+    }
+
+    /// Get the values of a property.
+    ///
+    /// # Arguments
+    /// - `key` - The property name.
+    ///
+    /// # Returns
+    /// The values, or `None` if the property isn't set.
+    pub fn get(&self, key: &str) -> Option<&[String]> {
+        self.entries.iter().find(|(k, _)| k == key).map(|(_, v)| v.as_slice())
+    }
+
+    /// Get all properties in the order they are written.
+    ///
+    /// # Returns
+    /// The property names and their values.
+    pub fn entries(&self) -> &[(String, Vec<String>)] {
+        &self.entries
+    }
+}
+
+
+/// Remove quotes and trailing comments from a YAML scalar.
+///
+/// # Arguments
+/// - `value` - The scalar as written.
+///
+/// # Returns
+/// The plain value.
+fn unquote(value: &str) -> String {
+    for quote in ['"', '\''] {
+        if let Some(inner) = value.strip_prefix(quote).and_then(|v| v.strip_suffix(quote)) {
+            return inner.to_string();
+        }
+    }
+    match value.find(" #") {
+        Some(comment) => value[..comment].trim_end().to_string(),
+        None => value.to_string(),
+    }
+}
+
+
+/// Split a note into its frontmatter and body.
+///
+/// Frontmatter starts with a `---` line at the very beginning of the note and
+/// ends with the next `---` (or `...`) line.
+///
+/// # Arguments
+/// - `content` - The note content.
+///
+/// # Returns
+/// The YAML between the delimiters, if any, and the rest of the note.
+pub fn split(content: &str) -> (Option<&str>, &str) {
+    let Some(rest) = content.strip_prefix("---") else {
+        return (None, content);
+    };
+    let Some(rest) = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n')) else {
+        return (None, content);
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if matches!(line.trim_end(), "---" | "...") {
+            return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+        }
+        offset += line.len();
+    }
+    (None, content)
+}
+
+
+/// Render the configured properties as a list below the chapter's first heading.
+///
+/// # Arguments
+/// - `frontmatter` - The chapter's properties.
+/// - `body` - The chapter content without frontmatter.
+/// - `keys` - The properties to show, in order.
+///
+/// # Returns
+/// The chapter content with the metadata block.
+fn render_metadata(frontmatter: &Frontmatter, body: &str, keys: &[String]) -> String {
+    let items: Vec<String> = keys
+        .iter()
+        .filter_map(|key| {
+            let values = frontmatter.get(key).filter(|v| !v.is_empty())?;
+            let mut chars = key.chars();
+            let label: String = chars.next()?.to_uppercase().chain(chars).collect();
+            let label = label.replace(['-', '_'], " ");
+            Some(format!("- **{}:** {}", label, values.join(", ")))
+        })
+        .collect();
+    if items.is_empty() {
+        return body.to_string();
+    }
+
+    let block = format!("<div class=\"obsidian-metadata\">\n\n{}\n\n</div>\n\n", items.join("\n"));
+    let first = body.lines().next().unwrap_or_default();
+    match markdown::heading(first) {
+        Some((1, _)) => {
+            let rest = body[first.len()..].trim_start_matches(['\r', '\n']);
+            format!("{}\n\n{}{}", first, block, rest)
+        }
+        _ => format!("{}{}", block, body),
+    }
+}
+
+
+/// Handle the frontmatter of a chapter according to the configured mode.
+///
+/// Unless the frontmatter is kept, the chapter is wrapped in a `<div>` with
+/// the classes listed in `cssclasses`.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `config` - The frontmatter settings.
+///
+/// # Returns
+/// The chapter content without frontmatter, unless it's kept.
+pub fn convert_frontmatter(content: &str, config: &FrontmatterConfig) -> String {
+    let (Some(yaml), body) = split(content) else {
+        return content.to_string();
+    };
+    if config.mode == FrontmatterMode::Keep {
+        return content.to_string();
+    }
+
+    let frontmatter = Frontmatter::parse(yaml);
+    let body = body.trim_start_matches(['\r', '\n']);
+    let body = match config.mode {
+        FrontmatterMode::Metadata => render_metadata(&frontmatter, body, &config.keys),
+        _ => body.to_string(),
+    };
+
+    // Obsidian's `cssclasses` style the whole note
+    let classes: Vec<&str> = ["cssclasses", "cssclass"]
+        .iter()
+        .filter_map(|key| frontmatter.get(key))
+        .flatten()
+        .flat_map(|value| value.split_whitespace())
+        .collect();
+    match classes.is_empty() {
+        true => body,
+        false => format!("<div class=\"{}\">\n\n{}\n\n</div>\n", classes.join(" ").replace('"', "&quot;"), body.trim_end()),
+    }
+}
+
+
+/// Remove chapters whose frontmatter sets the publish property to `false`.
+///
+/// Sub-chapters of removed chapters are removed as well.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `config` - The frontmatter settings.
+///
+/// # Returns
+/// The number of removed chapters.
+pub fn remove_unpublished(book: &mut Value, config: &FrontmatterConfig) -> usize {
+    if !config.exclude_unpublished {
+        return 0;
+    }
+
+    book::retain_chapters(book, &|chapter: &Value| {
+        let content = chapter["content"].as_str().unwrap_or_default();
+        let Some(yaml) = split(content).0 else { return true };
+        match Frontmatter::parse(yaml).get(&config.publish_key) {
+            Some([value]) => !value.eq_ignore_ascii_case("false"),
+            _ => true,
+        }
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split() {
+        assert_eq!(split("---\ntitle: A\ntags: [x]\n---\n# A\n"), (Some("title: A\ntags: [x]\n"), "# A\n"));
+        assert_eq!(split("---\r\n---\r\nBody"), (Some(""), "Body"));
+        assert_eq!(split("---\nno end"), (None, "---\nno end"));
+        assert_eq!(split("Text\n---\n"), (None, "Text\n---\n"));
+        assert_eq!(split("----\n"), (None, "----\n"));
+    }
+
+    #[test]
+    fn test_convert_frontmatter() {
+        let content = "---\ntitle: A\n---\n\n# A";
+        assert_eq!(convert_frontmatter(content, &FrontmatterConfig::default()), "# A");
+        let keep = FrontmatterConfig { mode: FrontmatterMode::Keep, ..Default::default() };
+        assert_eq!(convert_frontmatter(content, &keep), content);
+    }
+
+    #[test]
+    fn test_css_classes() {
+        let content = "---\ncssclasses:\n  - wide\n  - \"no-toc\"\n---\n# Title\n";
+        assert_eq!(
+            convert_frontmatter(content, &FrontmatterConfig::default()),
+            "<div class=\"wide no-toc\">\n\n# Title\n\n</div>\n"
+        );
+    }
+
+    #[test]
+    fn test_remove_unpublished() {
+        let mut book = serde_json::json!({ "items": [
+            { "Chapter": { "name": "Public", "content": "---\npublish: true\n---\n", "sub_items": [] } },
+            { "Chapter": { "name": "Private", "content": "---\npublish: false\n---\n", "sub_items": [] } },
+            { "Chapter": { "name": "Plain", "content": "Text", "sub_items": [] } },
+        ] });
+        assert_eq!(remove_unpublished(&mut book, &FrontmatterConfig::default()), 0);
+
+        let config = FrontmatterConfig { exclude_unpublished: true, ..Default::default() };
+        assert_eq!(remove_unpublished(&mut book, &config), 1);
+        let names: Vec<&str> = book::chapters(&book).iter().map(|c| c["name"].as_str().unwrap()).collect();
+        assert_eq!(names, ["Public", "Plain"]);
+    }
+
+    #[test]
+    fn test_parse() {
+        let yaml = "title: \"My: Note\"\ntags: [a, 'b']\naliases:\n  - One\n  - Two\nsummary: >\n  Folded\n  text\nnested:\n  key: value\ndate: 2024-01-02 # comment\n";
+        let frontmatter = Frontmatter::parse(yaml);
+        assert_eq!(frontmatter.get("title"), Some(&["My: Note".to_string()][..]));
+        assert_eq!(frontmatter.get("tags"), Some(&["a".to_string(), "b".to_string()][..]));
+        assert_eq!(frontmatter.get("aliases"), Some(&["One".to_string(), "Two".to_string()][..]));
+        assert_eq!(frontmatter.get("summary"), Some(&["Folded text".to_string()][..]));
+        assert_eq!(frontmatter.get("nested"), Some(&[][..]));
+        assert_eq!(frontmatter.get("date"), Some(&["2024-01-02".to_string()][..]));
+        assert_eq!(frontmatter.get("missing"), None);
+    }
+
+    #[test]
+    fn test_metadata() {
+        let config = FrontmatterConfig {
+            mode: FrontmatterMode::Metadata,
+            keys: vec!["status".to_string(), "author".to_string(), "date".to_string()],
+            ..Default::default()
+        };
+        let content = "---\nauthor: Jane\nstatus: draft\nsecret: x\n---\n# Title\n\nText";
+        assert_eq!(
+            convert_frontmatter(content, &config),
+            "# Title\n\n<div class=\"obsidian-metadata\">\n\n- **Status:** draft\n- **Author:** Jane\n\n</div>\n\nText"
+        );
+        assert_eq!(convert_frontmatter("---\nsecret: x\n---\nText", &config), "Text");
+    }
+}
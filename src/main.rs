@@ -1,24 +1,565 @@
+mod anchors;
+mod assets;
+mod blocks;
+mod book;
+mod callouts;
+mod canvas;
+mod citations;
+mod config;
+mod dataview;
+mod dates;
+mod embed;
+mod filenames;
+mod footnotes;
+mod formatting;
+mod frontmatter;
+mod markdown;
+mod media;
+mod obsidian;
+mod paths;
+mod search;
+mod tags;
+mod tasks;
+mod vault;
+
 use regex::Regex;
 use serde_json::Value;
+use std::collections::BTreeMap;
 use std::io::{self, Read};
+use std::sync::LazyLock;
 use anyhow::Result;
+use config::{
+    Config, DisplaySource, DraftLinkMode, FileNameMode, HeadingLinkMode, HtmlBlockMode, LinkConfig, LinkDisplay, LinkFormat, SectionDisplay,
+    UnresolvedLinkMode,
+};
+use markdown::BlockKind;
+use citations::Bibliography;
+use obsidian::ObsidianSettings;
+use vault::Vault;
+
+
+/// A wikilink, with the name in group 1, the section in group 2 and the
+/// display text in group 3.
+static WIKILINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[([^#\|\]]*?)\\?(?:#([^\|\]]+?)\\?)?(?:\|([^\]]+))?\]\]").unwrap());
+
+/// The backslash of an escaped wikilink or embed, with the rest of its start as group.
+static ESCAPED_WIKILINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\\(!?\[\[)").unwrap());
+
+/// A Markdown link or image to a `.md` file, with the `!`, text, path,
+/// fragment and title as groups.
+static MARKDOWN_LINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(!?)\[([^\[\]]*)\]\(\s*<?([^\s()<>]+\.md)(#[^\s()<>]*)?>?(\s+[^)]*)?\)").unwrap());
+
+/// An `obsidian://` URI, with the start of a Markdown link target, the
+/// brackets around the URI and the URI after the scheme as groups.
+static OBSIDIAN_URI: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"(\]\(\s*)?(<)?obsidian://([^\s<>()"]+)(>)?"#).unwrap());
+
+/// The opening fence of an admonish block up to its title, and the title.
+static ADMONISH_TITLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r#"^(\s*`{3,}admonish\s.*?title=")((?:[^"\\]|\\.)*)""#).unwrap());
+
+/// Print a warning to stderr, where mdBook shows it alongside its own output.
+///
+/// # Arguments
+/// - `message` - The warning message.
+pub fn warn(message: &str) {
+    eprintln!("[mdbook-obsidian-links] warning: {}", message);
+}
+
+
+/// Resolve a link within the current chapter (`[[#Heading]]`, `[[#^id]]`).
+///
+/// Without explicit text, heading links show the heading and block links
+/// the beginning of the block's text.
+///
+/// # Arguments
+/// - `section` - The heading, chained headings (`H1#H2`) or `^id` after the `#`.
+/// - `text` - The explicit display text, if any.
+/// - `source` - The path of the chapter, used to look up the block.
+/// - `config` - The link settings.
+/// - `vault` - The vault containing the chapter.
+///
+/// # Returns
+/// The display text and the anchor.
+fn same_file_link(section: &str, text: Option<&str>, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> (String, String) {
+    let heading = section.rsplit('#').next().unwrap_or(section);
+    let Some(id) = heading.strip_prefix('^') else {
+        let headings = source.and_then(|s| vault.headings(s, &config.anchors));
+        let anchor = anchors::heading_anchor(section, headings.as_deref().map(Vec::as_slice), &config.anchors);
+        return (text.unwrap_or(heading).to_string(), format!("#{}", anchor));
+    };
+
+    let display = text.map(str::to_string).unwrap_or_else(|| {
+        let block = source.and_then(|s| vault.note(s)).and_then(|note| embed::extract_block(note, id));
+        match block.as_deref().and_then(|b| b.lines().next()).map(|l| l.trim_start_matches(['-', '*', '+', '>', ' '])) {
+            Some(line) if line.chars().count() > 40 => {
+                let cut: String = line.chars().take(40).collect();
+                format!("{}…", cut.rsplit_once(' ').map_or(cut.as_str(), |(words, _)| words))
+            }
+            Some(line) if !line.is_empty() => line.to_string(),
+            _ => heading.to_string(),
+        }
+    });
+    (display, format!("#{}", id.trim()))
+}
+
+
+/// Resolve a wikilink to its display text and target.
+///
+/// # Arguments
+/// - `caps` - The captures of the link: name, section and display text.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up note titles and aliases.
+///
+/// # Returns
+/// The display text and the URL-encoded target, or `None` if the link is
+/// kept as written.
+fn resolve_link(caps: &regex::Captures, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> Option<(String, String)> {
+    // Chained headings (`#H1#H2`) link to the deepest heading below the others
+    let sektion = caps
+        .get(2)
+        .map(|m| m.as_str().split('#').map(str::trim).filter(|s| !s.is_empty()).collect::<Vec<_>>().join("#"))
+        .filter(|s| !s.is_empty());
+    resolve_target(caps[1].trim(), sektion.as_deref(), caps.get(3).map(|m| m.as_str().trim()), source, config, vault)
+}
+
+
+/// Get the path a link name refers to.
+///
+/// Folder paths, with either separator, are relative to the vault root,
+/// unless they start with `./` or `../` or the vault writes relative paths,
+/// and are relative to the chapter. Paths starting with `/` are always
+/// relative to the vault root. Dates link to the daily or periodic note,
+/// old names of renamed notes to their new name.
+///
+/// # Arguments
+/// - `mdname` - The note name or path as written in the link.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up the notes.
+///
+/// # Returns
+/// The normalized name without `.md` or `.markdown`, relative to the src
+/// directory if it has folders.
+fn link_name(mdname: &str, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> String {
+    let name = paths::strip_note_extension(mdname).replace('\\', "/");
+    if let Some(new) = config.renamed_to(&name).filter(|_| vault.resolve(&name, source).is_none()) {
+        return root_name(paths::strip_note_extension(new), config);
+    }
+    let relative = paths::join(source.unwrap_or_default(), &name);
+    match name.starts_with("./") || name.starts_with("../") {
+        true => relative,
+        false if name.starts_with('/') => root_name(&name, config),
+        // Daily and other periodic notes are in their folder, though linked by date only
+        false if vault.resolve(&name, source).is_none()
+            && let Some(path) = config.periodic_notes(&name).and_then(|notes| notes.path(&name)) =>
+        {
+            root_name(&path, config)
+        }
+        false if config.format == Some(LinkFormat::Relative) && name.contains('/') && vault.resolve(&relative, source).is_some() => relative,
+        false => root_name(&name, config),
+    }
+}
+
+
+/// Get the path of a link name written relative to the vault root.
+///
+/// # Arguments
+/// - `name` - The note name or path, optionally starting with `/`.
+/// - `config` - The link settings.
+///
+/// # Returns
+/// The normalized path relative to the src directory.
+fn root_name(name: &str, config: &LinkConfig) -> String {
+    let name = paths::normalize(name);
+    match name.strip_prefix(&config.vault_prefix).and_then(|rest| rest.strip_prefix('/')) {
+        Some(rest) if !config.vault_prefix.is_empty() => rest.to_string(),
+        _ => name,
+    }
+}
+
+
+/// Returns true if the target of a link exists.
+///
+/// Links to URLs, within the chapter, to external targets and to other books
+/// always exist; others need a note, attachment or chapter.
+///
+/// # Arguments
+/// - `mdname` - The note name, path or URL as written in the link.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up the notes.
+///
+/// # Returns
+/// Whether the target exists.
+fn link_exists(mdname: &str, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> bool {
+    if mdname.is_empty() || paths::is_url(mdname) {
+        return true;
+    }
+    let name = link_name(mdname, source, config, vault);
+    config.external_url(&name).or_else(|| config.external_url(mdname)).is_some()
+        || config.book_url(&name).is_some()
+        || vault.resolve(&name, source).is_some()
+        || vault.chapter(&name).is_some()
+        || vault.asset(&name).is_some()
+}
+
+
+/// Render the display text of a link that isn't converted to a link.
+///
+/// # Arguments
+/// - `display` - The display text.
+/// - `kind` - The kind of block the link is written in.
+/// - `class` - The class of the `<span>` the text is wrapped in, if any.
+///
+/// # Returns
+/// The text, escaped for the block.
+fn render_text(display: &str, kind: BlockKind, class: Option<&str>) -> String {
+    let display = match kind {
+        BlockKind::Html | BlockKind::Text if class.is_some() => {
+            display.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        }
+        BlockKind::Table => display.replace('|', "\\|"),
+        _ => display.to_string(),
+    };
+    match class {
+        Some(class) => format!("<span class=\"{}\">{}</span>", class, display),
+        None => display,
+    }
+}
+
+
+/// Render a link whose target doesn't exist according to the unresolved-link mode.
+///
+/// # Arguments
+/// - `link` - The wikilink as written.
+/// - `display` - The display text.
+/// - `kind` - The kind of block the link is written in.
+/// - `mode` - The unresolved-link mode; `Convert` and `Error` aren't handled here.
+///
+/// # Returns
+/// The replacement of the link.
+fn render_unresolved(link: &str, display: &str, kind: BlockKind, mode: UnresolvedLinkMode) -> String {
+    match mode {
+        UnresolvedLinkMode::Text => render_text(display, kind, None),
+        UnresolvedLinkMode::Broken => render_text(display, kind, Some("obsidian-unresolved")),
+        _ => link.to_string(),
+    }
+}
+
+
+/// Append a placeholder chapter for every linked note that doesn't exist yet.
+///
+/// The chapters are published where the link's note would be, so links to
+/// them resolve like links to any other chapter.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up the notes; learns the new chapters.
+fn add_stub_chapters(book: &mut Value, config: &LinkConfig, vault: &mut Vault) {
+    if !config.stubs {
+        return;
+    }
+
+    // Like note names, links to the same stub may differ in case
+    let mut stubs: BTreeMap<String, (String, String)> = BTreeMap::new();
+    for chapter in book::chapters(book) {
+        let source = chapter["source_path"].as_str().or_else(|| chapter["path"].as_str());
+        markdown::map_text(chapter["content"].as_str().unwrap_or_default(), |text| {
+            for caps in WIKILINK.captures_iter(text) {
+                let mdname = caps[1].trim();
+                let is_asset = paths::extension(mdname).is_some_and(|ext| ext != "md" && config.is_asset_extension(&ext));
+                let is_draft = vault.draft(paths::strip_note_extension(mdname)).is_some();
+                if is_asset || is_draft || link_exists(mdname, source, config, vault) {
+                    continue;
+                }
+                let name = link_name(mdname, source, config, vault);
+                let name = name.strip_suffix(".md").unwrap_or(&name);
+                let title = name.rsplit('/').next().unwrap_or(name).to_string();
+                stubs.entry(name.to_lowercase()).or_insert((format!("{}.md", name), title));
+            }
+            String::new()
+        });
+    }
+    if stubs.is_empty() {
+        return;
+    }
+
+    let mut chapters = serde_json::json!({ "items": [] });
+    if !config.stub_part.is_empty() {
+        book::push_part(&mut chapters, &config.stub_part);
+    }
+    for (path, title) in stubs.into_values() {
+        let content = format!("# {}\n\n*This note hasn't been written yet.*\n", title);
+        book::push_chapter(&mut chapters, &title, &path, content);
+    }
+    vault.add_chapters(&chapters);
+    if let (Some(items), Some(stubs)) = (book["items"].as_array_mut(), chapters["items"].as_array_mut()) {
+        items.append(stubs);
+    }
+}
+
+
+/// Check the links marked as unresolved while processing the book.
+///
+/// Each link is reported with the notes it may have been meant to link.
+///
+/// # Arguments
+/// - `vault` - The vault the links were marked in.
+/// - `config` - The link settings.
+///
+/// # Returns
+/// An error naming the unresolved links if the mode is `error`.
+fn check_unresolved(vault: &Vault, config: &LinkConfig) -> Result<()> {
+    let describe = |(name, source): &(String, String)| {
+        let suggestions: Vec<String> = vault.suggestions(name).iter().map(|s| format!("'{}'", s)).collect();
+        match suggestions.is_empty() {
+            true => format!("'{}' in '{}'", name, source),
+            false => format!("'{}' in '{}' (did you mean {}?)", name, source, suggestions.join(", ")),
+        }
+    };
+    let unresolved = vault.unresolved_links();
+    if config.unresolved == UnresolvedLinkMode::Error && !unresolved.is_empty() {
+        let links: Vec<String> = unresolved.iter().map(describe).collect();
+        anyhow::bail!("Unresolved links: {}", links.join(", "));
+    }
+    for link in &unresolved {
+        warn(&format!("Link {} doesn't match any note", describe(link)));
+    }
+    Ok(())
+}
+
+
+/// Get the display text of a link without an alias from the configured sources.
+///
+/// # Arguments
+/// - `note` - The note name as written in the link, without `.md`.
+/// - `path` - The path of the target note, if it exists.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up the note.
+///
+/// # Returns
+/// The text of the first source the note has, or else the name.
+fn fallback_display(note: &str, path: Option<&str>, config: &LinkConfig, vault: &Vault) -> String {
+    let stem = path.map(paths::strip_note_extension);
+    config
+        .display_fallback
+        .iter()
+        .find_map(|source| match source {
+            DisplaySource::Title => path.and_then(|p| vault.frontmatter_title(p)).map(str::to_string),
+            DisplaySource::Heading => path.and_then(|p| vault.heading(p)).map(str::to_string),
+            DisplaySource::Name => Some(config.display_name(note)),
+            DisplaySource::File => stem.map(|s| config.display_name(s.rsplit('/').next().unwrap_or(s))),
+            DisplaySource::Path => stem.map(|s| config.display_name(s)),
+        })
+        .unwrap_or_else(|| config.display_name(note))
+}
+
+
+/// Resolve the parts of a link to its display text and target.
+///
+/// # Arguments
+/// - `mdname` - The note name, path or URL.
+/// - `sektion` - The heading, chained headings (`H1#H2`) or `^id` after the `#`, if any.
+/// - `text` - The explicit display text, if any.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up note titles and aliases.
+///
+/// # Returns
+/// The display text and the URL-encoded target, or `None` if the link is
+/// kept as written.
+fn resolve_target(
+    mdname: &str,
+    sektion: Option<&str>,
+    text: Option<&str>,
+    source: Option<&str>,
+    config: &LinkConfig,
+    vault: &Vault,
+) -> Option<(String, String)> {
+    // Of chained headings (`H1#H2`), the deepest one is shown
+    let heading = sektion.map(|s| s.rsplit('#').next().unwrap_or(s));
+
+    // External URLs are linked as-is, including their fragment
+    if paths::is_url(mdname) {
+        let url = match heading {
+            Some(s) => format!("{}#{}", mdname, s),
+            None => mdname.to_string(),
+        };
+        return Some((text.unwrap_or(&url).to_string(), url.replace(' ', "%20")));
+    }
+
+    // Links within the chapter (`[[#Heading]]`, `[[#^id]]`) only need the anchor
+    if mdname.is_empty() {
+        return sektion.map(|s| same_file_link(s, text, source, config, vault));
+    }
+
+    let name = link_name(mdname, source, config, vault);
+    let note = paths::strip_note_extension(mdname).trim_start_matches('/');
+
+    // Anchor is optional; block IDs (`#^id`) are used as-is
+    let anchor = match sektion.zip(heading) {
+        Some((s, heading)) => match heading.strip_prefix('^') {
+            Some(id) => format!("#{}", id.trim()),
+            None => {
+                let headings = vault.resolve(&name, source).and_then(|path| vault.headings(path, &config.anchors));
+                format!("#{}", anchors::heading_anchor(s, headings.as_deref().map(Vec::as_slice), &config.anchors))
+            }
+        },
+        None => String::new(),
+    };
+
+    // Notes mapped to an external URL link there, e.g. to their published page
+    if let Some(url) = config.external_url(&name).or_else(|| config.external_url(mdname)) {
+        let display = text.map_or_else(|| config.display_name(note), str::to_string);
+        return Some((display, format!("{}{}", url.replace(' ', "%20"), anchor)));
+    }
+
+    // Links starting with the name of another book go to its published page
+    if vault.resolve(&name, source).is_none() && let Some(url) = config.book_url(&name) {
+        let display = text.map_or_else(|| config.display_name(name.split_once('/').map_or(note, |(_, page)| page)), str::to_string);
+        return Some((display, format!("{}{}", url, anchor)));
+    }
+
+    // Display text is either the explicit text, the note's title or the
+    // mdname without `.md`; links by unique ID show the rest of the note's name
+    let path = vault.resolve(&name, source);
+    let uid_title = path.and_then(|p| vault.uid_title(note, p));
+    let display = match text {
+        Some(text) => text.to_string(),
+        None if let Some(title) = uid_title => title,
+        None if let Some(date) = config.periodic_notes(note).and_then(|notes| notes.display_text(note)) => date,
+        None if !config.display_fallback.is_empty() => fallback_display(note, path, config, vault),
+        None => match config.display {
+            LinkDisplay::Title => path.and_then(|p| vault.title(p)).map_or_else(|| config.display_name(note), str::to_string),
+            LinkDisplay::Name => config.display_name(note),
+        },
+    };
+    let display = match (text, heading, config.section_display) {
+        (None, Some(s), SectionDisplay::Obsidian) => format!("{} > {}", display, s),
+        (None, Some(s), SectionDisplay::Heading) if !s.starts_with('^') => s.to_string(),
+        _ => display,
+    };
+
+    // Files like `diagram.svg` are linked directly, everything else is a
+    // chapter and gets the note extension
+    let is_asset = paths::extension(mdname).is_some_and(|ext| config.is_asset_extension(&ext));
+    let extension = match is_asset {
+        true => String::new(),
+        false => config.note_extension(),
+    };
+
+    // Notes and aliases resolve to the chapter the note is published as,
+    // other chapters of the book to their path, other paths with folders are
+    // kept; all are written relative to the chapter
+    let source = source.unwrap_or_default();
+    let chapter = |path: &str| (paths::relative(source, paths::strip_note_extension(path)), config.chapter_extension(path));
+    let (target, extension) = match path {
+        Some(path) => chapter(vault.chapter_path(path)),
+        None => match vault.chapter(&name).filter(|_| !is_asset) {
+            Some(path) => chapter(path),
+            None if name.contains('/') => (paths::relative(source, &name), extension),
+            None => (name, extension),
+        },
+    };
+
+    Some((display, format!("{}{}{}", paths::percent_encode(&target), extension, anchor)))
+}
+
+
+/// Format a converted link as Markdown or as an HTML `<a>` element.
+///
+/// In tables, pipes are escaped so the link stays in its cell.
+///
+/// # Arguments
+/// - `display` - The display text.
+/// - `target` - The link target.
+/// - `kind` - The kind of block the link is written in; links in HTML
+///   blocks are written as HTML.
+///
+/// # Returns
+/// The link.
+fn render_link(display: &str, target: &str, kind: BlockKind) -> String {
+    match kind {
+        BlockKind::Html => format!(
+            "<a href=\"{}\">{}</a>",
+            target.replace('&', "&amp;").replace('"', "&quot;"),
+            display.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+        ),
+        BlockKind::Table => format!("[{}]({})", display.replace('|', "\\|"), target.replace('|', "%7C")),
+        BlockKind::Text => format!("[{}]({})", display, target),
+    }
+}
 
 
-/// Convert an anchor string by lowercasing and replacing spaces/underscores with hyphens.
+/// Replace the links in headings with their display text.
 ///
-/// # Examples
-/// `Test test` becomes `test-test`
+/// mdBook derives a heading's anchor from its text, so plain headings keep
+/// the anchors `[[Note#Heading]]` links point to.
 ///
 /// # Arguments
-/// - `anchor` - The anchor string to convert.
+/// - `content` - The chapter content.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up note titles and aliases.
 ///
 /// # Returns
-/// A converted anchor string.
-fn convert_anchor(anchor: &str) -> String {
-    anchor
-        .to_lowercase()
-        .replace(' ', "-")
-        .replace('_', "-")
+/// The content with plain headings.
+fn heading_links_to_text(content: &str, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> String {
+    let mut fences = markdown::FenceTracker::default();
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| match !fences.is_code(line) && markdown::heading(line).is_some() {
+            true => markdown::map_text(line, |text| {
+                WIKILINK.replace_all(text, |caps: &regex::Captures| match resolve_link(caps, source, config, vault) {
+                    Some((display, _)) if !markdown::is_escaped(text, caps.get(0).map_or(0, |m| m.start())) => display,
+                    _ => caps[0].to_string(),
+                })
+                .into_owned()
+            }),
+            false => line.to_string(),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+
+/// Convert the links in the titles of converted callouts.
+///
+/// Admonish titles are an attribute of the fence line, which is otherwise
+/// skipped as code, and HTML titles need `<a>` elements to work.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `convert` - Converts the links in a piece of text for a kind of block.
+///
+/// # Returns
+/// The content with converted callout titles.
+fn convert_callout_titles(content: &str, convert: &impl Fn(&str, BlockKind) -> String) -> String {
+    let mut fences = markdown::FenceTracker::default();
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| {
+            let in_code = fences.in_code();
+            fences.is_code(line);
+            if in_code || !line.contains("[[") {
+                return line.to_string();
+            }
+            if let Some(caps) = ADMONISH_TITLE.captures(line) {
+                let title = caps[2].replace("\\\"", "\"").replace("\\\\", "\\");
+                let converted = convert(&title, BlockKind::Text).replace('\\', "\\\\").replace('"', "\\\"");
+                let rest = &line[caps.get(2).map_or(line.len(), |m| m.end())..];
+                return format!("{}{}{}", &caps[1], converted, rest);
+            }
+            match line.contains("class=\"callout-title\"") {
+                true => convert(line, BlockKind::Html),
+                false => line.to_string(),
+            }
+        })
+        .collect();
+    lines.join("\n")
 }
 
 
@@ -29,53 +570,244 @@ fn convert_anchor(anchor: &str) -> String {
 /// 2. `[[mdname#section]]` -> `[mdname](mdname.md#converted-section)`
 /// 3. `[[mdname|text]]` -> `[text](mdname.md)`
 ///
+/// Links to absolute URLs (`[[https://example.com|text]]`) link to the URL,
+/// links to files with an asset extension (`[[diagram.svg]]`) link to the file.
+///
+/// Links to a frontmatter alias of a note (`[[My Alias]]`) link to that note.
+///
+/// Links in raw HTML blocks are converted, skipped or written as `<a>`
+/// elements, depending on the settings.
+///
 /// # Arguments
 /// - `content` - The content string containing Obsidian links.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up note titles and aliases.
 ///
 /// # Returns
 /// A string with Obsidian links converted to Markdown links.
-fn convert_obsidian_links(content: &str) -> String {
-    let re = Regex::new(
-        r"\[\[([^#\|\]]+)(?:#([^#\|\]]+))?(?:\|([^\]]+))?\]\]"
-    ).unwrap();
+fn convert_obsidian_links(content: &str, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> String {
+    let convert = |block: &str, kind: BlockKind| markdown::map_text(block, |text| WIKILINK.replace_all(text, |caps: &regex::Captures| {
+        // Escaped links (`\[[Note]]`, `!\[[Note]]`) are shown as written
+        let start = caps.get(0).map_or(0, |m| m.start());
+        if markdown::is_escaped(text, start) || (text[..start].ends_with('!') && markdown::is_escaped(text, start - 1)) {
+            return caps[0].to_string();
+        }
 
-    re.replace_all(content, |caps: &regex::Captures| {
+        let Some((display, target)) = resolve_link(caps, source, config, vault) else {
+            return caps[0].to_string();
+        };
         let mdname = caps[1].trim();
-        let sektion = caps.get(2).map(|m| m.as_str().trim());
-        let text = caps.get(3).map(|m| m.as_str().trim());
+        let exists = link_exists(mdname, source, config, vault);
+
+        // Drafts have no page to link, so only their name is shown
+        if !exists && let Some(draft) = vault.draft(paths::strip_note_extension(mdname)) {
+            warn(&format!("Link '{}' in '{}' points to the draft chapter '{}'", mdname, source.unwrap_or_default(), draft));
+            let class = (config.drafts == DraftLinkMode::Styled).then_some("obsidian-draft");
+            return render_text(&display, kind, class);
+        }
+        if config.unresolved != UnresolvedLinkMode::Convert && !exists {
+            vault.mark_unresolved(mdname, source.unwrap_or_default());
+            if config.unresolved != UnresolvedLinkMode::Error {
+                return render_unresolved(&caps[0], &display, kind, config.unresolved);
+            }
+        }
+        render_link(&display, &target, kind)
+    }).into_owned());
+
+    // Links in headings can be reduced to their text first
+    let content = match config.headings {
+        HeadingLinkMode::Text => heading_links_to_text(content, source, config, vault),
+        HeadingLinkMode::Link => content.to_string(),
+    };
+    let content = convert_callout_titles(&content, &convert);
+
+    markdown::split_blocks(&content)
+        .into_iter()
+        .map(|(kind, block)| match (kind, config.html_blocks) {
+            (BlockKind::Html, HtmlBlockMode::Skip) => block.to_string(),
+            (BlockKind::Html, HtmlBlockMode::Convert) => unescape_wikilinks(&convert(block, BlockKind::Text)),
+            (BlockKind::Html, _) => unescape_wikilinks(&convert(block, kind)),
+            _ => convert(block, kind),
+        })
+        .collect()
+}
+
 
-        // Display text is either the explicit text or the mdname
-        let display = text.unwrap_or(mdname);
+/// Remove the backslashes of escaped wikilinks (`\[[Note]]`, `\![[Note]]`,
+/// `!\[[Note]]`) in HTML blocks, where mdBook doesn't process Markdown
+/// escapes and would show them.
+///
+/// # Arguments
+/// - `content` - The HTML block.
+///
+/// # Returns
+/// The block with the escaped wikilinks as written without backslash.
+fn unescape_wikilinks(content: &str) -> String {
+    markdown::map_text(content, |text| {
+        ESCAPED_WIKILINK.replace_all(text, |caps: &regex::Captures| {
+            match markdown::is_escaped(text, caps.get(0).map_or(0, |m| m.start())) {
+                true => caps[0].to_string(),
+                false => caps[1].to_string(),
+            }
+        })
+        .into_owned()
+    })
+}
 
-        // Anchor is optional
-        let anchor = sektion
-            .map(|s| format!("#{}", convert_anchor(s)))
-            .unwrap_or_default();
 
-            format!("[{}]({}.md{})", display, mdname.replace(' ', "%20"), anchor)
-    }).to_string()
+/// Resolve Markdown links to notes (`[text](Setup.md)`) like wikilinks.
+///
+/// Vaults using Markdown links write their paths in the same link format as
+/// wikilinks, so `[Setup](Setup.md)` may point to a note in another folder.
+/// Links to files that exist relative to the chapter are kept.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up the notes.
+///
+/// # Returns
+/// The content with resolved Markdown links.
+fn convert_markdown_links(content: &str, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> String {
+    markdown::map_text(content, |text| {
+        MARKDOWN_LINK.replace_all(text, |caps: &regex::Captures| {
+            let path = paths::percent_decode(&caps[3]);
+            let exists = vault.is_note(&paths::join(source.unwrap_or_default(), &path));
+            if !caps[1].is_empty() || paths::is_url(&path) || exists || vault.resolve(&path, source).is_none() {
+                return caps[0].to_string();
+            }
+
+            let fragment = caps.get(4).map(|m| paths::percent_decode(&m.as_str()[1..]));
+            match resolve_target(&path, fragment.as_deref(), Some(&caps[2]), source, config, vault) {
+                Some((display, target)) => format!("[{}]({}{})", display, target, caps.get(5).map_or("", |m| m.as_str())),
+                None => caps[0].to_string(),
+            }
+        })
+        .into_owned()
+    })
 }
 
 
-/// Recursively processes an item, converting Obsidian links in its content and sub-items.
+/// Rewrite `obsidian://` URIs of notes in the book into relative links.
+///
+/// Both `obsidian://open?vault=...&file=...` (or `&path=...`) and the short
+/// form `obsidian://vault/<vault>/<file>` are supported. As the vault can
+/// contain the src directory, leading folders of the file are dropped until
+/// it matches a note. URIs of other notes or actions are kept.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The link settings.
+/// - `vault` - The vault used to look up the notes.
+///
+/// # Returns
+/// The content with rewritten URIs.
+fn convert_obsidian_uris(content: &str, source: Option<&str>, config: &LinkConfig, vault: &Vault) -> String {
+    markdown::map_text(content, |text| {
+        OBSIDIAN_URI.replace_all(text, |caps: &regex::Captures| {
+            let uri = &caps[3];
+            let file = match uri.split_once('?') {
+                Some(("open", query)) => query.split('&').find_map(|param| match param.split_once('=') {
+                    Some(("file" | "path", value)) => Some(paths::percent_decode(value)),
+                    _ => None,
+                }),
+                _ => uri.strip_prefix("vault/").and_then(|rest| rest.split_once('/')).map(|(_, file)| paths::percent_decode(file)),
+            };
+
+            // Files may carry a heading (`Note#Heading`)
+            let Some(file) = file else { return caps[0].to_string() };
+            let (file, heading) = match file.split_once('#') {
+                Some((file, heading)) => (file, Some(heading)),
+                None => (file.as_str(), None),
+            };
+            let file = file.replace('\\', "/");
+            let file = file.trim_start_matches('/');
+            let Some(path) = std::iter::once(file)
+                .chain(file.match_indices('/').map(|(i, _)| &file[i + 1..]))
+                .find_map(|candidate| vault.resolve(paths::strip_note_extension(candidate), source))
+            else {
+                return caps[0].to_string();
+            };
+
+            let anchor = heading.map_or_else(String::new, |h| {
+                let headings = vault.headings(path, &config.anchors);
+                format!("#{}", anchors::heading_anchor(h, headings.as_deref().map(Vec::as_slice), &config.anchors))
+            });
+            let path = vault.chapter_path(path);
+            let stem = paths::strip_note_extension(path);
+            let target = format!("{}{}{}", paths::percent_encode(&paths::relative(source.unwrap_or_default(), stem)), config.chapter_extension(path), anchor);
+            match caps.get(1) {
+                Some(prefix) => format!("{}{}", prefix.as_str(), target),
+                None => format!("[{}]({})", stem.rsplit('/').next().unwrap_or(stem), target),
+            }
+        })
+        .into_owned()
+    })
+}
+
+
+/// Converts the Obsidian syntax in the content of a chapter.
+///
+/// Embeds are expanded first, so everything inside embedded notes is
+/// converted as well. Links are converted last.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `source` - The path of the chapter relative to the src directory, if any.
+/// - `config` - The preprocessor settings.
+/// - `vault` - The vault used to resolve embeds.
+/// - `bibliography` - The works citations refer to.
+///
+/// # Returns
+/// The converted content.
+fn process_content(content: &str, source: Option<&str>, config: &Config, vault: &Vault, bibliography: &Bibliography) -> String {
+    let content = frontmatter::convert_frontmatter(content, &config.frontmatter);
+    let content = embed::expand_embeds(&content, source, vault, config);
+    let content = dataview::render_queries(&content, vault, &config.dataview);
+    let content = search::convert_search_blocks(&content, &config.search);
+    let content = callouts::convert_callouts(&content, &config.callouts);
+    let content = formatting::convert_formatting(&content, &config.formatting);
+    let content = footnotes::convert_inline_footnotes(&content);
+    let content = dataview::convert_inline_fields(&content, &config.dataview);
+    let content = citations::convert_citations(&content, source, bibliography, &config.citations);
+    let content = tasks::convert_tasks(&content, &config.tasks);
+    let content = tags::convert_tags(&content, source, &config.tags);
+    let content = blocks::convert_block_ids(&content);
+    let content = convert_obsidian_uris(&content, source, &config.links, vault);
+    let content = match config.links.markdown_links {
+        Some(true) => convert_markdown_links(&content, source, &config.links, vault),
+        _ => content,
+    };
+    convert_obsidian_links(&content, source, &config.links, vault)
+}
+
+
+/// Recursively processes an item, converting Obsidian syntax in its content and sub-items.
 ///
 /// # Arguments
 /// - `item` - The JSON value representing the item to process.
+/// - `config` - The preprocessor settings.
+/// - `vault` - The vault used to resolve embeds.
+/// - `bibliography` - The works citations refer to.
 ///
 /// # Returns
 /// A Result indicating success or failure.
-fn process_item(item: &mut Value) -> Result<()> {
+fn process_item(item: &mut Value, config: &Config, vault: &Vault, bibliography: &Bibliography) -> Result<()> {
     if let Some(chapter) = item.get_mut("Chapter") {
         // Process chapter content
+        let source = chapter.get("path").and_then(|p| p.as_str()).map(str::to_string);
         if let Some(content) = chapter.get_mut("content").and_then(|c| c.as_str()) {
-            let converted = convert_obsidian_links(content);
+            let converted = process_content(content, source.as_deref(), config, vault, bibliography);
             chapter["content"] = Value::String(converted);
         }
 
         // Process sub-items recursively
         if let Some(sub_items) = chapter.get_mut("sub_items").and_then(|s| s.as_array_mut()) {
             for sub in sub_items {
-                process_item(sub)?;
+                process_item(sub, config, vault, bibliography)?;
             }
         }
     }
@@ -86,20 +818,80 @@ fn process_item(item: &mut Value) -> Result<()> {
 ///
 /// # Arguments
 /// - `book` - The JSON value representing the book.
+/// - `config` - The preprocessor settings.
+/// - `vault` - The vault used to resolve embeds.
+/// - `bibliography` - The works citations refer to.
 ///
 /// # Returns
 /// A Result indicating success or failure.
-fn process_book(book: &mut Value) -> Result<()> {
+fn process_book(book: &mut Value, config: &Config, vault: &Vault, bibliography: &Bibliography) -> Result<()> {
     // mdBook 0.5+ uses "items" instead of "sections"
     if let Some(items) = book.get_mut("items").and_then(|s| s.as_array_mut()) {
         for item in items {
-            process_item(item)?;
+            process_item(item, config, vault, bibliography)?;
         }
     }
     Ok(())
 }
 
 
+/// Loads the vault of the book, including attachments kept outside src.
+///
+/// Attachments are searched in the configured asset directories and in the
+/// attachment folder set in the Obsidian vault settings.
+///
+/// # Arguments
+/// - `config` - The preprocessor settings.
+///
+/// # Returns
+/// The loaded vault or an I/O error.
+fn load_vault(config: &Config) -> Result<Vault> {
+    let mut vault = Vault::load(&config.src_dir)?;
+    vault.exclude(|path| config.is_excluded(path));
+    vault.set_ambiguity(config.links.ambiguous);
+    vault.set_folder_notes(&config.links.folder_notes);
+    vault.set_languages(&config.links.languages);
+    vault.set_uid_pattern(config.links.uid_pattern.clone());
+    let mut dirs: Vec<_> = config.assets.dirs.iter().map(|dir| config.root.join(dir)).collect();
+
+    if let Some(vault_dir) = config.vault_root() {
+        let settings = ObsidianSettings::load(&vault_dir)?;
+        if let Some(folder) = settings.attachment_folder() {
+            let dir = vault_dir.join(folder);
+            // Folders inside src are part of the vault scan already
+            if !dir.starts_with(&config.src_dir) && !config.src_dir.starts_with(&dir) {
+                dirs.push(dir);
+            }
+        }
+    }
+
+    for dir in dirs {
+        vault.load_external(&dir, &config.assets.copy_to)?;
+    }
+    Ok(vault)
+}
+
+
+/// Build the `[output.html.redirect]` table for the old names of renamed notes.
+///
+/// # Arguments
+/// - `config` - The preprocessor settings.
+/// - `vault` - The vault used to look up the new notes.
+///
+/// # Returns
+/// The TOML table redirecting the old pages to the new ones.
+fn redirect_table(config: &Config, vault: &Vault) -> String {
+    let mut table = String::from("[output.html.redirect]\n");
+    for (old, new) in &config.links.renamed {
+        let new = paths::strip_note_extension(new);
+        let new = vault.resolve(new, None).map_or(new, |path| vault.chapter_path(path));
+        let page = |path: &str| format!("/{}.html", paths::percent_encode(paths::strip_note_extension(path)));
+        table.push_str(&format!("\"{}\" = \"{}\"\n", page(old), page(new)));
+    }
+    table
+}
+
+
 /// Main function to handle input/output and command-line arguments.
 ///
 /// # Returns
@@ -116,6 +908,28 @@ fn main() -> Result<()> {
         }
     }
 
+    // Handle "write-css [path]": write the stylesheet for HTML callouts
+    if args.len() >= 2 && args[1] == "write-css" {
+        let path = args.get(2).map_or("theme/obsidian-callouts.css", String::as_str);
+        if let Some(parent) = std::path::Path::new(path).parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, callouts::CALLOUT_CSS)?;
+        println!("Wrote {}", path);
+        return Ok(());
+    }
+
+    // Handle "redirects [book-dir]": print the redirects for renamed notes
+    if args.len() >= 2 && args[1] == "redirects" {
+        let root = std::path::PathBuf::from(args.get(2).map_or(".", String::as_str));
+        let book_toml = std::fs::read_to_string(root.join("book.toml"))?;
+        let book_config: toml::Table = toml::from_str(&book_toml)?;
+        let context = serde_json::json!({ "root": root, "config": book_config });
+        let config = Config::from_context(&context)?;
+        print!("{}", redirect_table(&config, &load_vault(&config)?));
+        return Ok(());
+    }
+
     // Read input from stdin
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
@@ -125,10 +939,28 @@ fn main() -> Result<()> {
     // mdBook 0.5+ sends [context, book] as an array
     if let Some(arr) = input_json.as_array_mut() {
         if arr.len() == 2 {
+            let config = Config::from_context(&arr[0])?;
+            let mut vault = load_vault(&config)?;
+            let bibliography = Bibliography::load(&config)?;
+
             // Modify the book (second element)
             if let Some(book) = arr.get_mut(1) {
-                process_book(book)?;
+                frontmatter::remove_unpublished(book, &config.frontmatter);
+                canvas::add_canvas_chapters(book, &vault, &config.src_dir)?;
+                tags::add_tag_chapters(book, &config.tags, &|path| config.is_excluded(path));
+                citations::add_bibliography_chapter(book, &bibliography, &config.citations);
+                vault.add_chapters(book);
+                add_stub_chapters(book, &config.links, &mut vault);
+                process_book(book, &config, &vault, &bibliography)?;
+                if config.links.file_names == FileNameMode::Slug {
+                    filenames::slugify_chapters(book);
+                }
+                anchors::check_anchors(book, &config.links)?;
             }
+            vault.check_ambiguity()?;
+            check_unresolved(&vault, &config.links)?;
+            assets::copy_used_assets(&vault, &config.src_dir)?;
+
             // Return the entire array [context, modified_book]
             serde_json::to_writer(io::stdout(), &arr[1])?;
         } else {
@@ -145,13 +977,17 @@ fn main() -> Result<()> {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use config::{AnchorConfig, Slugger};
 
     #[test]
-    fn test_convert_anchor() {
-        assert_eq!(convert_anchor("Test test"), "test-test");
-        assert_eq!(convert_anchor("Test Test"), "test-test");
-        assert_eq!(convert_anchor("Hello World Example"), "hello-world-example");
-        assert_eq!(convert_anchor("UPPERCASE"), "uppercase");
+    fn test_anchor_sluggers() {
+        let link = |input: &str, config: &LinkConfig| convert_obsidian_links(input, None, config, &Vault::default());
+        let config = LinkConfig::default();
+        assert_eq!(link("[[note#Hello World]]", &config), "[note](note.md#hello-world)");
+        assert_eq!(link("[[#Read_me]]", &config), "[Read_me](#read_me)");
+
+        let config = LinkConfig { anchors: AnchorConfig { slugger: Slugger::ObsidianPublish, ..Default::default() }, ..Default::default() };
+        assert_eq!(link("[[note#Hello World]]", &config), "[note](note.md#Hello+World)");
     }
 
     #[test]
@@ -159,34 +995,585 @@ mod tests {
         // Variant 1: [[mdname#section|text]]
         let input1 = "[[chapter_111#Test test|Test]]";
         let expected1 = "[Test](chapter_111.md#test-test)";
-        assert_eq!(convert_obsidian_links(input1), expected1);
+        assert_eq!(convert_obsidian_links(input1, None, &LinkConfig::default(), &Vault::default()), expected1);
 
         // Variant 2: [[mdname#section]]
         let input2 = "[[chapter_111#Test test]]";
         let expected2 = "[chapter_111](chapter_111.md#test-test)";
-        assert_eq!(convert_obsidian_links(input2), expected2);
+        assert_eq!(convert_obsidian_links(input2, None, &LinkConfig::default(), &Vault::default()), expected2);
 
         // Variant 3: [[mdname|text]]
         let input3 = "[[chapter_111|Test]]";
         let expected3 = "[Test](chapter_111.md)";
-        assert_eq!(convert_obsidian_links(input3), expected3);
+        assert_eq!(convert_obsidian_links(input3, None, &LinkConfig::default(), &Vault::default()), expected3);
 
         // Variant 4: [[mdname]]
         let input4 = "[[chapter_111]]";
         let expected4 = "[chapter_111](chapter_111.md)";
-        assert_eq!(convert_obsidian_links(input4), expected4);
+        assert_eq!(convert_obsidian_links(input4, None, &LinkConfig::default(), &Vault::default()), expected4);
     }
 
     #[test]
     fn test_multiple_links() {
         let input = "Text [[a#B C|X]] und [[d]] und [[e#F]] und [[g|H]].";
         let expected = "Text [X](a.md#b-c) und [d](d.md) und [e](e.md#f) und [H](g.md).";
-        assert_eq!(convert_obsidian_links(input), expected);
+        assert_eq!(convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()), expected);
+    }
+
+    #[test]
+    fn test_file_links() {
+        let config = LinkConfig::default();
+        assert_eq!(convert_obsidian_links("[[diagram.svg]]", None, &config, &Vault::default()), "[diagram.svg](diagram.svg)");
+        assert_eq!(convert_obsidian_links("[[docs/My Manual.pdf|Manual]]", None, &config, &Vault::default()), "[Manual](docs/My%20Manual.pdf)");
+        assert_eq!(convert_obsidian_links("[[Plan.canvas]]", None, &config, &Vault::default()), "[Plan.canvas](Plan.canvas.md)");
+        assert_eq!(
+            convert_obsidian_links("[[v1.2 release notes]]", None, &config, &Vault::default()),
+            "[v1.2 release notes](v1.2%20release%20notes.md)"
+        );
+    }
+
+    #[test]
+    fn test_configured_extensions() {
+        let config = LinkConfig {
+            extension: "markdown".to_string(),
+            asset_extensions: vec!["step".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(convert_obsidian_links("[[note#Part]]", None, &config, &Vault::default()), "[note](note.markdown#part)");
+        assert_eq!(convert_obsidian_links("[[model.STEP]]", None, &config, &Vault::default()), "[model.STEP](model.STEP)");
+        assert_eq!(convert_obsidian_links("[[chart.png]]", None, &config, &Vault::default()), "[chart.png](chart.png.markdown)");
+
+        // Notes keep their own Markdown extension, other extensions replace it
+        let vault = Vault::from_notes(&[("notes/Readme.markdown", ""), ("notes/Other.md", "")]);
+        assert_eq!(convert_obsidian_links("[[Readme]]", None, &config, &vault), "[Readme](notes/Readme.markdown)");
+        assert_eq!(convert_obsidian_links("[[Other]]", None, &config, &vault), "[Other](notes/Other.md)");
+        let config = LinkConfig { extension: ".html".to_string(), ..Default::default() };
+        assert_eq!(convert_obsidian_links("[[Readme]]", None, &config, &vault), "[Readme](notes/Readme.html)");
+    }
+
+    #[test]
+    fn test_display_fallback() {
+        let vault = Vault::from_notes(&[
+            ("guide/api_errors.md", "# API Errors\n"),
+            ("guide/setup.md", "---\ntitle: Setting up\n---\n# Setup\n"),
+            ("guide/faq.md", "No heading"),
+        ]);
+        let with_sources = |display_fallback| LinkConfig { display_fallback, ..Default::default() };
+        let link = |input: &str, config: &LinkConfig| convert_obsidian_links(input, None, config, &vault);
+
+        let config = with_sources(vec![DisplaySource::Title, DisplaySource::Heading, DisplaySource::File]);
+        assert_eq!(link("[[setup]]", &config), "[Setting up](guide/setup.md)");
+        assert_eq!(link("[[guide/api_errors]]", &config), "[API Errors](guide/api_errors.md)");
+        assert_eq!(link("[[FAQ]]", &config), "[faq](guide/faq.md)");
+        assert_eq!(link("[[Missing]]", &config), "[Missing](Missing.md)");
+
+        let config = with_sources(vec![DisplaySource::Heading, DisplaySource::Path]);
+        assert_eq!(link("[[setup]]", &config), "[Setup](guide/setup.md)");
+        assert_eq!(link("[[faq]]", &config), "[guide/faq](guide/faq.md)");
+    }
+
+    #[test]
+    fn test_section_display() {
+        let config = LinkConfig { section_display: SectionDisplay::Obsidian, ..Default::default() };
+        let link = |input: &str| convert_obsidian_links(input, None, &config, &Vault::default());
+        assert_eq!(link("[[note#Install Steps]]"), "[note > Install Steps](note.md#install-steps)");
+        assert_eq!(link("[[note#Install Steps|Install]]"), "[Install](note.md#install-steps)");
+        assert_eq!(link("[[note]]"), "[note](note.md)");
+
+        let config = LinkConfig { section_display: SectionDisplay::Heading, ..Default::default() };
+        let link = |input: &str| convert_obsidian_links(input, None, &config, &Vault::default());
+        assert_eq!(link("[[note#Install Steps]]"), "[Install Steps](note.md#install-steps)");
+        assert_eq!(link("[[note#^step-1]]"), "[note](note.md#step-1)");
+    }
+
+    #[test]
+    fn test_root_links() {
+        let vault = Vault::from_notes(&[("guide/setup.md", ""), ("guide/guide/setup.md", ""), ("intro.md", "")]);
+        let config = LinkConfig { format: Some(LinkFormat::Relative), ..Default::default() };
+        let link = |input: &str| convert_obsidian_links(input, Some("guide/intro.md"), &config, &vault);
+        assert_eq!(link("[[/guide/setup]]"), "[guide/setup](setup.md)");
+        assert_eq!(link("[[guide/setup]]"), "[guide/setup](guide/setup.md)");
+        assert_eq!(link("[[/intro#Start]]"), "[intro](../intro.md#start)");
+        assert_eq!(link("[[/drafts/idea]]"), "[drafts/idea](../drafts/idea.md)");
+
+        let config = LinkConfig { vault_prefix: "book/src".to_string(), ..Default::default() };
+        assert_eq!(convert_obsidian_links("[[/book/src/intro]]", None, &config, &vault), "[book/src/intro](intro.md)");
+    }
+
+    #[test]
+    fn test_draft_links() {
+        let mut vault = Vault::from_notes(&[("intro.md", "")]);
+        vault.add_chapters(&serde_json::json!({ "items": [
+            { "Chapter": { "name": "Intro", "path": "intro.md", "source_path": "intro.md", "content": "", "sub_items": [] } },
+            { "Chapter": { "name": "Road Map", "path": null, "content": "", "sub_items": [] } },
+        ] }));
+        let link = |input: &str, config: &LinkConfig| convert_obsidian_links(input, Some("intro.md"), config, &vault);
+
+        let config = LinkConfig::default();
+        assert_eq!(link("[[road map#Q1|Plans]] and [[Intro]]", &config), "Plans and [Intro](intro.md)");
+        assert_eq!(link("| [[Road Map]] |", &config), "| Road Map |");
+        let config = LinkConfig { drafts: DraftLinkMode::Styled, ..Default::default() };
+        assert_eq!(link("[[Road Map]]", &config), "<span class=\"obsidian-draft\">Road Map</span>");
+    }
+
+    #[test]
+    fn test_uid_links() {
+        let mut vault = Vault::from_notes(&[("zettel/202301011230 Some Title.md", "")]);
+        vault.set_uid_pattern(Regex::new(r"^\d{12}$").ok());
+        let link = |input: &str| convert_obsidian_links(input, None, &LinkConfig::default(), &vault);
+        assert_eq!(link("[[202301011230]]"), "[Some Title](zettel/202301011230%20Some%20Title.md)");
+        assert_eq!(link("[[202301011230|Idea]]"), "[Idea](zettel/202301011230%20Some%20Title.md)");
+    }
+
+    #[test]
+    fn test_daily_note_links() {
+        let vault = Vault::from_notes(&[("Journal/2024/2024-05-02.md", ""), ("2024-05-03.md", "")]);
+        let mut config = LinkConfig::default();
+        config.daily_notes.folder = Some("Journal".to_string());
+        config.daily_notes.format = Some("YYYY/YYYY-MM-DD".to_string());
+        let link = |input: &str, config: &LinkConfig| convert_obsidian_links(input, Some("guide/intro.md"), config, &vault);
+        assert_eq!(link("[[2024-05-01]]", &config), "[2024-05-01](../Journal/2024/2024-05-01.md)");
+        assert_eq!(link("[[2024-05-02#Tasks]]", &config), "[2024-05-02](../Journal/2024/2024-05-02.md#tasks)");
+        assert_eq!(link("[[2024-05-03]]", &config), "[2024-05-03](../2024-05-03.md)");
+
+        config.daily_notes.display = "MMMM D, YYYY".to_string();
+        assert_eq!(link("[[2024-05-01]]", &config), "[May 1, 2024](../Journal/2024/2024-05-01.md)");
+        assert_eq!(link("[[2024-05-01|Today]]", &config), "[Today](../Journal/2024/2024-05-01.md)");
+
+        config.weekly_notes.folder = Some("Reviews/Weekly".to_string());
+        config.weekly_notes.format = Some("gggg-[W]ww".to_string());
+        config.monthly_notes.format = Some("YYYY/YYYY-MM".to_string());
+        assert_eq!(link("[[2024-W18]]", &config), "[2024-W18](../Reviews/Weekly/2024-W18.md)");
+        assert_eq!(link("[[2024-05]]", &config), "[2024-05](../2024/2024-05.md)");
+        assert_eq!(link("[[2024]]", &config), "[2024](2024.md)");
+    }
+
+    #[test]
+    fn test_renamed_notes() {
+        let vault = Vault::from_notes(&[("guide/Getting Started.md", ""), ("Old.md", "")]);
+        let mut config = LinkConfig::default();
+        config.renamed.insert("Setup".to_string(), "guide/Getting Started".to_string());
+        config.renamed.insert("Old".to_string(), "guide/Getting Started".to_string());
+        let link = |input: &str| convert_obsidian_links(input, None, &config, &vault);
+        assert_eq!(link("[[setup#Linux]]"), "[setup](guide/Getting%20Started.md#linux)");
+        assert_eq!(link("[[Old]]"), "[Old](Old.md)");
+
+        let config = Config { links: config, ..Default::default() };
+        assert_eq!(
+            redirect_table(&config, &vault),
+            "[output.html.redirect]\n\"/Old.html\" = \"/guide/Getting%20Started.html\"\n\"/Setup.html\" = \"/guide/Getting%20Started.html\"\n"
+        );
+    }
+
+    #[test]
+    fn test_explicit_extensions() {
+        let vault = Vault::from_notes(&[("guide/Setup.md", "")]);
+        let link = |input: &str| convert_obsidian_links(input, None, &LinkConfig::default(), &vault);
+        assert_eq!(link("[[note.md]]"), "[note](note.md)");
+        assert_eq!(link("[[Setup.md#Linux]]"), "[Setup](guide/Setup.md#linux)");
+        assert_eq!(link("[[guide/Setup.markdown|Setup]]"), "[Setup](guide/Setup.md)");
+    }
+
+    #[test]
+    fn test_url_links() {
+        let config = LinkConfig::default();
+        assert_eq!(
+            convert_obsidian_links("[[https://example.com/docs#Intro|Docs]]", None, &config, &Vault::default()),
+            "[Docs](https://example.com/docs#Intro)"
+        );
+        assert_eq!(convert_obsidian_links("[[https://example.com]]", None, &config, &Vault::default()), "[https://example.com](https://example.com)");
+    }
+
+    #[test]
+    fn test_block_links() {
+        let config = LinkConfig::default();
+        assert_eq!(convert_obsidian_links("[[note#^Quote-1]]", None, &config, &Vault::default()), "[note](note.md#Quote-1)");
+        assert_eq!(convert_obsidian_links("[[My Note#^abc|the quote]]", None, &config, &Vault::default()), "[the quote](My%20Note.md#abc)");
+    }
+
+    #[test]
+    fn test_same_file_links() {
+        let config = LinkConfig::default();
+        let vault = Vault::from_notes(&[("intro.md", "- A list item ^item\n\nA very long paragraph that goes on and on and on ^long")]);
+        assert_eq!(convert_obsidian_links("[[#Getting Started]]", None, &config, &vault), "[Getting Started](#getting-started)");
+        assert_eq!(convert_obsidian_links("[[#^item]]", Some("intro.md"), &config, &vault), "[A list item](#item)");
+        assert_eq!(
+            convert_obsidian_links("[[#^long]]", Some("intro.md"), &config, &vault),
+            "[A very long paragraph that goes on and…](#long)"
+        );
+        assert_eq!(convert_obsidian_links("[[#^missing|see]] [[#^gone]]", None, &config, &vault), "[see](#missing) [^gone](#gone)");
+    }
+
+    #[test]
+    fn test_chained_headings() {
+        let config = LinkConfig::default();
+        assert_eq!(convert_obsidian_links("[[note#Setup#Linux]]", None, &config, &Vault::default()), "[note](note.md#linux)");
+        assert_eq!(convert_obsidian_links("[[#Setup#Linux|here]]", None, &config, &Vault::default()), "[here](#linux)");
+
+        let vault = Vault::from_notes(&[("note.md", "# Note\n\n## Windows\n\n### Linux\n\n## Setup\n\n### Linux\n")]);
+        assert_eq!(convert_obsidian_links("[[note#Linux]]", None, &config, &vault), "[note](note.md#linux)");
+        assert_eq!(convert_obsidian_links("[[note#Setup#Linux]]", None, &config, &vault), "[note](note.md#linux-1)");
+        assert_eq!(convert_obsidian_links("[[#Setup#Linux]]", Some("note.md"), &config, &vault), "[Linux](#linux-1)");
+    }
+
+    #[test]
+    fn test_title_display() {
+        let config = LinkConfig { display: LinkDisplay::Title, ..Default::default() };
+        let vault = Vault::from_notes(&[("setup.md", "---\ntitle: Getting Started\n---\n"), ("plain.md", "Text")]);
+        assert_eq!(convert_obsidian_links("[[setup]]", None, &config, &vault), "[Getting Started](setup.md)");
+        assert_eq!(convert_obsidian_links("[[setup|Alias]]", None, &config, &vault), "[Alias](setup.md)");
+        assert_eq!(convert_obsidian_links("[[plain]]", None, &config, &vault), "[plain](plain.md)");
+    }
+
+    #[test]
+    fn test_alias_links() {
+        let vault = Vault::from_notes(&[("guide/setup.md", "---\naliases: [Getting Started]\n---\n")]);
+        assert_eq!(
+            convert_obsidian_links("[[Getting Started#Step 1]]", Some("intro/index.md"), &LinkConfig::default(), &vault),
+            "[Getting Started](../guide/setup.md#step-1)"
+        );
+        assert_eq!(convert_obsidian_links("[[Unknown]]", None, &LinkConfig::default(), &vault), "[Unknown](Unknown.md)");
+    }
+
+    #[test]
+    fn test_nested_chapter_links() {
+        let vault = Vault::from_notes(&[("guide/intro.md", ""), ("reference/api.md", ""), ("guide/setup.md", "")]);
+        let config = LinkConfig::default();
+        let link = |input: &str| convert_obsidian_links(input, Some("guide/intro.md"), &config, &vault);
+        assert_eq!(link("[[reference/api#Errors]]"), "[reference/api](../reference/api.md#errors)");
+        assert_eq!(link("[[guide/setup]]"), "[guide/setup](setup.md)");
+        assert_eq!(link("[[api|API]]"), "[API](../reference/api.md)");
+        assert_eq!(link("[[reference/missing]]"), "[reference/missing](../reference/missing.md)");
+        assert_eq!(link("[[../reference/api|API]]"), "[API](../reference/api.md)");
+        assert_eq!(link("[[./setup]]"), "[./setup](setup.md)");
+        assert_eq!(link("[[reference\\api|API]]"), "[API](../reference/api.md)");
+        assert_eq!(link("[[..\\reference\\api\\|API]]"), "[API](../reference/api.md)");
+        assert_eq!(link("[[reference//./api/]]"), "[reference//./api/](../reference/api.md)");
+        assert_eq!(link("[[../../outside]]"), "[../../outside](../../outside.md)");
+    }
+
+    #[test]
+    fn test_encoded_targets() {
+        let config = LinkConfig::default();
+        assert_eq!(
+            convert_obsidian_links("[[My Great Note (draft)#Step 1]]", None, &config, &Vault::default()),
+            "[My Great Note (draft)](My%20Great%20Note%20%28draft%29.md#step-1)"
+        );
+        assert_eq!(convert_obsidian_links("[[Why? <Basics> 100%|FAQ]]", None, &config, &Vault::default()), "[FAQ](Why%3F%20%3CBasics%3E%20100%25.md)");
+    }
+
+    #[test]
+    fn test_chapter_structure() {
+        let mut vault = Vault::from_notes(&[("notes/Setup.md", "")]);
+        vault.add_chapters(&serde_json::json!({ "items": [
+            { "Chapter": { "name": "Setup", "path": "guide/install/Setup.md", "source_path": "notes/Setup.md", "sub_items": [] } },
+            { "Chapter": { "name": "References", "path": "references.md", "source_path": null, "sub_items": [] } },
+        ] }));
+        let config = LinkConfig::default();
+        let link = |input: &str| convert_obsidian_links(input, Some("guide/intro.md"), &config, &vault);
+        assert_eq!(link("[[Setup#Linux]]"), "[Setup](install/Setup.md#linux)");
+        assert_eq!(link("[[references]]"), "[references](../references.md)");
+    }
+
+    #[test]
+    fn test_link_formats() {
+        let vault = Vault::from_notes(&[("guide/sub/setup.md", ""), ("sub/setup.md", ""), ("guide/intro.md", "")]);
+        let link = |input: &str, config: &LinkConfig| convert_obsidian_links(input, Some("guide/intro.md"), config, &vault);
+        assert_eq!(link("[[sub/setup]]", &LinkConfig::default()), "[sub/setup](../sub/setup.md)");
+
+        let relative = LinkConfig { format: Some(LinkFormat::Relative), ..Default::default() };
+        assert_eq!(link("[[sub/setup]]", &relative), "[sub/setup](sub/setup.md)");
+
+        let prefixed = LinkConfig { vault_prefix: "book/src".to_string(), ..Default::default() };
+        assert_eq!(link("[[book/src/sub/setup|Setup]]", &prefixed), "[Setup](../sub/setup.md)");
+    }
+
+    #[test]
+    fn test_markdown_links() {
+        let vault = Vault::from_notes(&[("guide/My Setup.md", ""), ("intro.md", "")]);
+        let config = LinkConfig::default();
+        let input = "[Setup](My%20Setup.md#First%20Step \"title\") [Intro](intro.md) ![img](My%20Setup.md) [x](missing.md) `[c](My%20Setup.md)`";
+        assert_eq!(
+            convert_markdown_links(input, Some("intro.md"), &config, &vault),
+            "[Setup](guide/My%20Setup.md#first-step \"title\") [Intro](intro.md) ![img](My%20Setup.md) [x](missing.md) `[c](My%20Setup.md)`"
+        );
+    }
+
+    #[test]
+    fn test_display_prefix() {
+        let config = LinkConfig { display_prefix: Some(Regex::new(r"^\d+\s*-\s*").unwrap()), ..Default::default() };
+        let link = |input: &str| convert_obsidian_links(input, None, &config, &Vault::default());
+        assert_eq!(link("[[01 - Introduction]]"), "[Introduction](01%20-%20Introduction.md)");
+        assert_eq!(link("[[guide/02 - Setup#Linux]]"), "[guide/Setup](guide/02%20-%20Setup.md#linux)");
+        assert_eq!(link("[[01 - Introduction|01 - Intro]]"), "[01 - Intro](01%20-%20Introduction.md)");
+        assert_eq!(link("[[2024]]"), "[2024](2024.md)");
+    }
+
+    #[test]
+    fn test_external_targets() {
+        let mut config = LinkConfig::default();
+        config.external_urls.insert("private/journal".to_string(), "https://publish.obsidian.md/me/Journal".to_string());
+        let vault = Vault::from_notes(&[("private/Journal.md", ""), ("intro.md", "")]);
+        let link = |input: &str| convert_obsidian_links(input, Some("intro.md"), &config, &vault);
+        assert_eq!(link("[[Private/Journal#Week 1|Journal]]"), "[Journal](https://publish.obsidian.md/me/Journal#week-1)");
+        assert_eq!(link("[[intro]]"), "[intro](intro.md)");
+    }
+
+    #[test]
+    fn test_other_books() {
+        let mut config = LinkConfig::default();
+        config.books.insert("handbook".to_string(), "https://docs.example.com/handbook/".to_string());
+        let vault = Vault::from_notes(&[("guide/Setup.md", "")]);
+        let link = |input: &str| convert_obsidian_links(input, None, &config, &vault);
+        assert_eq!(link("[[handbook/Onboarding]]"), "[Onboarding](https://docs.example.com/handbook/Onboarding.html)");
+        assert_eq!(
+            link("[[handbook/team/First Day#Badge|Day 1]]"),
+            "[Day 1](https://docs.example.com/handbook/team/First%20Day.html#badge)"
+        );
+        assert_eq!(link("[[guide/Setup]]"), "[guide/Setup](guide/Setup.md)");
+    }
+
+    #[test]
+    fn test_unresolved_links() {
+        let vault = Vault::from_notes(&[("guide/Setup.md", "")]).with_assets(&["img/logo.png"]);
+        let with_mode = |unresolved| LinkConfig { unresolved, ..Default::default() };
+        let input = "[[Setup]] [[Missing|Soon]] [[logo.png]] [[#Top]]";
+        let link = |mode| convert_obsidian_links(input, Some("intro.md"), &with_mode(mode), &vault);
+
+        assert_eq!(link(UnresolvedLinkMode::Keep), "[Setup](guide/Setup.md) [[Missing|Soon]] [logo.png](logo.png) [Top](#top)");
+        assert_eq!(link(UnresolvedLinkMode::Text), "[Setup](guide/Setup.md) Soon [logo.png](logo.png) [Top](#top)");
+        assert_eq!(
+            link(UnresolvedLinkMode::Broken),
+            "[Setup](guide/Setup.md) <span class=\"obsidian-unresolved\">Soon</span> [logo.png](logo.png) [Top](#top)"
+        );
+        assert_eq!(link(UnresolvedLinkMode::Error), "[Setup](guide/Setup.md) [Soon](Missing.md) [logo.png](logo.png) [Top](#top)");
+        assert_eq!(vault.unresolved_links(), vec![("Missing".to_string(), "intro.md".to_string())]);
+        assert!(check_unresolved(&vault, &with_mode(UnresolvedLinkMode::Error)).is_err());
+    }
+
+    #[test]
+    fn test_stub_chapters() {
+        let mut vault = Vault::from_notes(&[("intro.md", ""), ("guide/Setup.md", "")]);
+        let config = LinkConfig { stubs: true, unresolved: UnresolvedLinkMode::Error, ..Default::default() };
+        let content = "[[Setup]] [[Roadmap#Q1]] [[guide/Linux|On Linux]] [[diagram.svg]] `[[Code]]` [[roadmap]]";
+        let mut book = serde_json::json!({ "items": [
+            { "Chapter": { "name": "Intro", "path": "intro.md", "source_path": "intro.md", "content": content, "sub_items": [] } },
+        ] });
+        vault.add_chapters(&book);
+        add_stub_chapters(&mut book, &config, &mut vault);
+
+        assert_eq!(book["items"][1]["PartTitle"], "Unwritten notes");
+        let chapters = book::chapters(&book);
+        assert_eq!(chapters.len(), 3);
+        assert_eq!(chapters[1]["name"], "Linux");
+        assert_eq!(chapters[1]["path"], "guide/Linux.md");
+        assert_eq!(chapters[2]["path"], "Roadmap.md");
+        assert_eq!(chapters[2]["content"], "# Roadmap\n\n*This note hasn't been written yet.*\n");
+        assert_eq!(
+            convert_obsidian_links("[[Roadmap#Q1]] [[guide/Linux|On Linux]]", Some("guide/Setup.md"), &config, &vault),
+            "[Roadmap](../Roadmap.md#q1) [On Linux](Linux.md)"
+        );
+        assert!(vault.unresolved_links().is_empty());
+    }
+
+    #[test]
+    fn test_case_insensitive_links() {
+        let vault = Vault::from_notes(&[("README.md", ""), ("guide/Setup Steps.md", "")]);
+        let config = LinkConfig::default();
+        assert_eq!(convert_obsidian_links("[[readme]]", None, &config, &vault), "[readme](README.md)");
+        assert_eq!(
+            convert_obsidian_links("[[setup steps#Linux]]", Some("guide/intro.md"), &config, &vault),
+            "[setup steps](Setup%20Steps.md#linux)"
+        );
+    }
+
+    #[test]
+    fn test_obsidian_uris() {
+        let vault = Vault::from_notes(&[("guide/My Setup.md", "")]);
+        let config = LinkConfig::default();
+        assert_eq!(
+            convert_obsidian_uris("[Setup](obsidian://open?vault=Notes&file=book%2Fsrc%2Fguide%2FMy%20Setup)", Some("intro.md"), &config, &vault),
+            "[Setup](guide/My%20Setup.md)"
+        );
+        assert_eq!(
+            convert_obsidian_uris("See <obsidian://vault/Notes/guide/My%20Setup.md%23Step%201>.", Some("guide/a.md"), &config, &vault),
+            "See [My Setup](My%20Setup.md#step-1)."
+        );
+        let unknown = "[x](obsidian://open?vault=Notes&file=Missing) obsidian://new?name=x";
+        assert_eq!(convert_obsidian_uris(unknown, None, &config, &vault), unknown);
+    }
+
+    #[test]
+    fn test_code_is_kept() {
+        let input = "```md title=\"Example\"\n[[Note]]\n```\n- Item\n  ~~~\n  [[Note]]\n  ~~~\n> ```\n> [[Note]]\n> ```\n[[Note]]";
+        let converted = convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default());
+        assert_eq!(converted.strip_suffix("[Note](Note.md)"), input.strip_suffix("[[Note]]"));
+    }
+
+    #[test]
+    fn test_code_spans_are_kept() {
+        let input = "`` `[[not a link]]` `` and `[[split\nlines]]` but [[Note]]";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "`` `[[not a link]]` `` and `[[split\nlines]]` but [Note](Note.md)"
+        );
+    }
+
+    #[test]
+    fn test_escaped_links() {
+        let input = "\\[[Note]] \\![[Image]] \\\\[[Note]]";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "\\[[Note]] \\![[Image]] \\\\[Note](Note.md)"
+        );
+
+        // HTML blocks don't process Markdown escapes, so the backslash goes
+        let input = "<div>\n\\[[Other]] !\\[[Image]] and [[Other]]\n</div>";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "<div>\n[[Other]] ![[Image]] and [Other](Other.md)\n</div>"
+        );
+    }
+
+    #[test]
+    fn test_html_comments_are_kept() {
+        let input = "<!-- [[Draft]] -->\n<!--\n[[Old]]\n-->\n[[Note]]";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "<!-- [[Draft]] -->\n<!--\n[[Old]]\n-->\n[Note](Note.md)"
+        );
+    }
+
+    #[test]
+    fn test_html_blocks() {
+        let input = "<details>\n<summary>[[Note|A & B]]</summary>\n</details>\n\n[[Note]]";
+        let mut config = LinkConfig::default();
+        assert_eq!(
+            convert_obsidian_links(input, None, &config, &Vault::default()),
+            "<details>\n<summary>[A & B](Note.md)</summary>\n</details>\n\n[Note](Note.md)"
+        );
+        config.html_blocks = HtmlBlockMode::Skip;
+        assert_eq!(
+            convert_obsidian_links(input, None, &config, &Vault::default()),
+            "<details>\n<summary>[[Note|A & B]]</summary>\n</details>\n\n[Note](Note.md)"
+        );
+        config.html_blocks = HtmlBlockMode::Html;
+        assert_eq!(
+            convert_obsidian_links(input, None, &config, &Vault::default()),
+            "<details>\n<summary><a href=\"Note.md\">A &amp; B</a></summary>\n</details>\n\n[Note](Note.md)"
+        );
+    }
+
+    #[test]
+    fn test_escaped_pipes() {
+        let input = "| Link | Note |\n|---|---|\n| [[guide\\|Guide]] | [[setup#Step 1\\|Step]] |\n| [[a\\b]] | x |";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "| Link | Note |\n|---|---|\n| [Guide](guide.md) | [Step](setup.md#step-1) |\n| [a\\b](a/b.md) | x |"
+        );
+    }
+
+    #[test]
+    fn test_table_links() {
+        let vault = Vault::from_notes(&[("guide.md", "# Input | Output\n")]);
+        let config = LinkConfig { display: LinkDisplay::Title, ..Default::default() };
+        assert_eq!(
+            convert_obsidian_links("| Link |\n|---|\n| [[guide]] |\n\n[[guide]]", None, &config, &vault),
+            "| Link |\n|---|\n| [Input \\| Output](guide.md) |\n\n[Input | Output](guide.md)"
+        );
+    }
+
+    #[test]
+    fn test_heading_links() {
+        let input = "## See [[Other Note|the docs]]\n\nRead [[Other Note]]";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "## See [the docs](Other%20Note.md)\n\nRead [Other Note](Other%20Note.md)"
+        );
+        let config = LinkConfig { headings: HeadingLinkMode::Text, ..Default::default() };
+        assert_eq!(
+            convert_obsidian_links(input, None, &config, &Vault::default()),
+            "## See the docs\n\nRead [Other Note](Other%20Note.md)"
+        );
+    }
+
+    #[test]
+    fn test_footnote_definitions() {
+        let input = "See this[^1].\n\n[^1]: See [[Other Note]] and\n    [[Third|more]].";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "See this[^1].\n\n[^1]: See [Other Note](Other%20Note.md) and\n    [more](Third.md)."
+        );
+    }
+
+    #[test]
+    fn test_callout_titles() {
+        let admonish = "```admonish note title=\"See [[Other Note|\\\"it\\\"]]\"\nRead [[Other Note]]\n```";
+        assert_eq!(
+            convert_obsidian_links(admonish, None, &LinkConfig::default(), &Vault::default()),
+            "```admonish note title=\"See [\\\"it\\\"](Other%20Note.md)\"\nRead [Other Note](Other%20Note.md)\n```"
+        );
+        let html = "<div class=\"callout-title\"><span class=\"callout-icon\">ℹ️</span> See [[Other Note]]</div>";
+        assert_eq!(
+            convert_obsidian_links(html, None, &LinkConfig::default(), &Vault::default()),
+            "<div class=\"callout-title\"><span class=\"callout-icon\">ℹ️</span> See <a href=\"Other%20Note.md\">Other Note</a></div>"
+        );
+        let code = "```\n```admonish note title=\"[[x]]\"\n```";
+        assert_eq!(convert_obsidian_links(code, None, &LinkConfig::default(), &Vault::default()), code);
+    }
+
+    #[test]
+    fn test_math_is_kept() {
+        let input = "$M = [[a,b],[c,d]]$ and [[Note]]\n$$\n[[1,0],[0,1]]\n$$";
+        assert_eq!(
+            convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()),
+            "$M = [[a,b],[c,d]]$ and [Note](Note.md)\n$$\n[[1,0],[0,1]]\n$$"
+        );
+    }
+
+    #[test]
+    fn test_directives_are_kept() {
+        let input = "{{#include ../[[draft]]/code.rs:2:10}}\n{{#playground example.rs editable}}\n{{#title Notes #1 ==draft==}}\n[[Note]]";
+        let mut config = Config::default();
+        config.formatting.highlight = config::HighlightStyle::Mark;
+        config.tags.mode = config::TagMode::Badge;
+        let converted = process_content(input, None, &config, &Vault::default(), &Bibliography::default());
+        assert_eq!(converted.strip_suffix("[Note](Note.md)"), input.strip_suffix("[[Note]]"));
+    }
+
+    #[test]
+    fn test_diagrams_are_kept() {
+        let diagram = "graph TD\n  A[[Sub]] --> B{{hex}}\n  %% comment\n  B --> C[\"#tag ==x== ^id\"]\n- [-] task:: value";
+        let quote = |text: &str| text.lines().map(|l| format!("> {}", l)).collect::<Vec<_>>().join("\n");
+        let indent = |text: &str| text.lines().map(|l| format!("  {}", l)).collect::<Vec<_>>().join("\n");
+        let inputs = [
+            format!("```mermaid\n{}\n```", diagram),
+            format!("~~~mermaid\n{}\n~~~", diagram),
+            "```dot\ndigraph { a -> b [label=\"[[Note]]\"] }\n```".to_string(),
+            format!("> [!note] Diagram\n> ```mermaid\n{}\n> ```", quote(diagram)),
+            format!("- Item\n\n  ```mermaid\n{}\n  ```", indent(diagram)),
+            format!("````md\n```mermaid\n{}\n```\n````", diagram),
+        ];
+
+        let mut config = Config::default();
+        config.callouts.mode = config::CalloutMode::Html;
+        config.formatting.highlight = config::HighlightStyle::Mark;
+        config.formatting.comments = config::CommentStyle::Strip;
+        config.formatting.line_breaks = true;
+        config.tags.mode = config::TagMode::Badge;
+        config.dataview.inline_fields = config::InlineFieldStyle::Bold;
+        for input in &inputs {
+            let converted = process_content(input, None, &config, &Vault::default(), &Bibliography::default());
+            let body = input.lines().filter(|l| !l.contains("[!note]") && !l.starts_with("- Item"));
+            for line in body.map(|l| l.trim_start_matches(['>', ' '])).filter(|l| !l.is_empty()) {
+                assert!(converted.contains(line), "{:?} lost in {:?}", line, converted);
+            }
+        }
     }
 
     #[test]
     fn test_no_conversion_needed() {
         let input = "Normal Text [normal](link.md)";
-        assert_eq!(convert_obsidian_links(input), input);
+        assert_eq!(convert_obsidian_links(input, None, &LinkConfig::default(), &Vault::default()), input);
     }
 }
@@ -0,0 +1,816 @@
+//! Small helpers for the line-based parts of Markdown the passes need.
+
+
+/// Parse an ATX heading line.
+///
+/// # Examples
+/// `## Install Steps ##` becomes `Some((2, "Install Steps"))`
+///
+/// # Arguments
+/// - `line` - A single line without the trailing newline.
+///
+/// # Returns
+/// The heading level and the heading text, or `None` if the line is no heading.
+pub fn heading(line: &str) -> Option<(usize, &str)> {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return None;
+    }
+
+    let level = trimmed.chars().take_while(|&c| c == '#').count();
+    let rest = &trimmed[level..];
+    if level == 0 || level > 6 || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+
+    let text = rest.trim();
+    let text = text.trim_end_matches('#');
+    if text.is_empty() || text.ends_with([' ', '\t']) {
+        Some((level, text.trim()))
+    } else {
+        Some((level, rest.trim()))
+    }
+}
+
+
+/// Returns true if the line starts a list item.
+///
+/// # Arguments
+/// - `line` - The line to check.
+///
+/// # Returns
+/// Whether the line is a bullet or numbered list item.
+pub fn is_list_item(line: &str) -> bool {
+    let trimmed = line.trim_start();
+    let digits = trimmed.chars().take_while(|c| c.is_ascii_digit()).count();
+    let rest = &trimmed[digits..];
+    if digits > 0 {
+        rest.starts_with(". ") || rest.starts_with(") ")
+    } else {
+        rest.starts_with("- ") || rest.starts_with("* ") || rest.starts_with("+ ")
+    }
+}
+
+
+/// Returns true if the character at a byte offset is escaped with a backslash.
+///
+/// # Arguments
+/// - `text` - The text.
+/// - `index` - The byte offset of the character.
+///
+/// # Returns
+/// Whether an odd number of backslashes precedes the character.
+pub fn is_escaped(text: &str, index: usize) -> bool {
+    text.as_bytes()[..index].iter().rev().take_while(|&&b| b == b'\\').count() % 2 == 1
+}
+
+
+/// Split the blockquote markers off a line.
+///
+/// # Arguments
+/// - `line` - The line.
+///
+/// # Returns
+/// The quote depth and the text inside the quote.
+pub fn unquote_all(line: &str) -> (usize, &str) {
+    let mut depth = 0;
+    let mut rest = line;
+    while let Some(inner) = rest.trim_start().strip_prefix('>') {
+        depth += 1;
+        rest = inner.strip_prefix(' ').unwrap_or(inner);
+    }
+    (depth, rest)
+}
+
+
+/// Returns true if the line is the delimiter row of a table (`| --- | :-: |`).
+pub fn is_table_delimiter(line: &str) -> bool {
+    let cells: Vec<&str> = line.trim().trim_matches('|').split('|').map(str::trim).collect();
+    line.contains('-')
+        && cells.iter().all(|c| !c.is_empty() && c.trim_matches(':').chars().all(|ch| ch == '-'))
+        && (line.contains('|') || cells.len() > 1)
+}
+
+
+/// Tracks whether lines are inside a fenced code block.
+///
+/// The content of ` ```admonish ` blocks, which the callout pass generates,
+/// is Markdown and therefore not code; only their fence lines are.
+#[derive(Debug, Clone, Default)]
+pub struct FenceTracker {
+    /// The fence character, length, whether it's an admonish block and
+    /// whether it's inside a blockquote, for every open fence from the
+    /// outside in.
+    open: Vec<(char, usize, bool, bool)>,
+}
+
+impl FenceTracker {
+    /// Returns true if the lines fed so far end inside a code block, not
+    /// counting admonish blocks.
+    pub fn in_code(&self) -> bool {
+        self.open.last().is_some_and(|&(_, _, admonish, _)| !admonish)
+    }
+
+    /// Feed the next line and report whether it belongs to a code block.
+    ///
+    /// Opening and closing fence lines count as part of the code block.
+    ///
+    /// # Arguments
+    /// - `line` - The next line of the document.
+    ///
+    /// # Returns
+    /// Whether the line is code.
+    pub fn is_code(&mut self, line: &str) -> bool {
+        // Fences may be indented in list items or quoted in blockquotes,
+        // and quoted fences end with the blockquote
+        let quoted = line.trim_start().starts_with('>');
+        let trimmed = line.trim_start_matches([' ', '\t', '>']);
+        while self.open.last().is_some_and(|&(_, _, _, in_quote)| in_quote && !quoted) {
+            self.open.pop();
+        }
+        let marker = trimmed.chars().next().filter(|&c| c == '`' || c == '~');
+        let fence = marker.map(|c| (c, trimmed.chars().take_while(|&x| x == c).count()));
+
+        match (self.open.last().copied(), fence) {
+            (Some((c, n, _, _)), Some((m, len))) if m == c && len >= n && trimmed[len..].trim().is_empty() => {
+                self.open.pop();
+                true
+            }
+            (Some((_, _, false, _)), _) => true,
+            (_, Some((c, len))) if len >= 3 && !(c == '`' && trimmed[len..].contains('`')) => {
+                let admonish = trimmed[len..].trim_start().starts_with("admonish");
+                self.open.push((c, len, admonish, quoted));
+                true
+            }
+            _ => false,
+        }
+    }
+}
+
+
+/// Split a line into inline code spans and the text between them.
+///
+/// A code span starts with a run of backticks and ends with a run of the
+/// same length. Backticks without a matching run and escaped backticks
+/// (`` \` ``) are text.
+///
+/// # Arguments
+/// - `line` - A single line, or several lines of a paragraph.
+///
+/// # Returns
+/// The parts of the line in order, each flagged whether it is a code span.
+pub fn code_spans(line: &str) -> Vec<(bool, &str)> {
+    let bytes = line.as_bytes();
+    let run_at = |i: usize| bytes[i..].iter().take_while(|&&b| b == b'`').count();
+
+    let mut parts = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    while i < bytes.len() {
+        if bytes[i] != b'`' {
+            i += 1;
+            continue;
+        }
+        // An escaped backtick is text
+        if is_escaped(line, i) {
+            i += 1;
+            continue;
+        }
+
+        let run = run_at(i);
+        let mut j = i + run;
+        let mut close = None;
+        while let Some(offset) = line[j..].find('`') {
+            let len = run_at(j + offset);
+            if len == run {
+                close = Some(j + offset);
+                break;
+            }
+            j += offset + len;
+        }
+
+        match close {
+            Some(end) => {
+                if start < i {
+                    parts.push((false, &line[start..i]));
+                }
+                parts.push((true, &line[i..end + run]));
+                i = end + run;
+                start = i;
+            }
+            None => i += run,
+        }
+    }
+    if start < line.len() {
+        parts.push((false, &line[start..]));
+    }
+    parts
+}
+
+
+/// Split text into math and the text between it.
+///
+/// Display math (`$$...$$`) may span lines and ends at the latest with a
+/// blank line. Inline math (`$...$`) follows Pandoc's rules: the opening `$`
+/// isn't followed by whitespace, the closing one isn't preceded by
+/// whitespace or followed by a digit, so prices like `$5 and $10` are text.
+///
+/// # Arguments
+/// - `text` - A part of a line outside code spans.
+/// - `display` - Whether display math is open, updated for the next part.
+///
+/// # Returns
+/// The parts of the text in order, each flagged whether it is math.
+pub fn math_spans<'a>(text: &'a str, display: &mut bool) -> Vec<(bool, &'a str)> {
+    let bytes = text.as_bytes();
+    let escaped = |i: usize| is_escaped(text, i);
+    let dollar = |i: usize| bytes.get(i) == Some(&b'$') && !escaped(i);
+
+    let mut parts = Vec::new();
+    let (mut start, mut i) = (0, 0);
+    if *display {
+        match text.match_indices("$$").find(|&(j, _)| !escaped(j)) {
+            Some((j, _)) => {
+                parts.push((true, &text[..j + 2]));
+                (start, i) = (j + 2, j + 2);
+                *display = false;
+            }
+            None => return vec![(true, text)],
+        }
+    }
+
+    while i < bytes.len() {
+        if !dollar(i) {
+            i += 1;
+            continue;
+        }
+
+        let close = match dollar(i + 1) {
+            true => text[i + 2..].match_indices("$$").find(|&(j, _)| !escaped(i + 2 + j)).map(|(j, _)| i + 2 + j + 2),
+            false => (i + 1..bytes.len())
+                .filter(|&j| dollar(j))
+                .find(|&j| !bytes[j - 1].is_ascii_whitespace() && !bytes.get(j + 1).is_some_and(u8::is_ascii_digit))
+                .filter(|_| bytes.get(i + 1).is_some_and(|b| !b.is_ascii_whitespace()))
+                .map(|j| j + 1),
+        };
+        match close {
+            Some(end) => {
+                if start < i {
+                    parts.push((false, &text[start..i]));
+                }
+                parts.push((true, &text[i..end]));
+                (start, i) = (end, end);
+            }
+            None if dollar(i + 1) => {
+                if start < i {
+                    parts.push((false, &text[start..i]));
+                }
+                parts.push((true, &text[i..]));
+                *display = true;
+                return parts;
+            }
+            None => i += 1,
+        }
+    }
+    if start < text.len() {
+        parts.push((false, &text[start..]));
+    }
+    parts
+}
+
+
+/// Split text into HTML comments (`<!-- ... -->`) and the text between them.
+///
+/// # Arguments
+/// - `text` - A part of a line outside code spans.
+/// - `open` - Whether a comment is open, updated for the next part.
+///
+/// # Returns
+/// The non-empty parts of the text in order, each flagged whether it is a comment.
+fn comment_spans<'a>(text: &'a str, open: &mut bool) -> Vec<(bool, &'a str)> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let (delimiter, len) = match *open {
+            true => ("-->", 3),
+            false => ("<!--", 0),
+        };
+        let found = rest.find(delimiter);
+        let (part, next) = rest.split_at(found.map_or(rest.len(), |i| i + len));
+        if !part.is_empty() {
+            parts.push((*open, part));
+        }
+        if found.is_some() {
+            *open = !*open;
+        }
+        rest = next;
+    }
+    parts
+}
+
+
+/// Split a line into mdBook directives (`{{#include file.rs:2}}`) and the text between them.
+///
+/// # Arguments
+/// - `text` - The text, without line breaks.
+///
+/// # Returns
+/// The parts of the text in order, each flagged whether it is a directive.
+fn directive_spans(text: &str) -> Vec<(bool, &str)> {
+    let mut parts = Vec::new();
+    let (mut start, mut search) = (0, 0);
+    while let Some(open) = text[search..].find("{{").map(|i| search + i) {
+        let close = text[open..].find("}}").map(|i| open + i + 2);
+        match close {
+            Some(close) if text[open + 2..].trim_start().starts_with('#') => {
+                if open > start {
+                    parts.push((false, &text[start..open]));
+                }
+                parts.push((true, &text[open..close]));
+                (start, search) = (close, close);
+            }
+            _ => search = open + 2,
+        }
+    }
+    if start < text.len() {
+        parts.push((false, &text[start..]));
+    }
+    parts
+}
+
+
+/// Split the content into protected regions and the text between them.
+///
+/// Protected are code blocks, code spans, math, HTML comments and mdBook
+/// directives. Code spans may continue
+/// on the next line of the same paragraph. Line breaks are protected, so the
+/// text parts are always within one line.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+///
+/// # Returns
+/// The parts of the content in order, each flagged whether it is protected.
+pub fn split_protected(content: &str) -> Vec<(bool, &str)> {
+    let lines: Vec<&str> = content.split('\n').collect();
+    let mut fences = FenceTracker::default();
+    let (mut display, mut comment) = (false, false);
+    let mut parts = Vec::new();
+    let (mut i, mut offset) = (0, 0);
+
+    while i < lines.len() {
+        // Fences inside HTML comments are part of the comment
+        let (protected, end) = match !comment && fences.is_code(lines[i]) {
+            true => (true, i + 1),
+            false => {
+                // A paragraph ends at a blank line or the start of a code block
+                let mut end = i + 1;
+                while end < lines.len() && !lines[end - 1].trim().is_empty() && !lines[end].trim().is_empty() {
+                    let mut probe = fences.clone();
+                    if !comment && probe.is_code(lines[end]) {
+                        break;
+                    }
+                    fences = probe;
+                    end += 1;
+                }
+                (false, end)
+            }
+        };
+
+        let len = lines[i..end].iter().map(|line| line.len() + 1).sum::<usize>() - 1;
+        let block = &content[offset..offset + len];
+        if protected {
+            parts.push((true, block));
+        } else {
+            if block.trim().is_empty() {
+                display = false;
+            }
+            for (code, part) in code_spans(block) {
+                if code {
+                    parts.push((true, part));
+                    continue;
+                }
+                for (n, piece) in part.split('\n').enumerate() {
+                    if n > 0 {
+                        parts.push((true, "\n"));
+                    }
+                    for (in_comment, text) in comment_spans(piece, &mut comment) {
+                        if in_comment {
+                            parts.push((true, text));
+                            continue;
+                        }
+                        for (directive, text) in directive_spans(text) {
+                            match directive {
+                                true => parts.push((true, text)),
+                                false => parts.extend(math_spans(text, &mut display)),
+                            }
+                        }
+                    }
+                }
+            }
+        }
+        if end < lines.len() {
+            parts.push((true, "\n"));
+        }
+        offset += len + 1;
+        i = end;
+    }
+    parts
+}
+
+
+/// Apply a conversion to all text outside of fenced code blocks, code spans,
+/// math, HTML comments and mdBook directives.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `convert` - The conversion, called for every piece of text between code.
+///
+/// # Returns
+/// The content with converted text.
+pub fn map_text(content: &str, mut convert: impl FnMut(&str) -> String) -> String {
+    split_protected(content)
+        .into_iter()
+        .map(|(protected, part)| if protected { part.to_string() } else { convert(part) })
+        .collect()
+}
+
+
+/// Find the byte ranges of code blocks, code spans, math, HTML comments and
+/// mdBook directives in the content.
+///
+/// These are the regions [`map_text`] leaves alone, for passes that need
+/// the position of a match within the whole content.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+///
+/// # Returns
+/// The start and end offsets of every protected region, in order.
+pub fn protected_ranges(content: &str) -> Vec<(usize, usize)> {
+    let mut ranges = Vec::new();
+    let mut offset = 0;
+    for (protected, part) in split_protected(content) {
+        if protected && part != "\n" {
+            ranges.push((offset, offset + part.len()));
+        }
+        offset += part.len();
+    }
+    ranges
+}
+
+
+/// The kind of a block of lines, as far as the passes care.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockKind {
+    /// Markdown text, including code blocks.
+    Text,
+    /// A raw HTML block, whose content mdBook doesn't render as Markdown.
+    Html,
+    /// A table, where unescaped `|` would start a new cell.
+    Table,
+}
+
+
+/// HTML elements that start an HTML block even in the middle of a paragraph.
+const BLOCK_ELEMENTS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "details", "dialog", "div", "dl", "dt", "dd",
+    "fieldset", "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "hr", "li", "main", "nav", "ol", "p", "pre", "script", "section", "style",
+    "summary", "table", "tbody", "td", "textarea", "tfoot", "th", "thead", "tr", "ul",
+];
+
+
+/// Returns true if the line starts a raw HTML block.
+///
+/// # Arguments
+/// - `line` - The line outside of code.
+/// - `in_paragraph` - Whether the previous line is paragraph text, which only
+///   block-level elements can interrupt.
+///
+/// # Returns
+/// Whether the line starts an HTML block.
+fn starts_html_block(line: &str, in_paragraph: bool) -> bool {
+    let trimmed = line.trim_start_matches(' ');
+    if line.len() - trimmed.len() > 3 {
+        return false;
+    }
+    let Some(tag) = trimmed.strip_prefix('<') else { return false };
+    let tag = tag.strip_prefix('/').unwrap_or(tag);
+    let name: String = tag.chars().take_while(|c| c.is_ascii_alphanumeric()).collect();
+    let after = &tag[name.len()..];
+    if name.is_empty() || !(after.is_empty() || after.starts_with([' ', '\t', '>', '/'])) {
+        return false;
+    }
+    if BLOCK_ELEMENTS.contains(&name.to_lowercase().as_str()) {
+        return true;
+    }
+    // Any other tag only starts a block if it's alone on its line
+    !in_paragraph && trimmed.trim_end().ends_with('>') && trimmed.matches('<').count() == 1
+}
+
+
+/// Split the content into raw HTML blocks, tables and Markdown text.
+///
+/// HTML blocks start with a tag line outside of code, tables with a header
+/// row followed by a delimiter row. Both end at the next blank line, as in
+/// CommonMark.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+///
+/// # Returns
+/// Consecutive blocks of whole lines covering the content.
+pub fn split_blocks(content: &str) -> Vec<(BlockKind, &str)> {
+    let lines: Vec<&str> = content.split_inclusive('\n').collect();
+    let text = |i: usize| lines.get(i).map(|line| line.trim_end_matches(['\r', '\n']));
+    let mut blocks: Vec<(BlockKind, &str)> = Vec::new();
+    let mut fences = FenceTracker::default();
+    let (mut kind, mut start, mut offset) = (BlockKind::Text, 0, 0);
+    let mut in_paragraph = false;
+
+    for (i, line) in lines.iter().enumerate() {
+        let current = text(i).unwrap_or_default();
+        let next = match kind {
+            _ if kind != BlockKind::Text && current.trim().is_empty() => BlockKind::Text,
+            BlockKind::Html | BlockKind::Table => kind,
+            BlockKind::Text if fences.is_code(current) => BlockKind::Text,
+            BlockKind::Text if starts_html_block(current, in_paragraph) => BlockKind::Html,
+            BlockKind::Text if current.contains('|') && text(i + 1).is_some_and(|next| is_table_delimiter(unquote_all(next).1)) => {
+                BlockKind::Table
+            }
+            BlockKind::Text => BlockKind::Text,
+        };
+        if next != kind {
+            if start < offset {
+                blocks.push((kind, &content[start..offset]));
+            }
+            (kind, start) = (next, offset);
+        }
+        in_paragraph = !current.trim().is_empty();
+        offset += line.len();
+    }
+    if start < content.len() {
+        blocks.push((kind, &content[start..]));
+    }
+    blocks
+}
+
+
+/// Replace regions between two delimiters outside of code.
+///
+/// Regions may span several lines. A region that is never closed is kept.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `open` - The opening delimiter, e.g. `<%`.
+/// - `close` - The closing delimiter, e.g. `%>`.
+/// - `replace` - Called with every region including its delimiters.
+///
+/// # Returns
+/// The content with replaced regions.
+pub fn map_delimited(content: &str, open: &str, close: &str, mut replace: impl FnMut(&str) -> String) -> String {
+    let mut fences = FenceTracker::default();
+    let mut output = String::new();
+    let mut region: Option<String> = None;
+
+    for (i, line) in content.split('\n').enumerate() {
+        if i > 0 {
+            region.as_mut().unwrap_or(&mut output).push('\n');
+        }
+        if region.is_none() && fences.is_code(line) {
+            output.push_str(line);
+            continue;
+        }
+
+        let mut rest = line;
+        loop {
+            match &mut region {
+                Some(text) => match rest.find(close) {
+                    Some(end) => {
+                        text.push_str(&rest[..end + close.len()]);
+                        output.push_str(&replace(text));
+                        region = None;
+                        rest = &rest[end + close.len()..];
+                    }
+                    None => {
+                        text.push_str(rest);
+                        break;
+                    }
+                },
+                None => {
+                    // Only look for the opening delimiter outside of code spans
+                    let mut offset = 0;
+                    let mut opened = None;
+                    for (code, part) in code_spans(rest) {
+                        if let (false, Some(start)) = (code, part.find(open)) {
+                            opened = Some(offset + start);
+                            break;
+                        }
+                        offset += part.len();
+                    }
+                    match opened {
+                        Some(start) => {
+                            output.push_str(&rest[..start]);
+                            region = Some(open.to_string());
+                            rest = &rest[start + open.len()..];
+                        }
+                        None => {
+                            output.push_str(rest);
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(text) = region {
+        output.push_str(&text);
+    }
+    output
+}
+
+
+/// Replace fenced code blocks with the given info string that aren't
+/// themselves inside code, e.g. an example in a longer ` ```` ` fence.
+///
+/// Only blocks opened by exactly three backticks at the start of a line
+/// count. A block that is never closed is kept.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `info` - The info string, e.g. `query`.
+/// - `replace` - Called with the body of every block; `None` keeps the block.
+///
+/// # Returns
+/// The content with replaced blocks.
+pub fn map_code_blocks(content: &str, info: &str, mut replace: impl FnMut(&str) -> Option<String>) -> String {
+    let mut fences = FenceTracker::default();
+    let mut output = String::new();
+    // The start of the open block and of its body
+    let mut block: Option<(usize, usize)> = None;
+    let mut offset = 0;
+
+    for line in content.split_inclusive('\n') {
+        let text = line.trim_end_matches(['\r', '\n']);
+        let outside = !fences.in_code();
+        fences.is_code(text);
+        match block {
+            None if outside && fences.in_code() && text.strip_prefix("```").is_some_and(|rest| rest.trim_end() == info) => {
+                block = Some((offset, offset + line.len()));
+            }
+            None => output.push_str(line),
+            Some((start, body)) if !fences.in_code() => {
+                match replace(&content[body..offset]) {
+                    Some(replaced) => {
+                        output.push_str(&replaced);
+                        output.push_str(&line[text.len()..]);
+                    }
+                    None => output.push_str(&content[start..offset + line.len()]),
+                }
+                block = None;
+            }
+            Some(_) => {}
+        }
+        offset += line.len();
+    }
+
+    if let Some((start, _)) = block {
+        output.push_str(&content[start..]);
+    }
+    output
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_heading() {
+        assert_eq!(heading("# Title"), Some((1, "Title")));
+        assert_eq!(heading("### Install Steps ##"), Some((3, "Install Steps")));
+        assert_eq!(heading("## C#"), Some((2, "C#")));
+        assert_eq!(heading("#tag"), None);
+        assert_eq!(heading("    # indented code"), None);
+        assert_eq!(heading("Text"), None);
+    }
+
+    #[test]
+    fn test_fence_tracker() {
+        let mut fences = FenceTracker::default();
+        let lines = ["Text", "````rust", "# not a heading", "```", "~~~", "````", "After"];
+        let code: Vec<bool> = lines.iter().map(|l| fences.is_code(l)).collect();
+        assert_eq!(code, [false, true, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn test_nested_fences() {
+        let mut fences = FenceTracker::default();
+        let lines = ["- Item", "    ```md", "    [[x]]", "    ```", "> ~~~", "> [[y]]", "> ~~~", "> ```", "After"];
+        let code: Vec<bool> = lines.iter().map(|l| fences.is_code(l)).collect();
+        assert_eq!(code, [false, true, true, true, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn test_multiline_code_spans() {
+        let upper = |text: &str| text.to_uppercase();
+        assert_eq!(map_text("a `b\nc` d\n\ne `f\n\ng`", upper), "A `b\nc` D\n\nE `F\n\nG`");
+    }
+
+    #[test]
+    fn test_comment_spans() {
+        let mut open = false;
+        assert_eq!(comment_spans("a <!-- b --> c <!-- d", &mut open), [(false, "a "), (true, "<!-- b -->"), (false, " c "), (true, "<!-- d")]);
+        assert!(open);
+        assert_eq!(comment_spans("e --> f <!-- g -->", &mut open), [(true, "e -->"), (false, " f "), (true, "<!-- g -->")]);
+        assert!(!open);
+        assert_eq!(comment_spans("-->", &mut open), [(false, "-->")]);
+        let upper = |text: &str| text.to_uppercase();
+        assert_eq!(map_text("a <!--\n```\n[[b]]\n\n--> c\n```\nd\n```", upper), "A <!--\n```\n[[b]]\n\n--> C\n```\nd\n```");
+    }
+
+    #[test]
+    fn test_split_blocks() {
+        let content = "Text <b>x</b>\n<details>\n[[a]]\n\nMore\n<span>\n\n<br>\n\n```\n<div>\n```\n";
+        assert_eq!(
+            split_blocks(content),
+            [
+                (BlockKind::Text, "Text <b>x</b>\n"),
+                (BlockKind::Html, "<details>\n[[a]]\n"),
+                (BlockKind::Text, "\nMore\n<span>\n\n"),
+                (BlockKind::Html, "<br>\n"),
+                (BlockKind::Text, "\n```\n<div>\n```\n"),
+            ]
+        );
+        assert_eq!(
+            split_blocks("Text\n| a | b |\n|---|:-:|\n| [[x]] | y |\n\nAfter"),
+            [(BlockKind::Text, "Text\n"), (BlockKind::Table, "| a | b |\n|---|:-:|\n| [[x]] | y |\n"), (BlockKind::Text, "\nAfter")]
+        );
+    }
+
+    #[test]
+    fn test_protected_ranges() {
+        assert_eq!(protected_ranges("a `b` $c$\n```\nd\n```\ne"), [(2, 5), (6, 9), (10, 13), (14, 15), (16, 19)]);
+    }
+
+    #[test]
+    fn test_admonish_fences() {
+        let mut fences = FenceTracker::default();
+        let lines = ["````admonish note", "# Markdown", "```sh", "# code", "```", "````", "After"];
+        let code: Vec<bool> = lines.iter().map(|l| fences.is_code(l)).collect();
+        assert_eq!(code, [true, false, true, true, true, true, false]);
+    }
+
+    #[test]
+    fn test_code_spans() {
+        assert_eq!(code_spans("a `b` c"), [(false, "a "), (true, "`b`"), (false, " c")]);
+        assert_eq!(code_spans("``x ` y`` `z"), [(true, "``x ` y``"), (false, " `z")]);
+        assert_eq!(code_spans("plain"), [(false, "plain")]);
+        assert_eq!(code_spans("\\`[[a]]` b"), [(false, "\\`[[a]]` b")]);
+    }
+
+    #[test]
+    fn test_math_spans() {
+        let mut display = false;
+        assert_eq!(math_spans("a $x$ b", &mut display), [(false, "a "), (true, "$x$"), (false, " b")]);
+        assert_eq!(math_spans("$5 and $10", &mut display), [(false, "$5 and $10")]);
+        assert_eq!(math_spans("\\$x$ y", &mut display), [(false, "\\$x$ y")]);
+        assert_eq!(math_spans("$$[[a,b]]$$ c $$", &mut display), [(true, "$$[[a,b]]$$"), (false, " c "), (true, "$$")]);
+        assert!(display);
+        assert_eq!(math_spans("x [[y]] $$ z", &mut display), [(true, "x [[y]] $$"), (false, " z")]);
+        assert!(!display);
+    }
+
+    #[test]
+    fn test_map_text() {
+        let upper = |text: &str| text.to_uppercase();
+        assert_eq!(map_text("a `b` $c$\n$$\nd\n$$\n```\ne\n```", upper), "A `b` $c$\n$$\nd\n$$\n```\ne\n```");
+    }
+
+    #[test]
+    fn test_directive_spans() {
+        assert_eq!(
+            directive_spans("a {{#include [[x]].md:2}} b {{ # title T }}"),
+            [(false, "a "), (true, "{{#include [[x]].md:2}}"), (false, " b "), (true, "{{ # title T }}")]
+        );
+        assert_eq!(directive_spans("{{hex}} and {{#open"), [(false, "{{hex}} and {{#open")]);
+    }
+
+    #[test]
+    fn test_map_delimited() {
+        let upper = |region: &str| region.to_uppercase();
+        assert_eq!(map_delimited("a <%x\ny%> b `<%c%>`", "<%", "%>", upper), "a <%X\nY%> b `<%c%>`");
+        assert_eq!(map_delimited("```\n<%x%>\n```\n<%open", "<%", "%>", upper), "```\n<%x%>\n```\n<%open");
+    }
+
+    #[test]
+    fn test_map_code_blocks() {
+        let upper = |body: &str| Some(body.to_uppercase());
+        assert_eq!(map_code_blocks("a\n```query\nx\n```\nb", "query", upper), "a\nX\n\nb");
+        let nested = "````md\n```query\nx\n```\n````\n```query\ny\n```";
+        assert_eq!(map_code_blocks(nested, "query", upper), "````md\n```query\nx\n```\n````\nY\n");
+        assert_eq!(map_code_blocks("```query\nx\n```\n```query\nopen", "query", |_| None), "```query\nx\n```\n```query\nopen");
+        assert_eq!(map_code_blocks("```queryx\nx\n```", "query", upper), "```queryx\nx\n```");
+    }
+}
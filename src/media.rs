@@ -0,0 +1,401 @@
+//! Rendering of embedded attachments such as `![[image.png]]`.
+
+use crate::config::MediaConfig;
+use crate::paths;
+use crate::vault::Vault;
+use crate::warn;
+
+
+/// File extensions rendered as images.
+///
+/// How an embedded file is rendered depends on its type, so these lists are
+/// fixed; `links.asset-extensions` only decides which links keep their
+/// extension. Other files aren't rendered inline.
+const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "bmp", "svg", "webp", "avif"];
+
+/// File extensions rendered as audio players.
+const AUDIO_EXTENSIONS: &[&str] = &["mp3", "m4a", "ogg", "wav", "flac", "3gp"];
+
+/// File extensions rendered as video players.
+const VIDEO_EXTENSIONS: &[&str] = &["mp4", "webm", "ogv", "mov", "mkv"];
+
+
+/// Get the file name of an embed target without directories and extension.
+///
+/// # Arguments
+/// - `target` - The embed target, e.g. `img/logo.png`.
+///
+/// # Returns
+/// The file stem, e.g. `logo`.
+fn stem(target: &str) -> &str {
+    let name = target.rsplit('/').next().unwrap_or(target);
+    name.rsplit_once('.').map_or(name, |(stem, _)| stem)
+}
+
+
+/// Compute the URL of an attachment as seen from the current chapter.
+///
+/// Attachments missing from the vault are linked as written.
+///
+/// # Arguments
+/// - `target` - The embed target.
+/// - `source` - The path of the current chapter relative to the src directory.
+/// - `vault` - The vault to look the attachment up in.
+///
+/// # Returns
+/// The URL of the attachment with spaces and special characters encoded.
+fn asset_url(target: &str, source: Option<&str>, vault: &Vault) -> String {
+    let url = match vault.asset(target) {
+        Some(path) => paths::relative(source.unwrap_or(""), path),
+        None => {
+            warn(&format!("Embedded file '{}' not found", target));
+            target.to_string()
+        }
+    };
+    paths::percent_encode(&url)
+}
+
+
+/// Escape a value for use in a double-quoted HTML attribute.
+///
+/// # Arguments
+/// - `value` - The raw attribute value.
+///
+/// # Returns
+/// The escaped value.
+fn escape_attr(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('"', "&quot;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+
+/// Parse an Obsidian size hint such as `300` or `300x200`.
+///
+/// # Arguments
+/// - `hint` - The text after the pipe.
+///
+/// # Returns
+/// The width and the optional height, or `None` if the text is no size hint.
+fn parse_size(hint: &str) -> Option<(u32, Option<u32>)> {
+    match hint.trim().split_once('x') {
+        Some((width, height)) => Some((width.parse().ok()?, Some(height.parse().ok()?))),
+        None => Some((hint.trim().parse().ok()?, None)),
+    }
+}
+
+
+/// Split the text after the pipe of an image embed into alt text and size.
+///
+/// Obsidian accepts `|300`, `|300x200` and `|alt text|300`.
+///
+/// # Arguments
+/// - `alias` - The text after the first pipe.
+///
+/// # Returns
+/// The alt text (if any) and the size hint (if any).
+fn split_alias(alias: &str) -> (Option<&str>, Option<(u32, Option<u32>)>) {
+    let (alt, last) = match alias.rsplit_once('|') {
+        Some((alt, last)) => (Some(alt.trim()), last),
+        None => (None, alias),
+    };
+    match parse_size(last) {
+        Some(size) => (alt.filter(|a| !a.is_empty()), Some(size)),
+        None => (Some(alias.trim()).filter(|a| !a.is_empty()), None),
+    }
+}
+
+
+/// Render an image embed.
+///
+/// # Arguments
+/// - `target` - The embed target, e.g. `logo.png`.
+/// - `alias` - The text after the pipe, if any.
+/// - `url` - The URL of the image.
+///
+/// # Returns
+/// A Markdown image, or an `<img>` tag if the alias carries a size hint.
+fn render_image(target: &str, alias: Option<&str>, url: &str) -> String {
+    let (alt, size) = alias.map_or((None, None), split_alias);
+    let alt = alt.unwrap_or_else(|| stem(target));
+    match size {
+        Some((width, height)) => format!(
+            "<img src=\"{}\" alt=\"{}\" width=\"{}\"{}>",
+            escape_attr(url),
+            escape_attr(alt),
+            width,
+            height.map(|h| format!(" height=\"{}\"", h)).unwrap_or_default()
+        ),
+        None => format!("![{}]({})", alt, url),
+    }
+}
+
+
+/// Render an Excalidraw drawing from its exported SVG or PNG.
+///
+/// The Excalidraw plugin exports `Drawing.excalidraw` as `Drawing.excalidraw.svg`
+/// or, without the extension, as `Drawing.svg`; SVG is preferred over PNG.
+/// Drawings without an export fall back to a link to the drawing.
+///
+/// # Arguments
+/// - `target` - The embed target, e.g. `Drawing.excalidraw`.
+/// - `alias` - The text after the pipe, if any.
+/// - `source` - The path of the current chapter relative to the src directory.
+/// - `vault` - The vault to look the export up in.
+///
+/// # Returns
+/// The rendered embed.
+fn render_excalidraw(target: &str, alias: Option<&str>, source: Option<&str>, vault: &Vault) -> String {
+    let drawing = target.trim_end_matches(".md");
+    let base = drawing.trim_end_matches(".excalidraw");
+    let export = [drawing, base]
+        .iter()
+        .flat_map(|name| ["svg", "png"].map(|ext| format!("{}.{}", name, ext)))
+        .find(|candidate| vault.asset(candidate).is_some());
+
+    match export {
+        Some(export) => {
+            let alias = alias.or_else(|| Some(stem(base)));
+            render_image(&export, alias, &asset_url(&export, source, vault))
+        }
+        None => {
+            warn(&format!("No exported SVG or PNG found for drawing '{}'", target));
+            format!("[[{}]]", target)
+        }
+    }
+}
+
+
+/// Render an embed of a remote URL.
+///
+/// URLs of images become images, all other URLs become links.
+///
+/// # Arguments
+/// - `url` - The URL including any fragment.
+/// - `alias` - The text after the pipe, if any.
+///
+/// # Returns
+/// The rendered embed.
+pub fn render_remote(url: &str, alias: Option<&str>) -> String {
+    let path = url.split(['?', '#']).next().unwrap_or(url);
+    let is_image = paths::extension(path).is_some_and(|ext| IMAGE_EXTENSIONS.contains(&ext.as_str()));
+    let url = url.replace(' ', "%20");
+
+    if is_image {
+        render_image(path, alias, &url)
+    } else {
+        format!("[{}]({})", alias.unwrap_or(&url), url)
+    }
+}
+
+
+/// Render an embed of a non-note file.
+///
+/// Images become Markdown images, with the alias (if any) as alt text and
+/// the file name as fallback. Images with a size hint become `<img>` tags
+/// carrying `width` and `height`. PDFs become an `<object>` viewer that keeps
+/// the fragment (e.g. `page=5`) and falls back to a download link. Audio and
+/// video files become `<audio controls>` and `<video controls>` players.
+/// Excalidraw drawings are shown through their exported image, canvases are
+/// linked.
+///
+/// # Arguments
+/// - `target` - The embed target, e.g. `logo.png`.
+/// - `fragment` - The text after the `#`, if any.
+/// - `alias` - The text after the pipe, if any.
+/// - `source` - The path of the current chapter relative to the src directory.
+/// - `vault` - The vault to look the attachment up in.
+/// - `config` - The media settings.
+///
+/// # Returns
+/// The rendered embed, or `None` if the file type isn't supported.
+pub fn render_embed(
+    target: &str,
+    fragment: Option<&str>,
+    alias: Option<&str>,
+    source: Option<&str>,
+    vault: &Vault,
+    config: &MediaConfig,
+) -> Option<String> {
+    let ext = paths::extension(target)?;
+    if IMAGE_EXTENSIONS.contains(&ext.as_str()) {
+        return Some(render_image(target, alias, &asset_url(target, source, vault)));
+    }
+
+    if ext == "excalidraw" || target.ends_with(".excalidraw.md") {
+        return Some(render_excalidraw(target, alias, source, vault));
+    }
+
+    // Canvases are rendered as chapters of their own
+    if ext == "canvas" {
+        return Some(format!("[[{}]]", target));
+    }
+
+    if ext == "pdf" {
+        let url = escape_attr(&asset_url(target, source, vault));
+        let fragment = fragment.map(|f| format!("#{}", escape_attr(f.trim()))).unwrap_or_default();
+        let title = escape_attr(alias.unwrap_or(target));
+        return Some(format!(
+            "<object data=\"{}{}\" type=\"application/pdf\" width=\"100%\" height=\"600\">\
+             <a href=\"{}{}\">{}</a></object>",
+            url, fragment, url, fragment, title
+        ));
+    }
+
+    if AUDIO_EXTENSIONS.contains(&ext.as_str()) {
+        let url = escape_attr(&asset_url(target, source, vault));
+        let title = escape_attr(alias.unwrap_or(target));
+        return Some(format!("<audio controls src=\"{}\"><a href=\"{}\">{}</a></audio>", url, url, title));
+    }
+
+    if VIDEO_EXTENSIONS.contains(&ext.as_str()) {
+        let url = escape_attr(&asset_url(target, source, vault));
+        let title = escape_attr(alias.unwrap_or(target));
+        let mut attributes = String::new();
+        if let Some(width) = &config.video_width {
+            attributes.push_str(&format!(" width=\"{}\"", escape_attr(&width.to_string())));
+        }
+        if let Some(preload) = &config.video_preload {
+            attributes.push_str(&format!(" preload=\"{}\"", escape_attr(preload)));
+        }
+        if config.video_muted {
+            attributes.push_str(" muted");
+        }
+        return Some(format!(
+            "<video controls{} src=\"{}\"><a href=\"{}\">{}</a></video>",
+            attributes, url, url, title
+        ));
+    }
+
+    None
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Dimension;
+
+    fn render(embed: &str, source: Option<&str>, vault: &Vault) -> Option<String> {
+        let (target, alias) = embed.split_once('|').map_or((embed, None), |(t, a)| (t, Some(a)));
+        let (target, fragment) = target.split_once('#').map_or((target, None), |(t, f)| (t, Some(f)));
+        render_embed(target, fragment, alias, source, vault, &MediaConfig::default())
+    }
+
+    #[test]
+    fn test_image_embed() {
+        let vault = Vault::default().with_assets(&["attachments/My Logo.png"]);
+        assert_eq!(
+            render("My Logo.png", Some("guide/intro.md"), &vault),
+            Some("![My Logo](../attachments/My%20Logo.png)".to_string())
+        );
+        assert_eq!(
+            render("My Logo.png|The logo", None, &vault),
+            Some("![The logo](attachments/My%20Logo.png)".to_string())
+        );
+        assert_eq!(
+            render("img/missing.JPG", None, &vault),
+            Some("![missing](img/missing.JPG)".to_string())
+        );
+        assert_eq!(render("archive.zip", None, &vault), None);
+    }
+
+    #[test]
+    fn test_image_size() {
+        let vault = Vault::default();
+        assert_eq!(
+            render("logo.png|300", None, &vault),
+            Some("<img src=\"logo.png\" alt=\"logo\" width=\"300\">".to_string())
+        );
+        assert_eq!(
+            render("logo.png|300x200", None, &vault),
+            Some("<img src=\"logo.png\" alt=\"logo\" width=\"300\" height=\"200\">".to_string())
+        );
+        assert_eq!(
+            render("logo.png|The \"logo\"|120", None, &vault),
+            Some("<img src=\"logo.png\" alt=\"The &quot;logo&quot;\" width=\"120\">".to_string())
+        );
+        assert_eq!(
+            render("logo.png|300 px wide", None, &vault),
+            Some("![300 px wide](logo.png)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_remote_embed() {
+        assert_eq!(
+            render_remote("https://example.com/img/chart.png?v=2", None),
+            "![chart](https://example.com/img/chart.png?v=2)"
+        );
+        assert_eq!(
+            render_remote("https://example.com/chart.svg", Some("200")),
+            "<img src=\"https://example.com/chart.svg\" alt=\"chart\" width=\"200\">"
+        );
+        assert_eq!(
+            render_remote("https://example.com/page#intro", Some("Example")),
+            "[Example](https://example.com/page#intro)"
+        );
+    }
+
+    #[test]
+    fn test_excalidraw_embed() {
+        let vault = Vault::default().with_assets(&["Drawing.excalidraw.svg", "img/Sketch.png"]);
+        assert_eq!(
+            render("Drawing.excalidraw", None, &vault),
+            Some("![Drawing](Drawing.excalidraw.svg)".to_string())
+        );
+        assert_eq!(
+            render("Sketch.excalidraw.md|400", None, &vault),
+            Some("<img src=\"img/Sketch.png\" alt=\"Sketch\" width=\"400\">".to_string())
+        );
+        assert_eq!(render("Missing.excalidraw", None, &vault), Some("[[Missing.excalidraw]]".to_string()));
+    }
+
+    #[test]
+    fn test_audio_embed() {
+        let vault = Vault::default().with_assets(&["audio/recording.mp3"]);
+        assert_eq!(
+            render("recording.mp3", Some("intro.md"), &vault),
+            Some(
+                "<audio controls src=\"audio/recording.mp3\">\
+                 <a href=\"audio/recording.mp3\">recording.mp3</a></audio>"
+                    .to_string()
+            )
+        );
+        let flac = render("Take 2.FLAC", None, &vault).unwrap();
+        assert!(flac.starts_with("<audio controls src=\"Take%202.FLAC\">"));
+    }
+
+    #[test]
+    fn test_video_embed() {
+        let vault = Vault::default();
+        assert_eq!(
+            render("clip.mp4", None, &vault),
+            Some("<video controls src=\"clip.mp4\"><a href=\"clip.mp4\">clip.mp4</a></video>".to_string())
+        );
+
+        let config = MediaConfig {
+            video_width: Some(Dimension::Css("100%".to_string())),
+            video_preload: Some("metadata".to_string()),
+            video_muted: true,
+        };
+        assert!(render_embed("clip.webm", None, None, None, &vault, &config)
+            .unwrap()
+            .starts_with("<video controls width=\"100%\" preload=\"metadata\" muted src=\"clip.webm\">"));
+    }
+
+    #[test]
+    fn test_pdf_embed() {
+        let vault = Vault::default().with_assets(&["docs/manual.pdf"]);
+        assert_eq!(
+            render("manual.pdf#page=5", Some("intro.md"), &vault),
+            Some(
+                "<object data=\"docs/manual.pdf#page=5\" type=\"application/pdf\" width=\"100%\" height=\"600\">\
+                 <a href=\"docs/manual.pdf#page=5\">manual.pdf</a></object>"
+                    .to_string()
+            )
+        );
+    }
+}
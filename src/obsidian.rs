@@ -0,0 +1,183 @@
+//! Settings of the Obsidian vault the book is written in (`.obsidian/app.json`
+//! and the settings of core plugins).
+
+use crate::config::LinkFormat;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+
+
+/// The parts of `.obsidian/app.json` the preprocessor cares about.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ObsidianSettings {
+    /// "Default location for new attachments": `/` for the vault root, `./`
+    /// or `./folder` relative to the note, or a folder path in the vault.
+    pub attachment_folder_path: Option<String>,
+
+    /// "New link format": `shortest`, `relative` or `absolute`.
+    pub new_link_format: Option<String>,
+
+    /// Whether new links are Markdown links instead of wikilinks.
+    pub use_markdown_links: Option<bool>,
+
+    /// "Excluded files": paths and `/regex/` filters of files to ignore.
+    pub user_ignore_filters: Vec<String>,
+
+    /// The settings of the "Daily notes" plugin (`.obsidian/daily-notes.json`).
+    #[serde(skip)]
+    pub daily_notes: NoteFolderSettings,
+
+    /// The settings of the "Periodic Notes" community plugin.
+    #[serde(skip)]
+    pub periodic_notes: PeriodicNotesSettings,
+}
+
+
+/// Where a plugin creates notes named after a date.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct NoteFolderSettings {
+    /// The folder relative to the vault root.
+    pub folder: Option<String>,
+
+    /// The Moment.js format of the note names, e.g. `YYYY-MM-DD`.
+    pub format: Option<String>,
+
+    /// Whether the plugin creates these notes at all.
+    pub enabled: Option<bool>,
+}
+
+impl NoteFolderSettings {
+    /// Get the settings if the notes are enabled, or else empty ones.
+    pub fn used(&self) -> NoteFolderSettings {
+        match self.enabled {
+            Some(false) => NoteFolderSettings::default(),
+            _ => self.clone(),
+        }
+    }
+}
+
+
+/// The settings of the "Periodic Notes" plugin
+/// (`.obsidian/plugins/periodic-notes/data.json`).
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct PeriodicNotesSettings {
+    pub daily: NoteFolderSettings,
+    pub weekly: NoteFolderSettings,
+    pub monthly: NoteFolderSettings,
+    pub quarterly: NoteFolderSettings,
+    pub yearly: NoteFolderSettings,
+}
+
+
+/// Read a JSON settings file of the vault.
+///
+/// # Arguments
+/// - `path` - The path of the file.
+///
+/// # Returns
+/// The settings (default ones if the file doesn't exist), or an error if
+/// it can't be parsed.
+fn read_settings<T: Default + for<'de> Deserialize<'de>>(path: &Path) -> Result<T> {
+    if !path.is_file() {
+        return Ok(T::default());
+    }
+    let json = fs::read_to_string(path)?;
+    serde_json::from_str(&json).with_context(|| format!("Failed to parse '{}'", path.display()))
+}
+
+impl ObsidianSettings {
+    /// Read the settings of the vault at `vault_dir`.
+    ///
+    /// A vault without `.obsidian/app.json` or plugin settings has default settings.
+    ///
+    /// # Arguments
+    /// - `vault_dir` - The vault root containing the `.obsidian` folder.
+    ///
+    /// # Returns
+    /// The settings, or an error if a settings file can't be parsed.
+    pub fn load(vault_dir: &Path) -> Result<ObsidianSettings> {
+        let dir = vault_dir.join(".obsidian");
+        let mut settings: ObsidianSettings = read_settings(&dir.join("app.json"))?;
+        settings.daily_notes = read_settings(&dir.join("daily-notes.json"))?;
+        settings.periodic_notes = read_settings(&dir.join("plugins").join("periodic-notes").join("data.json"))?;
+        Ok(settings)
+    }
+
+    /// Get the vault folder all attachments are stored in.
+    ///
+    /// Attachment folders relative to each note (`./...`) are no single folder,
+    /// they end up next to the notes and are found by the vault scan.
+    ///
+    /// # Returns
+    /// The folder relative to the vault root (empty for the root itself), if any.
+    pub fn attachment_folder(&self) -> Option<&str> {
+        let folder = self.attachment_folder_path.as_deref()?.trim();
+        if folder.starts_with("./") || folder == "." {
+            return None;
+        }
+        Some(folder.trim_matches('/'))
+    }
+
+    /// Get the format the vault writes paths in links in.
+    ///
+    /// # Returns
+    /// The link format, if the vault sets a known one.
+    pub fn link_format(&self) -> Option<LinkFormat> {
+        match self.new_link_format.as_deref()? {
+            "shortest" => Some(LinkFormat::Shortest),
+            "relative" => Some(LinkFormat::Relative),
+            "absolute" => Some(LinkFormat::Absolute),
+            _ => None,
+        }
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_attachment_folder() {
+        let parse = |json: &str| serde_json::from_str::<ObsidianSettings>(json).unwrap();
+        let settings = parse(r#"{"attachmentFolderPath": "Files/Attachments"}"#);
+        assert_eq!(settings.attachment_folder(), Some("Files/Attachments"));
+        assert_eq!(parse(r#"{"attachmentFolderPath": "/"}"#).attachment_folder(), Some(""));
+        assert_eq!(parse(r#"{"attachmentFolderPath": "./assets"}"#).attachment_folder(), None);
+        assert_eq!(parse(r#"{"promptDelete": false}"#).attachment_folder(), None);
+    }
+
+    #[test]
+    fn test_daily_note_settings() {
+        let dir = std::env::temp_dir().join(format!("obsidian-links-settings-{}", std::process::id()));
+        fs::create_dir_all(dir.join(".obsidian")).unwrap();
+        fs::write(dir.join(".obsidian/daily-notes.json"), r#"{"folder": "Journal", "format": "YYYY/YYYY-MM-DD"}"#).unwrap();
+        fs::create_dir_all(dir.join(".obsidian/plugins/periodic-notes")).unwrap();
+        fs::write(
+            dir.join(".obsidian/plugins/periodic-notes/data.json"),
+            r#"{"weekly": {"enabled": true, "folder": "Weekly", "format": "gggg-[W]ww"}, "monthly": {"enabled": false, "folder": "Monthly"}}"#,
+        )
+        .unwrap();
+
+        let settings = ObsidianSettings::load(&dir).unwrap();
+        assert_eq!(settings.daily_notes.folder.as_deref(), Some("Journal"));
+        assert_eq!(settings.daily_notes.format.as_deref(), Some("YYYY/YYYY-MM-DD"));
+        assert_eq!(settings.periodic_notes.weekly.used().folder.as_deref(), Some("Weekly"));
+        assert_eq!(settings.periodic_notes.monthly.used().folder, None);
+        assert_eq!(settings.attachment_folder(), None);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_link_settings() {
+        let parse = |json: &str| serde_json::from_str::<ObsidianSettings>(json).unwrap();
+        let settings = parse(r#"{"newLinkFormat": "relative", "useMarkdownLinks": true}"#);
+        assert_eq!(settings.link_format(), Some(LinkFormat::Relative));
+        assert_eq!(settings.use_markdown_links, Some(true));
+        assert_eq!(parse("{}").link_format(), None);
+    }
+}
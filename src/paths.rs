@@ -0,0 +1,274 @@
+//! Helpers for the forward-slash paths used inside the book.
+
+
+/// Compute the path of `to` relative to the directory containing `from`.
+///
+/// Both paths are relative to the book's src directory.
+///
+/// # Examples
+/// From `guide/intro.md` to `assets/logo.png` is `../assets/logo.png`
+///
+/// # Arguments
+/// - `from` - The path of the file the link is written in.
+/// - `to` - The path of the link target.
+///
+/// # Returns
+/// The relative path.
+pub fn relative(from: &str, to: &str) -> String {
+    let from_dirs: Vec<&str> = from.split('/').filter(|c| !c.is_empty()).collect();
+    let from_dirs = &from_dirs[..from_dirs.len().saturating_sub(1)];
+    let to_parts: Vec<&str> = to.split('/').filter(|c| !c.is_empty()).collect();
+
+    let common = from_dirs
+        .iter()
+        .zip(&to_parts)
+        .take_while(|(a, b)| a == b)
+        .count()
+        .min(to_parts.len().saturating_sub(1));
+
+    let mut parts: Vec<&str> = vec![".."; from_dirs.len() - common];
+    parts.extend(&to_parts[common..]);
+    parts.join("/")
+}
+
+
+/// Normalize a path by collapsing `.`, `..` and repeated or trailing slashes.
+///
+/// Windows separators (`\`) are turned into forward slashes.
+///
+/// # Examples
+/// `guide/./sub//../intro/` becomes `guide/intro`
+///
+/// # Arguments
+/// - `path` - The path to normalize.
+///
+/// # Returns
+/// The normalized path; `..` that leave the root are kept.
+pub fn normalize(path: &str) -> String {
+    let mut parts: Vec<&str> = Vec::new();
+    for part in path.split(['/', '\\']) {
+        match part {
+            "" | "." => {}
+            ".." if parts.last().is_some_and(|last| *last != "..") => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+
+/// Resolve a path written relative to the file `from`.
+///
+/// # Examples
+/// `../reference/api` written in `guide/intro.md` is `reference/api`
+///
+/// # Arguments
+/// - `from` - The path of the file the path is written in.
+/// - `path` - The relative path.
+///
+/// # Returns
+/// The normalized path relative to the src directory.
+pub fn join(from: &str, path: &str) -> String {
+    match from.rsplit_once('/') {
+        Some((dir, _)) => normalize(&format!("{}/{}", dir, path)),
+        None => normalize(path),
+    }
+}
+
+
+/// Get the lowercased file extension of a link or embed target.
+///
+/// Dots followed by spaces don't start an extension, so `v1.2 notes` and
+/// `Dr. Who` have none.
+///
+/// # Arguments
+/// - `target` - The target, e.g. `img/Logo.PNG`.
+///
+/// # Returns
+/// The extension without the dot, if any.
+pub fn extension(target: &str) -> Option<String> {
+    let name = target.trim().rsplit('/').next().unwrap_or(target);
+    match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() && !ext.is_empty() && !ext.contains(char::is_whitespace) => {
+            Some(ext.to_lowercase())
+        }
+        _ => None,
+    }
+}
+
+
+/// Remove a Markdown extension (`.md` or `.markdown`) from a note name.
+///
+/// # Arguments
+/// - `name` - The note name, e.g. `guide/Setup.md`.
+///
+/// # Returns
+/// The name without the extension, or the name itself if it has none.
+pub fn strip_note_extension(name: &str) -> &str {
+    match extension(name).as_deref() {
+        Some(ext @ ("md" | "markdown")) => &name.trim_end()[..name.trim_end().len() - ext.len() - 1],
+        _ => name,
+    }
+}
+
+
+/// Returns true if a file name has a Markdown extension (`.md` or `.markdown`).
+///
+/// # Arguments
+/// - `name` - The file name or path, e.g. `notes/Readme.markdown`.
+///
+/// # Returns
+/// True for note files.
+pub fn is_note_file(name: &str) -> bool {
+    strip_note_extension(name).len() < name.trim_end().len()
+}
+
+
+/// Returns true if the link target is an absolute URL such as `https://...`.
+///
+/// # Arguments
+/// - `target` - The link or embed target.
+///
+/// # Returns
+/// Whether the target has a URL scheme.
+pub fn is_url(target: &str) -> bool {
+    let target = target.trim();
+    if target.starts_with("mailto:") {
+        return true;
+    }
+    match target.split_once("://") {
+        Some((scheme, _)) => {
+            !scheme.is_empty()
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'))
+        }
+        None => false,
+    }
+}
+
+
+/// Encode a path for use as a link target.
+///
+/// Spaces and the ASCII characters that are unsafe or reserved in URLs and
+/// Markdown link destinations are written as `%XX` escapes. Slashes and
+/// non-ASCII letters are kept, so targets stay readable.
+///
+/// # Examples
+/// `My Great Note (draft).md` becomes `My%20Great%20Note%20%28draft%29.md`
+///
+/// # Arguments
+/// - `path` - The path, without fragment.
+///
+/// # Returns
+/// The encoded path.
+pub fn percent_encode(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for c in path.chars() {
+        match c {
+            ' ' | '"' | '#' | '%' | '<' | '>' | '?' | '[' | ']' | '\\' | '^' | '`' | '{' | '|' | '}' | '(' | ')' => {
+                encoded.push_str(&format!("%{:02X}", c as u8));
+            }
+            c if c.is_ascii_control() => encoded.push_str(&format!("%{:02X}", c as u8)),
+            c => encoded.push(c),
+        }
+    }
+    encoded
+}
+
+
+/// Decode `%XX` escapes in a URL component.
+///
+/// Invalid escapes are kept as written.
+///
+/// # Arguments
+/// - `text` - The encoded text, e.g. `My%20Note`.
+///
+/// # Returns
+/// The decoded text.
+pub fn percent_decode(text: &str) -> String {
+    let bytes = text.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = text.get(i + 1..i + 3).and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative() {
+        assert_eq!(relative("intro.md", "logo.png"), "logo.png");
+        assert_eq!(relative("guide/intro.md", "assets/logo.png"), "../assets/logo.png");
+        assert_eq!(relative("guide/intro.md", "guide/setup.md"), "setup.md");
+        assert_eq!(relative("a/b/c.md", "a/d.md"), "../d.md");
+        assert_eq!(relative("intro.md", "guide/img/x.png"), "guide/img/x.png");
+    }
+
+    #[test]
+    fn test_normalize() {
+        assert_eq!(normalize("guide/./sub//../intro/"), "guide/intro");
+        assert_eq!(normalize("./note"), "note");
+        assert_eq!(normalize("../../a/b"), "../../a/b");
+        assert_eq!(normalize("notes\\sub\\file"), "notes/sub/file");
+        assert_eq!(join("guide/intro.md", "../reference/api"), "reference/api");
+        assert_eq!(join("guide/intro.md", "./setup"), "guide/setup");
+        assert_eq!(join("intro.md", "./setup"), "setup");
+    }
+
+    #[test]
+    fn test_extension() {
+        assert_eq!(extension("img/Logo.PNG"), Some("png".to_string()));
+        assert_eq!(extension("v1.2 notes"), None);
+        assert_eq!(extension("folder.d/note"), None);
+        assert_eq!(extension(".hidden"), None);
+    }
+
+    #[test]
+    fn test_strip_note_extension() {
+        assert_eq!(strip_note_extension("guide/Setup.md"), "guide/Setup");
+        assert_eq!(strip_note_extension("Notes.MARKDOWN"), "Notes");
+        assert_eq!(strip_note_extension("v1.2 notes"), "v1.2 notes");
+        assert_eq!(strip_note_extension(".md"), ".md");
+        assert!(is_note_file("notes/Readme.markdown") && is_note_file("Setup.md"));
+        assert!(!is_note_file("logo.png") && !is_note_file(".md"));
+    }
+
+    #[test]
+    fn test_is_url() {
+        assert!(is_url("https://example.com/a.png"));
+        assert!(is_url("mailto:me@example.com"));
+        assert!(!is_url("notes/https"));
+        assert!(!is_url("My Note"));
+    }
+
+    #[test]
+    fn test_percent_encode() {
+        assert_eq!(percent_encode("My Great Note (draft).md"), "My%20Great%20Note%20%28draft%29.md");
+        assert_eq!(percent_encode("guide/100% <sure>?"), "guide/100%25%20%3Csure%3E%3F");
+        assert_eq!(percent_encode("Übersicht/C#"), "Übersicht/C%23");
+        assert_eq!(percent_decode(&percent_encode("a [b] {c}|d")), "a [b] {c}|d");
+    }
+
+    #[test]
+    fn test_percent_decode() {
+        assert_eq!(percent_decode("My%20Note%2Fa%C3%A4"), "My Note/aä");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+    }
+}
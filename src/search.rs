@@ -0,0 +1,53 @@
+//! Handling of Obsidian's embedded search (` ```query `) blocks.
+
+use crate::config::{SearchConfig, SearchMode};
+use crate::markdown;
+
+
+/// Remove ` ```query ` blocks or replace them with a note about the search.
+///
+/// The placeholder is an `info` callout, which the callout pass converts
+/// like any other.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `config` - The search block settings.
+///
+/// # Returns
+/// The content with converted search blocks.
+pub fn convert_search_blocks(content: &str, config: &SearchConfig) -> String {
+    if config.mode == SearchMode::Keep {
+        return content.to_string();
+    }
+
+    markdown::map_code_blocks(content, "query", |query| match config.mode {
+        SearchMode::Placeholder => {
+            let query = query.split_whitespace().collect::<Vec<_>>().join(" ");
+            Some(format!("> [!info] {}\n> Obsidian search: `{}`", config.title, query))
+        }
+        _ => Some(String::new()),
+    })
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_search_blocks() {
+        let input = "Before\n\n```query\ntag:#recipe\n  path:food\n```\n\nAfter";
+        let placeholder = SearchConfig { mode: SearchMode::Placeholder, ..Default::default() };
+        assert_eq!(
+            convert_search_blocks(input, &placeholder),
+            "Before\n\n> [!info] Search results\n> Obsidian search: `tag:#recipe path:food`\n\nAfter"
+        );
+
+        let strip = SearchConfig { mode: SearchMode::Strip, ..Default::default() };
+        assert_eq!(convert_search_blocks(input, &strip), "Before\n\n\n\nAfter");
+        assert_eq!(convert_search_blocks(input, &SearchConfig::default()), input);
+
+        let example = "````md\n```query\ntag:#recipe\n```\n````";
+        assert_eq!(convert_search_blocks(example, &strip), example);
+    }
+}
@@ -0,0 +1,217 @@
+//! Conversion of Obsidian `#tags`.
+
+use crate::book;
+use crate::config::{TagConfig, TagMode};
+use crate::markdown;
+use crate::paths;
+use regex::Regex;
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+
+/// A tag with the whitespace (or line start) before it in group 1 and the
+/// tag in group 2.
+///
+/// Like in Obsidian, tags consist of letters, digits, `_`, `-` and `/` and
+/// must not be purely numeric, so `#1` is no tag.
+static TAG: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"(^|\s)#([\p{L}\p{N}_/-]*[\p{L}_/-][\p{L}\p{N}_/-]*)").unwrap());
+
+
+/// Get the path of a tag's page.
+///
+/// Tags are case-insensitive, nested tags (`project/alpha`) get nested pages.
+///
+/// # Arguments
+/// - `tag` - The tag without `#`.
+/// - `config` - The tag settings.
+///
+/// # Returns
+/// The page path relative to the src directory.
+pub fn tag_page(tag: &str, config: &TagConfig) -> String {
+    format!("{}/{}.md", config.dir.trim_matches('/'), tag.trim_matches('/').to_lowercase())
+}
+
+
+/// Convert all tags outside of code according to the configured mode.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `source` - The path of the chapter, used to make tag page links relative.
+/// - `config` - The tag settings.
+///
+/// # Returns
+/// The content with converted tags.
+pub fn convert_tags(content: &str, source: Option<&str>, config: &TagConfig) -> String {
+    if config.mode == TagMode::Keep {
+        return content.to_string();
+    }
+
+    markdown::map_text(content, |text| {
+        TAG.replace_all(text, |caps: &regex::Captures| {
+            let (prefix, tag) = (&caps[1], &caps[2]);
+            match config.mode {
+                TagMode::Strip | TagMode::Keep => String::new(),
+                TagMode::Badge => format!("{}<span class=\"obsidian-tag\">#{}</span>", prefix, tag),
+                TagMode::Link => {
+                    // Raw HTML links aren't rewritten by mdBook, so link the rendered page
+                    let page = tag_page(tag, config);
+                    let href = paths::relative(source.unwrap_or_default(), &page);
+                    let href = href.strip_suffix(".md").unwrap_or(&href);
+                    format!("{}<a class=\"obsidian-tag\" href=\"{}.html\">#{}</a>", prefix, href, tag)
+                }
+            }
+        })
+        .into_owned()
+    })
+}
+
+
+/// Find all tags outside of code.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+///
+/// # Returns
+/// The tags without `#`, in order of appearance.
+pub fn find_tags(content: &str) -> Vec<String> {
+    let mut tags = Vec::new();
+    markdown::map_text(content, |text| {
+        tags.extend(TAG.captures_iter(text).map(|caps| caps[2].trim_end_matches('/').to_string()));
+        String::new()
+    });
+    tags
+}
+
+
+/// Render the page of a tag.
+///
+/// # Arguments
+/// - `tag` - The tag as first written in the book.
+/// - `page` - The path of the tag page.
+/// - `chapters` - The names and paths of the chapters with the tag.
+///
+/// # Returns
+/// The Markdown content of the page.
+fn render_tag_page(tag: &str, page: &str, chapters: &[(String, String)]) -> String {
+    let mut content = format!("# {}\n\n", tag);
+    for (name, path) in chapters {
+        content.push_str(&format!("- [{}]({})\n", name, paths::percent_encode(&paths::relative(page, path))));
+    }
+    content
+}
+
+
+/// Append a page per tag listing the chapters that use the tag.
+///
+/// Pages are only generated if enabled or tags are rendered as links. The
+/// page of a tag also lists the chapters with nested tags, so `#project`
+/// includes chapters tagged `#project/alpha`.
+///
+/// # Arguments
+/// - `book` - The JSON value representing the book.
+/// - `config` - The tag settings.
+/// - `excluded` - Decides by source path which chapters aren't listed.
+pub fn add_tag_chapters(book: &mut Value, config: &TagConfig, excluded: &impl Fn(&str) -> bool) {
+    if !config.pages && config.mode != TagMode::Link {
+        return;
+    }
+
+    // Tags are case-insensitive and shown as first written
+    let mut tags: BTreeMap<String, (String, Vec<(String, String)>)> = BTreeMap::new();
+    for chapter in book::chapters(book) {
+        let (Some(name), Some(path)) = (chapter["name"].as_str(), chapter["path"].as_str()) else {
+            continue;
+        };
+        if chapter["source_path"].as_str().is_some_and(excluded) {
+            continue;
+        }
+        for tag in find_tags(chapter["content"].as_str().unwrap_or_default()) {
+            let entry = tags.entry(tag.to_lowercase()).or_insert_with(|| (tag, Vec::new()));
+            let chapter = (name.to_string(), path.to_string());
+            if !entry.1.contains(&chapter) {
+                entry.1.push(chapter);
+            }
+        }
+    }
+    if tags.is_empty() {
+        return;
+    }
+
+    let pages: Vec<(String, String, String)> = tags
+        .iter()
+        .map(|(key, (tag, _))| {
+            let mut chapters: Vec<(String, String)> = Vec::new();
+            for (other, (_, tagged)) in &tags {
+                if other != key && !other.starts_with(&format!("{}/", key)) {
+                    continue;
+                }
+                for chapter in tagged {
+                    if !chapters.contains(chapter) {
+                        chapters.push(chapter.clone());
+                    }
+                }
+            }
+            let page = tag_page(tag, config);
+            let content = render_tag_page(tag, &page, &chapters);
+            (format!("#{}", tag), page, content)
+        })
+        .collect();
+
+    if !config.part.is_empty() {
+        book::push_part(book, &config.part);
+    }
+    for (name, page, content) in pages {
+        book::push_chapter(book, &name, &page, content);
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_mode(mode: TagMode) -> TagConfig {
+        TagConfig { mode, ..Default::default() }
+    }
+
+    #[test]
+    fn test_convert_tags() {
+        let input = "#todo Call #Project/Alpha about issue #42, see [[Note#Heading]] and `#code`";
+        assert_eq!(
+            convert_tags(input, None, &with_mode(TagMode::Badge)),
+            "<span class=\"obsidian-tag\">#todo</span> Call <span class=\"obsidian-tag\">#Project/Alpha</span> \
+             about issue #42, see [[Note#Heading]] and `#code`"
+        );
+        assert_eq!(convert_tags("Text #tag\n# Heading", None, &with_mode(TagMode::Strip)), "Text\n# Heading");
+        assert_eq!(convert_tags("#tag", None, &TagConfig::default()), "#tag");
+    }
+
+    #[test]
+    fn test_tag_links() {
+        assert_eq!(
+            convert_tags("See #Project/Alpha", Some("guide/intro.md"), &with_mode(TagMode::Link)),
+            "See <a class=\"obsidian-tag\" href=\"../tags/project/alpha.html\">#Project/Alpha</a>"
+        );
+    }
+
+    #[test]
+    fn test_tag_chapters() {
+        let mut book = serde_json::json!({ "items": [
+            { "Chapter": { "name": "Intro", "path": "intro.md", "content": "#Project and #project/alpha", "sub_items": [
+                { "Chapter": { "name": "Setup", "path": "guide/setup.md", "content": "#project/alpha `#code`", "sub_items": [] } },
+                { "Chapter": { "name": "Daily", "path": "Templates/Daily.md", "source_path": "Templates/Daily.md", "content": "#project", "sub_items": [] } }
+            ] } },
+        ] });
+        add_tag_chapters(&mut book, &TagConfig { pages: true, ..Default::default() }, &|path| path.starts_with("Templates/"));
+
+        assert_eq!(book["items"][1]["PartTitle"], "Tags");
+        let pages = book::chapters(&book);
+        assert_eq!(pages[3]["name"], "#Project");
+        assert_eq!(pages[3]["path"], "tags/project.md");
+        assert_eq!(pages[3]["content"], "# Project\n\n- [Intro](../intro.md)\n- [Setup](../guide/setup.md)\n");
+        assert_eq!(pages[4]["path"], "tags/project/alpha.md");
+        assert_eq!(pages[4]["content"], "# project/alpha\n\n- [Intro](../../intro.md)\n- [Setup](../../guide/setup.md)\n");
+        assert_eq!(pages.len(), 5);
+    }
+}
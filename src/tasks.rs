@@ -0,0 +1,133 @@
+//! Conversion of Obsidian's extended task statuses (`- [-]`, `- [/]`, ...).
+
+use crate::config::TaskConfig;
+use crate::markdown::FenceTracker;
+use regex::{Captures, Regex};
+use std::sync::LazyLock;
+
+
+/// A task list item with the marker in group 1, the status in group 2 and the
+/// separator after the checkbox in group 3.
+static TASK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^((?:[ \t]*>)*[ \t]*(?:[-*+]|\d+[.)])[ \t]+)\[([^ xX\]])\]([ \t]|$)").unwrap());
+
+
+/// The statuses supported by common Obsidian themes and their names.
+const STATUSES: &[(char, &str)] = &[
+    ('-', "cancelled"),
+    ('/', "in-progress"),
+    ('>', "forwarded"),
+    ('<', "scheduled"),
+    ('?', "question"),
+    ('!', "important"),
+    ('*', "star"),
+    ('"', "quote"),
+    ('l', "location"),
+    ('b', "bookmark"),
+    ('i', "information"),
+    ('S', "savings"),
+    ('I', "idea"),
+    ('p', "pro"),
+    ('c', "con"),
+    ('f', "fire"),
+    ('k', "key"),
+    ('w', "win"),
+    ('u', "up"),
+    ('d', "down"),
+];
+
+
+/// Get the name of a task status, used in its CSS class.
+///
+/// # Arguments
+/// - `status` - The character between the brackets.
+/// - `config` - The task settings.
+///
+/// # Returns
+/// The configured or built-in name, or `other` for unknown statuses.
+fn status_name(status: char, config: &TaskConfig) -> String {
+    if let Some(name) = config.names.get(&status.to_string()) {
+        return name.clone();
+    }
+    STATUSES
+        .iter()
+        .find(|(c, _)| *c == status)
+        .map_or_else(|| "other".to_string(), |(_, name)| name.to_string())
+}
+
+
+/// Replace task markers other than `[ ]` and `[x]` with checked checkboxes.
+///
+/// mdBook only knows the standard markers and renders the others as text.
+/// The checkbox gets the class `task-{name}` and a `data-task` attribute
+/// like in Obsidian, so theme CSS can style each status.
+///
+/// # Arguments
+/// - `content` - The chapter content.
+/// - `config` - The task settings.
+///
+/// # Returns
+/// The content with converted tasks.
+pub fn convert_tasks(content: &str, config: &TaskConfig) -> String {
+    if !config.statuses {
+        return content.to_string();
+    }
+
+    let mut fences = FenceTracker::default();
+    let lines: Vec<String> = content
+        .split('\n')
+        .map(|line| match fences.is_code(line) {
+            true => line.to_string(),
+            false => TASK
+                .replace(line, |caps: &Captures| {
+                    let status = caps[2].chars().next().unwrap_or_default();
+                    let attribute = match status {
+                        '"' => "&quot;".to_string(),
+                        '<' => "&lt;".to_string(),
+                        '>' => "&gt;".to_string(),
+                        '&' => "&amp;".to_string(),
+                        _ => status.to_string(),
+                    };
+                    format!(
+                        "{}<input type=\"checkbox\" class=\"task task-{}\" data-task=\"{}\" checked disabled>{}",
+                        &caps[1],
+                        status_name(status, config),
+                        attribute,
+                        &caps[3]
+                    )
+                })
+                .into_owned(),
+        })
+        .collect();
+    lines.join("\n")
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_tasks() {
+        let input = "- [ ] open\n- [x] done\n- [-] dropped\n  1. [>] moved\n> - [/] started\n`- [?] code`\n```\n- [!] code\n```";
+        assert_eq!(
+            convert_tasks(input, &TaskConfig::default()),
+            "- [ ] open\n- [x] done\n\
+             - <input type=\"checkbox\" class=\"task task-cancelled\" data-task=\"-\" checked disabled> dropped\n  \
+             1. <input type=\"checkbox\" class=\"task task-forwarded\" data-task=\"&gt;\" checked disabled> moved\n\
+             > - <input type=\"checkbox\" class=\"task task-in-progress\" data-task=\"/\" checked disabled> started\n\
+             `- [?] code`\n```\n- [!] code\n```"
+        );
+        let disabled = TaskConfig { statuses: false, ..Default::default() };
+        assert_eq!(convert_tasks(input, &disabled), input);
+    }
+
+    #[test]
+    fn test_status_names() {
+        let mut config = TaskConfig::default();
+        config.names.insert("R".to_string(), "review".to_string());
+        assert_eq!(status_name('R', &config), "review");
+        assert_eq!(status_name('?', &config), "question");
+        assert_eq!(status_name('Z', &config), "other");
+    }
+}
@@ -0,0 +1,932 @@
+//! In-memory index of the notes and attachments in the book's source directory.
+
+use crate::anchors::{self, Heading};
+use crate::book;
+use crate::config::{AmbiguityPolicy, AnchorConfig};
+use crate::dataview::Page;
+use crate::frontmatter::{self, Frontmatter};
+use crate::markdown::{self, FenceTracker};
+use crate::paths;
+use anyhow::Result;
+use regex::Regex;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+use unicode_normalization::UnicodeNormalization;
+
+
+/// Bring a name into the composed Unicode form (NFC), so `Ü` written as `U`
+/// and a combining diaeresis, as in file names from macOS, matches `Ü`.
+///
+/// # Arguments
+/// - `text` - The name or path.
+///
+/// # Returns
+/// The composed text.
+fn nfc(text: &str) -> String {
+    text.nfc().collect()
+}
+
+
+/// Count the single-character edits turning one text into another.
+///
+/// # Arguments
+/// - `a` - The first text.
+/// - `b` - The second text.
+///
+/// # Returns
+/// The Levenshtein distance of the texts.
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substituted = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substituted.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+    row[b.len()]
+}
+
+
+/// Find a path equal to `name`, or else the first path ending in `/name`.
+///
+/// Paths differing in case only match if no path matches exactly. Paths are
+/// compared in their composed Unicode form.
+///
+/// # Arguments
+/// - `paths` - The candidate paths.
+/// - `name` - The file name or path to look for.
+///
+/// # Returns
+/// The matching path, if any.
+fn find_by_name<'a>(paths: impl Iterator<Item = &'a str> + Clone, name: &str) -> Option<&'a str> {
+    let key = |path: &str| nfc(&format!("/{}", path));
+    let suffix = key(name);
+    let lower = suffix.to_lowercase();
+    paths
+        .clone()
+        .find(|p| key(p) == suffix)
+        .or_else(|| paths.clone().find(|p| key(p).ends_with(&suffix)))
+        .or_else(|| paths.clone().find(|p| key(p).to_lowercase() == lower))
+        .or_else(|| paths.clone().find(|p| key(p).to_lowercase().ends_with(&lower)))
+}
+
+
+/// Get the frontmatter `title` of a note.
+///
+/// # Arguments
+/// - `content` - The note content.
+///
+/// # Returns
+/// The title, if the frontmatter has one.
+fn yaml_title(content: &str) -> Option<String> {
+    let yaml = frontmatter::split(content).0?;
+    Frontmatter::parse(yaml).get("title").and_then(|t| t.first().cloned())
+}
+
+
+/// Get the first H1 heading of a note, ignoring code blocks.
+///
+/// # Arguments
+/// - `content` - The note content.
+///
+/// # Returns
+/// The heading text, if the note has one.
+fn first_heading(content: &str) -> Option<String> {
+    let mut fences = FenceTracker::default();
+    frontmatter::split(content)
+        .1
+        .lines()
+        .filter(|line| !fences.is_code(line))
+        .find_map(|line| markdown::heading(line).filter(|(level, _)| *level == 1))
+        .map(|(_, title)| title.to_string())
+}
+
+
+/// The files of a vault with paths relative to the vault root.
+///
+/// Markdown notes are kept with their content, all other files (attachments)
+/// only by path. Paths always use forward slashes, e.g. `guide/intro.md`.
+///
+/// Attachments from directories outside the vault root are known by the path
+/// they get once copied into the vault, and are marked as used when looked up.
+#[derive(Debug, Default)]
+pub struct Vault {
+    notes: HashMap<String, String>,
+    frontmatter_titles: HashMap<String, String>,
+    first_headings: HashMap<String, String>,
+    names: HashMap<String, Vec<String>>,
+    aliases: HashMap<String, String>,
+    assets: Vec<String>,
+    external: Vec<(String, PathBuf)>,
+    chapters: HashMap<String, String>,
+    chapter_names: Vec<(String, String)>,
+    drafts: Vec<String>,
+    used: RefCell<BTreeSet<String>>,
+    ambiguity: AmbiguityPolicy,
+    folder_notes: Vec<String>,
+    languages: Vec<String>,
+    uid: Option<Regex>,
+    uids: HashMap<String, String>,
+    ambiguous: RefCell<BTreeSet<(String, String)>>,
+    unresolved: RefCell<BTreeSet<(String, String)>>,
+    headings: RefCell<HashMap<String, Rc<Vec<Heading>>>>,
+    pages: RefCell<Option<Rc<Vec<Page>>>>,
+}
+
+impl Vault {
+    /// Load all files below `root`.
+    ///
+    /// Hidden files and directories such as `.obsidian` are skipped.
+    /// A missing root directory yields an empty vault.
+    ///
+    /// # Arguments
+    /// - `root` - The directory to scan.
+    ///
+    /// # Returns
+    /// The loaded vault or an I/O error.
+    pub fn load(root: &Path) -> Result<Vault> {
+        let mut vault = Vault::default();
+        if root.is_dir() {
+            vault.scan(root, root)?;
+        }
+        vault.assets.sort();
+        vault.index();
+        Ok(vault)
+    }
+
+    /// Build a vault from `(path, content)` pairs without touching the file system.
+    ///
+    /// # Arguments
+    /// - `notes` - The notes with paths relative to the vault root.
+    ///
+    /// # Returns
+    /// A vault containing exactly the given notes.
+    #[cfg(test)]
+    pub fn from_notes(notes: &[(&str, &str)]) -> Vault {
+        let mut vault = Vault {
+            notes: notes
+                .iter()
+                .map(|(path, content)| (path.to_string(), content.to_string()))
+                .collect(),
+            ..Default::default()
+        };
+        vault.index();
+        vault
+    }
+
+    /// Add attachments to a vault built with [`Vault::from_notes`].
+    ///
+    /// # Arguments
+    /// - `assets` - The attachment paths relative to the vault root.
+    ///
+    /// # Returns
+    /// The vault including the attachments.
+    #[cfg(test)]
+    pub fn with_assets(mut self, assets: &[&str]) -> Vault {
+        self.assets.extend(assets.iter().map(|a| a.to_string()));
+        self.assets.sort();
+        self
+    }
+
+    /// Remove the notes and attachments for which `excluded` returns true.
+    ///
+    /// # Arguments
+    /// - `excluded` - Decides by the path relative to the vault root.
+    pub fn exclude(&mut self, excluded: impl Fn(&str) -> bool) {
+        self.notes.retain(|path, _| !excluded(path));
+        self.assets.retain(|path| !excluded(path));
+        self.index();
+    }
+
+    /// Add the attachments of a directory outside the vault root.
+    ///
+    /// The attachments are resolved as if they were located below `prefix`
+    /// in the vault. A missing directory is reported and skipped.
+    ///
+    /// # Arguments
+    /// - `dir` - The directory to scan.
+    /// - `prefix` - The vault directory the attachments are copied to.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure.
+    pub fn load_external(&mut self, dir: &Path, prefix: &str) -> Result<()> {
+        if !dir.is_dir() {
+            crate::warn(&format!("Attachment directory '{}' not found", dir.display()));
+            return Ok(());
+        }
+
+        let mut files = Vault::default();
+        files.scan(dir, dir)?;
+        let prefix = prefix.trim_matches('/');
+        for asset in files.assets {
+            let path = if prefix.is_empty() { asset.clone() } else { format!("{}/{}", prefix, asset) };
+            self.external.push((path, dir.join(&asset)));
+        }
+        self.external.sort();
+        Ok(())
+    }
+
+    /// Build the indexes derived from the paths and content of the notes.
+    ///
+    /// Notes sharing a file name are ordered by their path, shortest first.
+    /// If several notes share an alias, the first one by path wins.
+    fn index(&mut self) {
+        self.frontmatter_titles = self
+            .notes
+            .iter()
+            .filter_map(|(path, content)| Some((path.clone(), yaml_title(content)?)))
+            .collect();
+        self.first_headings = self
+            .notes
+            .iter()
+            .filter_map(|(path, content)| Some((path.clone(), first_heading(content)?)))
+            .collect();
+
+        let mut paths: Vec<&String> = self.notes.keys().collect();
+        paths.sort_by_key(|path| (path.matches('/').count(), *path));
+        self.names.clear();
+        for path in &paths {
+            let name = paths::strip_note_extension(path.rsplit('/').next().unwrap_or(path));
+            self.names.entry(nfc(&name.to_lowercase())).or_default().push(path.to_string());
+        }
+
+        paths.sort();
+        self.aliases.clear();
+        for path in paths {
+            let Some(yaml) = frontmatter::split(&self.notes[path]).0 else { continue };
+            let frontmatter = Frontmatter::parse(yaml);
+            let aliases = ["aliases", "alias"].iter().filter_map(|key| frontmatter.get(key)).flatten();
+            for alias in aliases {
+                self.aliases.entry(nfc(&alias.to_lowercase())).or_insert_with(|| path.clone());
+            }
+        }
+        self.index_uids();
+    }
+
+    /// Recursively add the files of `dir` to the vault.
+    ///
+    /// # Arguments
+    /// - `root` - The vault root used to compute relative paths.
+    /// - `dir` - The directory to scan.
+    ///
+    /// # Returns
+    /// A Result indicating success or failure.
+    fn scan(&mut self, root: &Path, dir: &Path) -> Result<()> {
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_name().to_string_lossy().starts_with('.') {
+                continue;
+            }
+
+            let path = entry.path();
+            let relative = path.strip_prefix(root)?.to_string_lossy().replace('\\', "/");
+            if path.is_dir() {
+                self.scan(root, &path)?;
+            } else if paths::is_note_file(&relative) {
+                self.notes.insert(relative, fs::read_to_string(&path)?);
+            } else {
+                self.assets.push(relative);
+            }
+        }
+        Ok(())
+    }
+
+    /// Set which note links get when several notes share their name.
+    ///
+    /// # Arguments
+    /// - `policy` - The ambiguity policy.
+    pub fn set_ambiguity(&mut self, policy: AmbiguityPolicy) {
+        self.ambiguity = policy;
+    }
+
+    /// Set the names links to a folder resolve to, e.g. `{name}` or `index`.
+    ///
+    /// # Arguments
+    /// - `names` - The note names inside the folder, where `{name}` is the folder's name.
+    pub fn set_folder_notes(&mut self, names: &[String]) {
+        self.folder_notes = names.to_vec();
+    }
+
+    /// Set the language folders of a translated book, the default language first.
+    ///
+    /// # Arguments
+    /// - `languages` - The folder names, e.g. `en` and `de`.
+    pub fn set_languages(&mut self, languages: &[String]) {
+        self.languages = languages.iter().map(|l| l.trim_matches('/').to_string()).collect();
+    }
+
+    /// Set the pattern of unique IDs that link to the notes whose file name starts with them.
+    ///
+    /// # Arguments
+    /// - `pattern` - The regex an ID matches, e.g. `^\d{12}$`.
+    pub fn set_uid_pattern(&mut self, pattern: Option<Regex>) {
+        self.uid = pattern;
+        self.index_uids();
+    }
+
+    /// Index the notes by the unique IDs their file names start with.
+    ///
+    /// An ID is a start of the file name that matches the pattern and isn't
+    /// followed by a letter or digit. Notes closest to the vault root win.
+    fn index_uids(&mut self) {
+        self.uids.clear();
+        let Some(re) = &self.uid else { return };
+        let mut paths: Vec<&String> = self.notes.keys().collect();
+        paths.sort_by_key(|path| (path.matches('/').count(), *path));
+        for path in paths {
+            let file = path.rsplit('/').next().unwrap_or(path);
+            let ends = file.char_indices().filter(|(_, c)| !c.is_alphanumeric()).map(|(i, _)| i);
+            for end in ends.chain(std::iter::once(file.len())).filter(|&end| end > 0) {
+                if re.is_match(&file[..end]) {
+                    self.uids.entry(file[..end].to_string()).or_insert_with(|| path.to_string());
+                }
+            }
+        }
+    }
+
+    /// Find the note whose file name starts with a unique ID.
+    ///
+    /// # Examples
+    /// `202301011230` matches `202301011230 Some Title.md`
+    ///
+    /// # Arguments
+    /// - `uid` - The ID as written in the wikilink.
+    ///
+    /// # Returns
+    /// The path of the note closest to the vault root, if the name is an ID and a note has it.
+    fn find_uid(&self, uid: &str) -> Option<&str> {
+        self.uids.get(uid).map(String::as_str)
+    }
+
+    /// Get the display text of a link to a note by its unique ID.
+    ///
+    /// # Arguments
+    /// - `uid` - The ID as written in the wikilink.
+    /// - `path` - The path of the note the link resolves to.
+    ///
+    /// # Returns
+    /// The note's frontmatter `title` or else the rest of its file name, if
+    /// the link is an ID the note's file name starts with.
+    pub fn uid_title(&self, uid: &str, path: &str) -> Option<String> {
+        let file = path.rsplit('/').next().unwrap_or(path);
+        let rest = paths::strip_note_extension(file).strip_prefix(uid)?;
+        let rest = rest.trim_start_matches(|c: char| !c.is_alphanumeric());
+        if rest.is_empty() || !self.uid.as_ref().is_some_and(|re| re.is_match(uid)) {
+            return None;
+        }
+        Some(self.frontmatter_title(path).unwrap_or(rest).to_string())
+    }
+
+    /// Keep the matches in the language of the linking file, or else in the default language.
+    ///
+    /// # Arguments
+    /// - `matches` - The paths of the notes matching a link.
+    /// - `from` - The path of the file containing the link, if any.
+    ///
+    /// # Returns
+    /// The matches in the first language that has any, or all matches.
+    fn in_language<'a>(&self, matches: Vec<&'a str>, from: Option<&str>) -> Vec<&'a str> {
+        let current = from
+            .and_then(|from| from.split('/').next())
+            .and_then(|folder| self.languages.iter().find(|l| *l == folder));
+        for language in current.into_iter().chain(self.languages.first()) {
+            let prefix = format!("{}/", language);
+            let scoped: Vec<&str> = matches.iter().copied().filter(|p| p.starts_with(&prefix)).collect();
+            if !scoped.is_empty() {
+                return scoped;
+            }
+        }
+        matches
+    }
+
+    /// Resolve a wikilink name to the path of a note in the vault.
+    ///
+    /// `note` and `note.md` both refer to `note.md` relative to the vault root,
+    /// or to `note.markdown` if there is no `note.md`.
+    /// Like in Obsidian, a name without the full path matches a note of that
+    /// name in any folder, preferring the one closest to the vault root, and
+    /// names match notes in a different case if none matches exactly. Names
+    /// match regardless of their Unicode normalization form. Names of folders
+    /// resolve to the folder's note, if folder notes are set.
+    /// In translated books, notes in the language of the linking file are
+    /// preferred, then those in the default language. Unique IDs match the
+    /// note whose file name starts with them, if an ID pattern is set.
+    /// Names that aren't a note are looked up in the notes' frontmatter
+    /// `aliases`, ignoring case.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    ///
+    /// # Returns
+    /// The path of the note relative to the vault root, if the note exists.
+    pub fn path(&self, name: &str) -> Option<&str> {
+        self.resolve(name, None)
+    }
+
+    /// Resolve a wikilink name written in the file `from` to the path of a note.
+    ///
+    /// Like [`Vault::path`], but if several notes share the name, the
+    /// ambiguity policy picks one of them. Every ambiguity is reported once.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    /// - `from` - The path of the file containing the link, if any.
+    ///
+    /// # Returns
+    /// The path of the note relative to the vault root, if the note exists.
+    pub fn resolve(&self, name: &str, from: Option<&str>) -> Option<&str> {
+        let name = name.trim().replace('\\', "/");
+        let name = name.trim_end_matches('/');
+        self.find(name, from)
+            .or_else(|| {
+                let folder = name.rsplit('/').next().unwrap_or(name);
+                self.folder_notes
+                    .iter()
+                    .find_map(|note| self.find(&format!("{}/{}", name, note.replace("{name}", folder)), from))
+            })
+            .or_else(|| self.find_uid(name))
+            .or_else(|| self.aliases.get(&nfc(&name.to_lowercase())).map(String::as_str))
+    }
+
+    /// Find the note with the given name or path, without folder notes and aliases.
+    ///
+    /// # Arguments
+    /// - `name` - The note name with forward slashes.
+    /// - `from` - The path of the file containing the link, if any.
+    ///
+    /// # Returns
+    /// The path of the note relative to the vault root, if the note exists.
+    fn find(&self, name: &str, from: Option<&str>) -> Option<&str> {
+        let stem = paths::strip_note_extension(name);
+        let exact = [name.to_string(), format!("{}.md", stem), format!("{}.markdown", stem)];
+        if let Some((key, _)) = exact.iter().find_map(|path| self.notes.get_key_value(path)) {
+            return Some(key);
+        }
+
+        // Matches in the exact case win over those in a different case
+        let key = |path: &str| nfc(&format!("/{}", paths::strip_note_extension(path)));
+        let suffix = key(stem);
+        let file_name = stem.rsplit('/').next().unwrap_or(stem);
+        let matches: Vec<&str> = self
+            .names
+            .get(&nfc(&file_name.to_lowercase()))
+            .into_iter()
+            .flatten()
+            .filter(|p| key(p).to_lowercase().ends_with(&suffix.to_lowercase()))
+            .map(String::as_str)
+            .collect();
+        let matches = self.in_language(matches, from);
+        let exact: Vec<&str> = matches.iter().copied().filter(|p| key(p).ends_with(&suffix)).collect();
+        let candidates = if exact.is_empty() { matches } else { exact };
+        let chosen = match (self.ambiguity, from) {
+            (AmbiguityPolicy::Closest, Some(from)) => {
+                candidates.iter().min_by_key(|p| paths::relative(from, p).split('/').count()).copied()
+            }
+            _ => candidates.first().copied(),
+        };
+
+        if let Some(chosen) = chosen.filter(|_| candidates.len() > 1)
+            && self.ambiguous.borrow_mut().insert((name.to_string(), chosen.to_string()))
+        {
+            crate::warn(&format!("Link '{}' matches several notes ({}), using '{}'", name, candidates.join(", "), chosen));
+        }
+        chosen
+    }
+
+    /// Learn where the chapters of the book are published.
+    ///
+    /// mdBook publishes a chapter at its `path`, which can differ from the
+    /// note's `source_path`. Generated chapters have no note at all, draft
+    /// chapters have no path and are only known by name.
+    ///
+    /// # Arguments
+    /// - `book` - The JSON value representing the book.
+    pub fn add_chapters(&mut self, book: &Value) {
+        for chapter in book::chapters(book) {
+            let Some(path) = chapter.get("path").and_then(Value::as_str) else {
+                if let Some(name) = chapter.get("name").and_then(Value::as_str) {
+                    self.drafts.push(name.to_string());
+                }
+                continue;
+            };
+            if let Some(source) = chapter.get("source_path").and_then(Value::as_str) {
+                self.chapters.insert(source.replace('\\', "/"), path.to_string());
+            }
+            let name = chapter.get("name").and_then(Value::as_str).unwrap_or_default();
+            self.chapter_names.push((name.to_string(), path.to_string()));
+        }
+    }
+
+    /// Find a draft chapter by its name, ignoring case.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    ///
+    /// # Returns
+    /// The name of the draft chapter, if there is one.
+    pub fn draft(&self, name: &str) -> Option<&str> {
+        let name = nfc(&name.trim().to_lowercase());
+        let name = name.rsplit('/').next().unwrap_or(&name).to_string();
+        self.drafts.iter().find(|draft| nfc(&draft.to_lowercase()) == name).map(String::as_str)
+    }
+
+    /// Get the path a note is published at.
+    ///
+    /// # Arguments
+    /// - `path` - The path of the note relative to the vault root.
+    ///
+    /// # Returns
+    /// The path of the note's chapter, or the note's path if it isn't a chapter.
+    pub fn chapter_path<'a>(&'a self, path: &'a str) -> &'a str {
+        self.chapters.get(path).map_or(path, String::as_str)
+    }
+
+    /// Find a chapter of the book that isn't a note by its path or name.
+    ///
+    /// Like note names, the path may leave out leading folders. Paths and
+    /// names are compared ignoring case.
+    ///
+    /// # Arguments
+    /// - `name` - The name as written in the wikilink.
+    ///
+    /// # Returns
+    /// The path of the chapter, if there is one.
+    pub fn chapter(&self, name: &str) -> Option<&str> {
+        let name = nfc(paths::strip_note_extension(name.trim())).to_lowercase();
+        let suffix = format!("/{}", name);
+        let stem = |path: &str| nfc(&format!("/{}", paths::strip_note_extension(path))).to_lowercase();
+        self.chapter_names
+            .iter()
+            .find(|(_, path)| stem(path).ends_with(&suffix))
+            .or_else(|| self.chapter_names.iter().find(|(title, _)| nfc(title).to_lowercase() == name))
+            .map(|(_, path)| path.as_str())
+    }
+
+    /// Fail if links were ambiguous and the ambiguity policy is `error`.
+    ///
+    /// # Returns
+    /// An error naming the ambiguous links, if there are any.
+    pub fn check_ambiguity(&self) -> Result<()> {
+        let ambiguous = self.ambiguous.borrow();
+        if self.ambiguity != AmbiguityPolicy::Error || ambiguous.is_empty() {
+            return Ok(());
+        }
+        let names: BTreeSet<&str> = ambiguous.iter().map(|(name, _)| name.as_str()).collect();
+        let names: Vec<&str> = names.into_iter().collect();
+        anyhow::bail!("Ambiguous links to several notes: {}", names.join(", "))
+    }
+
+    /// Remember a link whose target doesn't exist.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    /// - `source` - The path of the chapter containing the link.
+    pub fn mark_unresolved(&self, name: &str, source: &str) {
+        self.unresolved.borrow_mut().insert((name.to_string(), source.to_string()));
+    }
+
+    /// List the links marked as unresolved so far.
+    ///
+    /// # Returns
+    /// Pairs of the note name and the chapter, sorted by name.
+    pub fn unresolved_links(&self) -> Vec<(String, String)> {
+        self.unresolved.borrow().iter().cloned().collect()
+    }
+
+    /// Suggest notes for a name that doesn't match any.
+    ///
+    /// Names without folders are compared to the file names and aliases of
+    /// the notes, names with folders to their paths, ignoring case.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    ///
+    /// # Returns
+    /// Up to three close note names, closest first.
+    pub fn suggestions(&self, name: &str) -> Vec<String> {
+        let name = nfc(&paths::strip_note_extension(name.trim()).to_lowercase());
+        let max = (name.chars().count() / 3).max(1);
+        let notes = self.notes.keys().map(|path| paths::strip_note_extension(path).to_string());
+        let candidates = notes.chain(self.aliases.keys().cloned()).map(|candidate| {
+            let compared = match name.contains('/') {
+                true => candidate.as_str(),
+                false => candidate.rsplit('/').next().unwrap_or(&candidate),
+            };
+            (edit_distance(&name, &nfc(&compared.to_lowercase())), candidate)
+        });
+
+        let mut close: Vec<(usize, String)> = candidates.filter(|(distance, _)| *distance <= max).collect();
+        close.sort();
+        close.dedup_by(|a, b| a.1 == b.1);
+        close.into_iter().take(3).map(|(_, candidate)| candidate).collect()
+    }
+
+    /// Returns true if there is a note at exactly the given path.
+    ///
+    /// # Arguments
+    /// - `path` - The path relative to the vault root, e.g. `guide/setup.md`.
+    ///
+    /// # Returns
+    /// Whether the note exists.
+    pub fn is_note(&self, path: &str) -> bool {
+        self.notes.contains_key(path)
+    }
+
+    /// Look up the content of a note by its wikilink name.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    ///
+    /// # Returns
+    /// The note content, if the note exists.
+    pub fn note(&self, name: &str) -> Option<&str> {
+        self.path(name).and_then(|path| self.notes.get(path)).map(String::as_str)
+    }
+
+    /// Get the headings of a note and their IDs.
+    ///
+    /// They are found once per note, however many links point to it.
+    ///
+    /// # Arguments
+    /// - `name` - The path or name of the note.
+    /// - `config` - The anchor settings.
+    ///
+    /// # Returns
+    /// The headings in order, if the note exists.
+    pub fn headings(&self, name: &str, config: &AnchorConfig) -> Option<Rc<Vec<Heading>>> {
+        let path = self.path(name)?;
+        if let Some(headings) = self.headings.borrow().get(path) {
+            return Some(Rc::clone(headings));
+        }
+        let headings = Rc::new(anchors::heading_ids(self.notes.get(path)?, config));
+        self.headings.borrow_mut().insert(path.to_string(), Rc::clone(&headings));
+        Some(headings)
+    }
+
+    /// Get the notes as Dataview pages, sorted by path.
+    ///
+    /// Their fields and tags are read once, however many queries the book has.
+    ///
+    /// # Returns
+    /// The pages of all notes.
+    pub fn pages(&self) -> Rc<Vec<Page>> {
+        if let Some(pages) = self.pages.borrow().as_ref() {
+            return Rc::clone(pages);
+        }
+        let pages: Rc<Vec<Page>> = Rc::new(self.notes().into_iter().map(|(path, content)| Page::new(path, content)).collect());
+        *self.pages.borrow_mut() = Some(Rc::clone(&pages));
+        pages
+    }
+
+    /// List all notes, sorted by path.
+    ///
+    /// # Returns
+    /// The paths and contents of the notes.
+    pub fn notes(&self) -> Vec<(&str, &str)> {
+        let mut notes: Vec<(&str, &str)> = self.notes.iter().map(|(p, c)| (p.as_str(), c.as_str())).collect();
+        notes.sort();
+        notes
+    }
+
+    /// Look up the title of a note by its wikilink name.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    ///
+    /// # Returns
+    /// The frontmatter `title` or first H1 of the note, if it has one.
+    pub fn title(&self, name: &str) -> Option<&str> {
+        self.frontmatter_title(name).or_else(|| self.heading(name))
+    }
+
+    /// Look up the frontmatter `title` of a note by its wikilink name.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    ///
+    /// # Returns
+    /// The title, if the note exists and its frontmatter has one.
+    pub fn frontmatter_title(&self, name: &str) -> Option<&str> {
+        self.path(name).and_then(|path| self.frontmatter_titles.get(path)).map(String::as_str)
+    }
+
+    /// Look up the first H1 heading of a note by its wikilink name.
+    ///
+    /// # Arguments
+    /// - `name` - The note name as written in the wikilink.
+    ///
+    /// # Returns
+    /// The heading text, if the note exists and has one.
+    pub fn heading(&self, name: &str) -> Option<&str> {
+        self.path(name).and_then(|path| self.first_headings.get(path)).map(String::as_str)
+    }
+
+    /// Resolve an attachment name to its path in the vault.
+    ///
+    /// Like Obsidian, a bare file name matches the attachment anywhere in the
+    /// vault; an exact path relative to the vault root takes precedence.
+    /// Attachments inside the vault win over external ones.
+    ///
+    /// # Arguments
+    /// - `name` - The attachment as written in the embed, e.g. `logo.png`.
+    ///
+    /// # Returns
+    /// The path of the attachment relative to the vault root, if it exists.
+    pub fn asset(&self, name: &str) -> Option<&str> {
+        let name = name.trim().replace('\\', "/");
+        let name = name.trim_start_matches("./");
+        if let Some(path) = find_by_name(self.assets.iter().map(String::as_str), name) {
+            return Some(path);
+        }
+        let path = find_by_name(self.external.iter().map(|(path, _)| path.as_str()), name)?;
+        self.used.borrow_mut().insert(path.to_string());
+        Some(path)
+    }
+
+    /// List the external attachments that were looked up so far.
+    ///
+    /// # Returns
+    /// Pairs of the vault path and the location of the original file.
+    pub fn used_external_assets(&self) -> Vec<(String, PathBuf)> {
+        let used = self.used.borrow();
+        self.external.iter().filter(|(path, _)| used.contains(path)).cloned().collect()
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_note_lookup() {
+        let vault = Vault::from_notes(&[("intro.md", "Hello"), ("guide/setup.md", "Setup")]);
+        assert_eq!(vault.note("intro"), Some("Hello"));
+        assert_eq!(vault.note("intro.md"), Some("Hello"));
+        assert_eq!(vault.note("guide/setup"), Some("Setup"));
+        assert_eq!(vault.note("missing"), None);
+        assert_eq!(vault.path("guide/setup.md"), Some("guide/setup.md"));
+    }
+
+    #[test]
+    fn test_name_lookup() {
+        let vault = Vault::from_notes(&[("docs/deep/api.md", "Deep"), ("reference/api.md", "API"), ("guide/setup.md", "")]);
+        assert_eq!(vault.path("api"), Some("reference/api.md"));
+        assert_eq!(vault.path("deep/api"), Some("docs/deep/api.md"));
+        assert_eq!(vault.path("setup.md"), Some("guide/setup.md"));
+        assert_eq!(vault.path("ide/setup"), None);
+    }
+
+    #[test]
+    fn test_case_insensitive_lookup() {
+        let vault = Vault::from_notes(&[("README.md", ""), ("Guide/Setup.md", ""), ("guide/setup.md", "")])
+            .with_assets(&["img/Logo.PNG"]);
+        assert_eq!(vault.path("readme"), Some("README.md"));
+        assert_eq!(vault.path("GUIDE/SETUP"), Some("Guide/Setup.md"));
+        assert_eq!(vault.path("Setup"), Some("Guide/Setup.md"));
+        assert_eq!(vault.path("setup"), Some("guide/setup.md"));
+        assert_eq!(vault.asset("logo.png"), Some("img/Logo.PNG"));
+    }
+
+    #[test]
+    fn test_unicode_normalization() {
+        let vault = Vault::from_notes(&[("Notizen/U\u{308}bersicht.md", "NFD")]).with_assets(&["Bilder/Cafe\u{301}.png"]);
+        assert_eq!(vault.note("Übersicht"), Some("NFD"));
+        assert_eq!(vault.note("Notizen/Übersicht"), Some("NFD"));
+        assert_eq!(vault.asset("Café.png"), Some("Bilder/Cafe\u{301}.png"));
+    }
+
+    #[test]
+    fn test_folder_notes() {
+        let mut vault = Vault::from_notes(&[("Projects/index.md", ""), ("Areas/Home/Home.md", ""), ("Areas/Home.md", "")]);
+        assert_eq!(vault.path("Projects"), None);
+
+        vault.set_folder_notes(&["{name}".to_string(), "index".to_string()]);
+        assert_eq!(vault.path("Projects"), Some("Projects/index.md"));
+        assert_eq!(vault.path("Projects/"), Some("Projects/index.md"));
+        assert_eq!(vault.path("Areas/Home"), Some("Areas/Home.md"));
+    }
+
+    #[test]
+    fn test_exclude() {
+        let mut vault = Vault::from_notes(&[("Templates/Setup.md", "Template"), ("guide/setup.md", "Setup")]).with_assets(&["Templates/logo.png"]);
+        vault.exclude(|path| path.starts_with("Templates/"));
+        assert_eq!(vault.note("Setup"), Some("Setup"));
+        assert_eq!(vault.note("Templates/Setup"), None);
+        assert_eq!(vault.asset("logo.png"), None);
+    }
+
+    #[test]
+    fn test_chapters() {
+        let mut vault = Vault::from_notes(&[("notes/setup.md", "")]);
+        vault.add_chapters(&serde_json::json!({ "items": [
+            { "Chapter": { "name": "Setup", "path": "guide/setup.md", "source_path": "notes/setup.md", "sub_items": [] } },
+            { "Chapter": { "name": "All Tags", "path": "tags/index.md", "source_path": null, "sub_items": [] } },
+        ] }));
+        assert_eq!(vault.chapter_path("notes/setup.md"), "guide/setup.md");
+        assert_eq!(vault.chapter_path("other.md"), "other.md");
+        assert_eq!(vault.chapter("tags/index"), Some("tags/index.md"));
+        assert_eq!(vault.chapter("all tags"), Some("tags/index.md"));
+        assert_eq!(vault.chapter("missing"), None);
+    }
+
+    #[test]
+    fn test_suggestions() {
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        let vault = Vault::from_notes(&[
+            ("guide/Installation.md", "---\naliases: [Install guide]\n---\n"),
+            ("guide/Configuration.md", ""),
+            ("Intro.md", ""),
+        ]);
+        assert_eq!(vault.suggestions("Instalation"), vec!["guide/Installation"]);
+        assert_eq!(vault.suggestions("install guid"), vec!["install guide"]);
+        assert_eq!(vault.suggestions("guide/configuraton.md"), vec!["guide/Configuration"]);
+        assert!(vault.suggestions("Roadmap").is_empty());
+    }
+
+    #[test]
+    fn test_languages() {
+        let mut vault = Vault::from_notes(&[
+            ("en/guide/Setup.md", ""),
+            ("de/guide/Setup.md", ""),
+            ("en/FAQ.md", ""),
+            ("fr/FAQ.md", ""),
+        ]);
+        vault.set_languages(&["en".to_string(), "de".to_string(), "fr".to_string()]);
+        assert_eq!(vault.resolve("Setup", Some("de/intro.md")), Some("de/guide/Setup.md"));
+        assert_eq!(vault.resolve("guide/Setup", Some("en/intro.md")), Some("en/guide/Setup.md"));
+        assert_eq!(vault.resolve("FAQ", Some("de/intro.md")), Some("en/FAQ.md"));
+        assert_eq!(vault.resolve("FAQ", Some("fr/intro.md")), Some("fr/FAQ.md"));
+        assert_eq!(vault.resolve("de/guide/Setup", Some("en/intro.md")), Some("de/guide/Setup.md"));
+    }
+
+    #[test]
+    fn test_uids() {
+        let mut vault = Vault::from_notes(&[
+            ("zettel/202301011230 Some Title.md", ""),
+            ("zettel/202301011231-Other.md", "---\ntitle: The other one\n---\n"),
+            ("zettel/2023010112301 Longer.md", ""),
+        ]);
+        assert_eq!(vault.resolve("202301011230", None), None);
+        vault.set_uid_pattern(Some(Regex::new(r"^\d{12}$").unwrap()));
+        assert_eq!(vault.resolve("202301011230", None), Some("zettel/202301011230 Some Title.md"));
+        assert_eq!(vault.uid_title("202301011230", "zettel/202301011230 Some Title.md").as_deref(), Some("Some Title"));
+        assert_eq!(vault.uid_title("202301011231", "zettel/202301011231-Other.md").as_deref(), Some("The other one"));
+        assert_eq!(vault.resolve("2023010112", None), None);
+    }
+
+    #[test]
+    fn test_ambiguity() {
+        let mut vault = Vault::from_notes(&[("guide/setup.md", ""), ("other/setup.md", ""), ("unique.md", "")]);
+        assert_eq!(vault.resolve("setup", Some("other/intro.md")), Some("guide/setup.md"));
+        assert!(vault.check_ambiguity().is_ok());
+
+        vault.set_ambiguity(AmbiguityPolicy::Closest);
+        assert_eq!(vault.resolve("setup", Some("other/intro.md")), Some("other/setup.md"));
+        assert_eq!(vault.resolve("setup", Some("intro.md")), Some("guide/setup.md"));
+
+        vault.set_ambiguity(AmbiguityPolicy::Error);
+        assert!(vault.check_ambiguity().is_err());
+    }
+
+    #[test]
+    fn test_aliases() {
+        let vault = Vault::from_notes(&[
+            ("guide/setup.md", "---\naliases:\n  - Getting Started\n  - Install\n---\nSetup"),
+            ("install.md", "Install"),
+        ]);
+        assert_eq!(vault.path("getting started"), Some("guide/setup.md"));
+        assert_eq!(vault.note("Getting Started"), Some("---\naliases:\n  - Getting Started\n  - Install\n---\nSetup"));
+        assert_eq!(vault.path("install"), Some("install.md"));
+    }
+
+    #[test]
+    fn test_titles() {
+        let vault = Vault::from_notes(&[
+            ("a.md", "---\ntitle: Front Title\n---\n# Heading"),
+            ("b.md", "```\n# code\n```\n## Sub\n# First H1"),
+            ("c.md", "No title"),
+        ]);
+        assert_eq!(vault.title("a"), Some("Front Title"));
+        assert_eq!(vault.title("b"), Some("First H1"));
+        assert_eq!(vault.title("c"), None);
+    }
+
+    #[test]
+    fn test_asset_lookup() {
+        let vault = Vault::default().with_assets(&["attachments/logo.png", "logo.png", "img/chart.svg"]);
+        assert_eq!(vault.asset("logo.png"), Some("logo.png"));
+        assert_eq!(vault.asset("chart.svg"), Some("img/chart.svg"));
+        assert_eq!(vault.asset("attachments/logo.png"), Some("attachments/logo.png"));
+        assert_eq!(vault.asset("img\\chart.svg"), Some("img/chart.svg"));
+        assert_eq!(vault.asset("missing.png"), None);
+    }
+}